@@ -0,0 +1,323 @@
+//! Abstract syntax tree for C(-1) programs, as produced by
+//! [`C1Parser::parse_program`](crate::C1Parser::parse_program).
+//!
+//! The node types mirror the nonterminals of the C1 grammar in `c1-syntax.ebnf`, of which the C(-1)
+//! grammar in `c-1-syntax.ebnf` is a subset. The precedence levels `expr`, `simpexpr`, `term`,
+//! `unary` and `factor` are folded into a single [`Expr`] tree whose shape encodes operator
+//! precedence and left associativity.
+
+use std::fmt;
+use std::ops::Range;
+
+/// Position of a node in the parsed source text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Location {
+    /// 1-based line number
+    pub line: usize,
+    /// 1-based column, counted in characters
+    pub column: usize,
+    /// Byte offset of the first character of the token
+    pub start: usize,
+    /// Byte offset one past the last character of the token
+    pub end: usize,
+}
+
+impl Location {
+    /// Return the byte range of the token this location points at
+    pub fn span(&self) -> Range<usize> {
+        self.start..self.end
+    }
+}
+
+/// program ::= ( functiondefinition | globaldeclaration )* <EOF>
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program {
+    /// Always empty in C(-1)
+    pub globals: Vec<GlobalDeclaration>,
+    pub functions: Vec<FunctionDefinition>,
+}
+
+/// globaldeclaration ::= type <ID> ( "=" constant )? ";"
+#[derive(Debug, Clone, PartialEq)]
+pub struct GlobalDeclaration {
+    pub ty: Type,
+    pub name: Identifier,
+    pub value: Option<Literal>,
+    /// Location of the type keyword
+    pub location: Location,
+}
+
+/// functiondefinition ::= type <ID> "(" ( parameterlist )? ")" "{" statementlist "}"
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionDefinition {
+    pub return_type: Type,
+    pub name: Identifier,
+    /// Always empty in C(-1)
+    pub parameters: Vec<Parameter>,
+    pub body: Vec<Statement>,
+    /// Location of the return type keyword
+    pub location: Location,
+}
+
+/// A parameter of a function definition: type <ID>
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parameter {
+    pub ty: Type,
+    pub name: Identifier,
+}
+
+/// type ::= <KW_BOOLEAN> | <KW_FLOAT> | <KW_INT> | <KW_VOID>
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Type {
+    Bool,
+    Float,
+    Int,
+    Void,
+}
+
+/// An occurrence of an <ID> token
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Identifier {
+    pub name: String,
+    pub location: Location,
+}
+
+/// functioncall ::= <ID> "(" ( assignment ( "," assignment )* )? ")"
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionCall {
+    pub name: Identifier,
+    /// Always empty in C(-1)
+    pub arguments: Vec<Expr>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Statement {
+    pub kind: StatementKind,
+    /// Location of the first token of the statement
+    pub location: Location,
+}
+
+/// block ::= "{" statementlist "}" | statement
+///
+/// statement ::= ifstatement | whilestatement | dowhilestatement | forstatement | declaration
+///             | returnstatement ";" | printf ";" | statassignment ";" | functioncall ";"
+#[derive(Debug, Clone, PartialEq)]
+pub enum StatementKind {
+    /// "{" statementlist "}"
+    Block(Vec<Statement>),
+    /// <KW_IF> "(" assignment ")" block ( <KW_ELSE> block )?
+    ///
+    /// An `else` belongs to the nearest `if` without `else`.
+    If {
+        condition: Expr,
+        then_branch: Box<Statement>,
+        else_branch: Option<Box<Statement>>,
+    },
+    /// <KW_WHILE> "(" assignment ")" block
+    While {
+        condition: Expr,
+        body: Box<Statement>,
+    },
+    /// <KW_DO> block <KW_WHILE> "(" assignment ")" ";"
+    DoWhile {
+        body: Box<Statement>,
+        condition: Expr,
+    },
+    /// <KW_FOR> "(" ( statassignment )? ";" ( assignment )? ";" ( statassignment )? ")" block
+    ///
+    /// `init` and `update` are assignment statements. A missing condition is always true.
+    For {
+        init: Option<Box<Statement>>,
+        condition: Option<Expr>,
+        update: Option<Box<Statement>>,
+        body: Box<Statement>,
+    },
+    /// type <ID> ( "=" assignment )? ";"
+    ///
+    /// Only in C1.
+    Declaration {
+        ty: Type,
+        name: Identifier,
+        value: Option<Expr>,
+    },
+    /// <KW_RETURN> ( assignment )?
+    Return(Option<Expr>),
+    /// <KW_PRINTF> "(" ( <CONST_STRING> | assignment ) ")"
+    Printf(PrintfArgument),
+    /// <ID> "=" assignment
+    Assignment { target: Identifier, value: Expr },
+    /// functioncall
+    Call(FunctionCall),
+}
+
+/// What a printf statement prints
+#[derive(Debug, Clone, PartialEq)]
+pub enum PrintfArgument {
+    /// <CONST_STRING> without the quotes and with its escape sequences decoded. Only in C1.
+    String(String),
+    Value(Expr),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr {
+    pub kind: ExprKind,
+    /// Location of the operator for unary and binary expressions, otherwise of the first token
+    pub location: Location,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprKind {
+    /// <CONST_INT> | <CONST_FLOAT> | <CONST_BOOLEAN>
+    Literal(Literal),
+    /// <ID>
+    Variable(Identifier),
+    /// functioncall
+    Call(FunctionCall),
+    /// <ID> "=" assignment
+    Assignment {
+        target: Identifier,
+        value: Box<Expr>,
+    },
+    Unary {
+        op: UnaryOperator,
+        operand: Box<Expr>,
+    },
+    Binary {
+        op: BinaryOperator,
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+    },
+}
+
+/// constant ::= <CONST_INT> | <CONST_FLOAT> | <CONST_BOOLEAN>
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Literal {
+    Int(i32),
+    Float(f32),
+    Bool(bool),
+}
+
+/// The operators of unary
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOperator {
+    /// "!"
+    Not,
+    /// "-"
+    Minus,
+    /// "+"
+    Plus,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOperator {
+    // expr
+    Equal,
+    NotEqual,
+    LessEqual,
+    GreaterEqual,
+    Less,
+    Greater,
+    // simpexpr
+    Add,
+    Subtract,
+    Or,
+    // term
+    Multiply,
+    Divide,
+    Modulo,
+    And,
+}
+
+impl FunctionDefinition {
+    /// Return the names of the variables declared in the body, including nested blocks, in source
+    /// order. Parameters are not included.
+    pub fn declarations(&self) -> Vec<&Identifier> {
+        let mut names = Vec::new();
+        for statement in &self.body {
+            statement_declarations(statement, &mut names);
+        }
+        names
+    }
+
+    /// Return the names of the parameters and declared variables. In the function, these names
+    /// refer to local variables even if a global variable with the same name exists.
+    pub fn locals(&self) -> Vec<&Identifier> {
+        self.parameters
+            .iter()
+            .map(|parameter| &parameter.name)
+            .chain(self.declarations())
+            .collect()
+    }
+}
+
+fn statement_declarations<'a>(statement: &'a Statement, names: &mut Vec<&'a Identifier>) {
+    match &statement.kind {
+        StatementKind::Block(statements) => {
+            for statement in statements {
+                statement_declarations(statement, names);
+            }
+        }
+        StatementKind::If {
+            then_branch,
+            else_branch,
+            ..
+        } => {
+            statement_declarations(then_branch, names);
+            if let Some(else_branch) = else_branch {
+                statement_declarations(else_branch, names);
+            }
+        }
+        StatementKind::While { body, .. }
+        | StatementKind::DoWhile { body, .. }
+        | StatementKind::For { body, .. } => statement_declarations(body, names),
+        StatementKind::Declaration { name, .. } => names.push(name),
+        StatementKind::Return(_)
+        | StatementKind::Printf(_)
+        | StatementKind::Assignment { .. }
+        | StatementKind::Call(_) => {}
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Names of the type keywords
+        let name = match self {
+            Type::Bool => "bool",
+            Type::Float => "float",
+            Type::Int => "int",
+            Type::Void => "void",
+        };
+        f.write_str(name)
+    }
+}
+
+impl fmt::Display for UnaryOperator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UnaryOperator::Not => f.write_str("!"),
+            UnaryOperator::Minus => f.write_str("-"),
+            UnaryOperator::Plus => f.write_str("+"),
+        }
+    }
+}
+
+impl fmt::Display for BinaryOperator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = match self {
+            BinaryOperator::Equal => "==",
+            BinaryOperator::NotEqual => "!=",
+            BinaryOperator::LessEqual => "<=",
+            BinaryOperator::GreaterEqual => ">=",
+            BinaryOperator::Less => "<",
+            BinaryOperator::Greater => ">",
+            BinaryOperator::Add => "+",
+            BinaryOperator::Subtract => "-",
+            BinaryOperator::Or => "||",
+            BinaryOperator::Multiply => "*",
+            BinaryOperator::Divide => "/",
+            BinaryOperator::Modulo => "%",
+            BinaryOperator::And => "&&",
+        };
+        f.write_str(symbol)
+    }
+}
@@ -0,0 +1,842 @@
+//! Compiler from C(-1) programs to bytecode for the stack-based [virtual machine](crate::vm).
+//!
+//! [`compile`] turns a [`Program`] into a [`Module`]: a pool of constants, the global variables and
+//! one [`Function`] per function definition. The code of a function is a sequence of
+//! [`Instruction`]s that pop their operands from and push their results onto a stack of
+//! [`Value`]s. Jump targets are indices into the code of the function, and every instruction keeps
+//! the source location it was compiled from. Modules can be stored in files with [`file`].
+//!
+//! Names are resolved at compile time with the rules of the [interpreter](crate::interpreter):
+//! a variable is global if the function has no parameter or declaration with its name and a global
+//! variable with the name exists, otherwise it is one of the numbered local variables of the
+//! function, parameters first. Calls are resolved as well, so calling a function that does not
+//! exist or with the wrong number of arguments and initializing a global variable with a value of
+//! the wrong type are [`CompileError`]s instead of runtime errors. Everything else, like the types
+//! of operands or reading a variable before it is assigned, is checked when the code runs, the same
+//! way the interpreter does.
+//!
+//! ```
+//! use cb_3::bytecode::compile;
+//! use cb_3::C1Parser;
+//!
+//! let program = C1Parser::parse_program("void main() { x = 6; printf(x * 7); }").unwrap();
+//! let module = compile(&program).unwrap();
+//! assert_eq!(
+//!     module.disassemble(),
+//!     "\
+//! function 0: void main()
+//!     locals: x
+//!     0000    1  constant 0             ; 6
+//!     0001    |  store_local 0          ; x
+//!     0002    |  load_local 0           ; x
+//!     0003    |  constant 1             ; 7
+//!     0004    |  binary *
+//!     0005    |  print
+//!     0006    |  return_void
+//! "
+//! );
+//! ```
+
+use crate::ast::{
+    BinaryOperator, Expr, ExprKind, FunctionCall, FunctionDefinition, Identifier, Location,
+    PrintfArgument, Program, Statement, StatementKind, Type, UnaryOperator,
+};
+use crate::interpreter::{convert, literal_value, Value};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fmt::Write;
+
+pub mod file;
+
+/// A bytecode instruction
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Instruction {
+    /// Push the constant with the given index
+    Constant(usize),
+    /// Push the value of the local variable in the given slot
+    LoadLocal(usize),
+    /// Pop a value and assign it to the local variable in the given slot
+    StoreLocal(usize),
+    /// Push the value of the global variable with the given index
+    LoadGlobal(usize),
+    /// Pop a value and assign it to the global variable with the given index
+    StoreGlobal(usize),
+    /// Pop the initial value of a declared local variable, convert it to the type of the variable
+    /// and store it in the given slot
+    Declare {
+        slot: usize,
+        ty: Type,
+    },
+    /// Convert the argument on top of the stack to the type of its parameter
+    Argument(Type),
+    /// Replace the value on top of the stack by the result of the operator
+    Unary(UnaryOperator),
+    /// Pop the right and the left operand and push the result of an operator other than `&&` and
+    /// `||`
+    Binary(BinaryOperator),
+    /// Check the left operand of `&&` or `||` on top of the stack. If it decides the result, jump
+    /// to the target and keep it as the result, otherwise pop it.
+    ShortCircuit {
+        op: BinaryOperator,
+        target: usize,
+    },
+    /// Check that the right operand of `&&` or `||` on top of the stack is a `bool`
+    Logical(BinaryOperator),
+    Jump(usize),
+    /// Pop a condition and jump to the target if it is false
+    JumpIfFalse(usize),
+    /// Pop a condition and jump to the target if it is true
+    JumpIfTrue(usize),
+    /// Call the function with the given index. Its arguments are popped from the stack and become
+    /// its first local variables, its return value is pushed.
+    Call(usize),
+    /// Pop the return value and return from the function
+    Return,
+    /// Return from the function without a return value
+    ReturnVoid,
+    /// Pop a value and print it, followed by a newline
+    Print,
+    /// Print the string with the given index
+    PrintString(usize),
+    /// Discard the value on top of the stack
+    Pop,
+}
+
+/// A compiled function definition
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    pub name: String,
+    pub return_type: Type,
+    /// The types of the parameters, which are the first local variables
+    pub parameters: Vec<Type>,
+    /// The names of the local variables by slot
+    pub locals: Vec<String>,
+    pub code: Vec<Instruction>,
+    /// The source location of each instruction
+    pub locations: Vec<Location>,
+}
+
+/// A global variable and its initial value
+#[derive(Debug, Clone, PartialEq)]
+pub struct Global {
+    pub name: String,
+    pub value: Value,
+}
+
+/// A compiled program
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Module {
+    /// The values pushed by [`Instruction::Constant`]
+    pub constants: Vec<Value>,
+    /// The strings printed by [`Instruction::PrintString`]
+    pub strings: Vec<String>,
+    pub globals: Vec<Global>,
+    pub functions: Vec<Function>,
+    /// Index of the `main` function, `None` if the program has none
+    pub main: Option<usize>,
+}
+
+/// Classification of a compile error
+#[derive(Debug, Clone, PartialEq)]
+pub enum CompileErrorKind {
+    UndefinedFunction(String),
+    /// A function is called with the wrong number of arguments
+    ArgumentCount {
+        function: String,
+        expected: usize,
+        found: usize,
+    },
+    /// The initial value of a global variable does not match its type
+    InvalidInitializer {
+        expected: Type,
+        found: Type,
+    },
+}
+
+/// An error found by [`compile`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompileError {
+    pub kind: CompileErrorKind,
+    /// Location of the offending name
+    pub location: Location,
+}
+
+impl CompileError {
+    /// Short description of what went wrong, without location information
+    pub fn message(&self) -> String {
+        match &self.kind {
+            CompileErrorKind::UndefinedFunction(name) => {
+                format!("Call of undefined function '{}'", name)
+            }
+            CompileErrorKind::ArgumentCount {
+                function,
+                expected,
+                found,
+            } => format!(
+                "Function '{}' takes {} arguments, but {} were given",
+                function, expected, found
+            ),
+            CompileErrorKind::InvalidInitializer { expected, found } => {
+                format!(
+                    "Expected an initial value of type {}, got {}",
+                    expected, found
+                )
+            }
+        }
+    }
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at line {}", self.message(), self.location.line)
+    }
+}
+
+impl std::error::Error for CompileError {}
+
+type Compiled<T> = Result<T, CompileError>;
+
+/// Compile the program into a module
+///
+/// Of several functions or global variables with the same name only the first one is compiled.
+pub fn compile(program: &Program) -> Compiled<Module> {
+    let mut compiler = Compiler::default();
+    for global in &program.globals {
+        let name = global.name.name.as_str();
+        if compiler.globals.contains_key(name) {
+            continue;
+        }
+        let value = match global.value {
+            Some(literal) => {
+                let value = literal_value(literal);
+                convert(global.ty, value).ok_or_else(|| CompileError {
+                    kind: CompileErrorKind::InvalidInitializer {
+                        expected: global.ty,
+                        found: value.ty(),
+                    },
+                    location: global.name.location,
+                })?
+            }
+            None => Value::zero(global.ty),
+        };
+        compiler.globals.insert(name, compiler.module.globals.len());
+        compiler.module.globals.push(Global {
+            name: name.to_string(),
+            value,
+        });
+    }
+    for function in &program.functions {
+        let name = function.name.name.as_str();
+        if !compiler.functions.contains_key(name) {
+            compiler.functions.insert(name, compiler.definitions.len());
+            compiler.definitions.push(function);
+        }
+    }
+    compiler.module.main = compiler.functions.get("main").copied();
+
+    for index in 0..compiler.definitions.len() {
+        let function = compiler.function(compiler.definitions[index])?;
+        compiler.module.functions.push(function);
+    }
+    Ok(compiler.module)
+}
+
+/// Where a variable is stored
+#[derive(Debug, Clone, Copy)]
+enum Variable {
+    Local(usize),
+    Global(usize),
+}
+
+#[derive(Default)]
+struct Compiler<'a> {
+    module: Module,
+    /// The definitions of the compiled functions, indexed like the functions of the module
+    definitions: Vec<&'a FunctionDefinition>,
+    functions: HashMap<&'a str, usize>,
+    globals: HashMap<&'a str, usize>,
+    /// The names that refer to local variables in the current function, see
+    /// [`FunctionDefinition::locals`]
+    locals: HashSet<&'a str>,
+    /// The slots of the local variables of the current function
+    slots: HashMap<&'a str, usize>,
+    /// The names of the local variables of the current function by slot
+    names: Vec<String>,
+    code: Vec<Instruction>,
+    locations: Vec<Location>,
+}
+
+impl<'a> Compiler<'a> {
+    fn function(&mut self, definition: &'a FunctionDefinition) -> Compiled<Function> {
+        self.locals = definition
+            .locals()
+            .into_iter()
+            .map(|name| name.name.as_str())
+            .collect();
+        self.slots.clear();
+        // Parameter `i` is stored in slot `i`, even if another parameter has the same name
+        for parameter in &definition.parameters {
+            let name = parameter.name.name.as_str();
+            self.slots.insert(name, self.names.len());
+            self.names.push(name.to_string());
+        }
+
+        for statement in &definition.body {
+            self.statement(statement)?;
+        }
+        self.emit(Instruction::ReturnVoid, definition.location);
+        Ok(Function {
+            name: definition.name.name.clone(),
+            return_type: definition.return_type,
+            parameters: definition.parameters.iter().map(|p| p.ty).collect(),
+            locals: std::mem::take(&mut self.names),
+            code: std::mem::take(&mut self.code),
+            locations: std::mem::take(&mut self.locations),
+        })
+    }
+
+    fn statement(&mut self, statement: &'a Statement) -> Compiled<()> {
+        let location = statement.location;
+        match &statement.kind {
+            StatementKind::Block(statements) => {
+                for statement in statements {
+                    self.statement(statement)?;
+                }
+            }
+            StatementKind::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                self.expr(condition)?;
+                let to_else = self.emit(Instruction::JumpIfFalse(0), condition.location);
+                self.statement(then_branch)?;
+                match else_branch {
+                    Some(else_branch) => {
+                        let to_end = self.emit(Instruction::Jump(0), location);
+                        self.patch(to_else);
+                        self.statement(else_branch)?;
+                        self.patch(to_end);
+                    }
+                    None => self.patch(to_else),
+                }
+            }
+            StatementKind::While { condition, body } => {
+                let start = self.code.len();
+                self.expr(condition)?;
+                let to_end = self.emit(Instruction::JumpIfFalse(0), condition.location);
+                self.statement(body)?;
+                self.emit(Instruction::Jump(start), location);
+                self.patch(to_end);
+            }
+            StatementKind::DoWhile { body, condition } => {
+                let start = self.code.len();
+                self.statement(body)?;
+                self.expr(condition)?;
+                self.emit(Instruction::JumpIfTrue(start), condition.location);
+            }
+            StatementKind::For {
+                init,
+                condition,
+                update,
+                body,
+            } => {
+                if let Some(init) = init {
+                    self.statement(init)?;
+                }
+                let start = self.code.len();
+                let to_end = match condition {
+                    Some(condition) => {
+                        self.expr(condition)?;
+                        Some(self.emit(Instruction::JumpIfFalse(0), condition.location))
+                    }
+                    None => None,
+                };
+                self.statement(body)?;
+                if let Some(update) = update {
+                    self.statement(update)?;
+                }
+                self.emit(Instruction::Jump(start), location);
+                if let Some(to_end) = to_end {
+                    self.patch(to_end);
+                }
+            }
+            StatementKind::Declaration { ty, name, value } => {
+                let value_location = match value {
+                    Some(value) => {
+                        self.expr(value)?;
+                        value.location
+                    }
+                    None => {
+                        self.constant(Value::zero(*ty), name.location);
+                        name.location
+                    }
+                };
+                let Variable::Local(slot) = self.variable(&name.name) else {
+                    unreachable!("declared variables are local");
+                };
+                self.emit(Instruction::Declare { slot, ty: *ty }, value_location);
+            }
+            StatementKind::Return(value) => match value {
+                Some(value) => {
+                    self.expr(value)?;
+                    self.emit(Instruction::Return, location);
+                }
+                None => {
+                    self.emit(Instruction::ReturnVoid, location);
+                }
+            },
+            StatementKind::Printf(PrintfArgument::String(string)) => {
+                let index = match self.module.strings.iter().position(|s| s == string) {
+                    Some(index) => index,
+                    None => {
+                        self.module.strings.push(string.clone());
+                        self.module.strings.len() - 1
+                    }
+                };
+                self.emit(Instruction::PrintString(index), location);
+            }
+            StatementKind::Printf(PrintfArgument::Value(value)) => {
+                self.expr(value)?;
+                self.emit(Instruction::Print, location);
+            }
+            StatementKind::Assignment { target, value } => {
+                self.expr(value)?;
+                self.store(target);
+            }
+            StatementKind::Call(call) => {
+                self.call(call)?;
+                self.emit(Instruction::Pop, location);
+            }
+        }
+        Ok(())
+    }
+
+    fn expr(&mut self, expr: &'a Expr) -> Compiled<()> {
+        match &expr.kind {
+            ExprKind::Literal(literal) => self.constant(literal_value(*literal), expr.location),
+            ExprKind::Variable(name) => self.load(name),
+            ExprKind::Call(call) => self.call(call)?,
+            ExprKind::Assignment { target, value } => {
+                // The value of the assignment is the value of the variable afterwards
+                self.expr(value)?;
+                self.store(target);
+                self.load(target);
+            }
+            ExprKind::Unary { op, operand } => {
+                self.expr(operand)?;
+                self.emit(Instruction::Unary(*op), expr.location);
+            }
+            ExprKind::Binary {
+                op: op @ (BinaryOperator::And | BinaryOperator::Or),
+                lhs,
+                rhs,
+            } => {
+                self.expr(lhs)?;
+                let to_end = self.emit(
+                    Instruction::ShortCircuit { op: *op, target: 0 },
+                    expr.location,
+                );
+                self.expr(rhs)?;
+                self.emit(Instruction::Logical(*op), expr.location);
+                self.patch(to_end);
+            }
+            ExprKind::Binary { op, lhs, rhs } => {
+                self.expr(lhs)?;
+                self.expr(rhs)?;
+                self.emit(Instruction::Binary(*op), expr.location);
+            }
+        }
+        Ok(())
+    }
+
+    fn call(&mut self, call: &'a FunctionCall) -> Compiled<()> {
+        let name = &call.name;
+        let index = *self
+            .functions
+            .get(name.name.as_str())
+            .ok_or_else(|| CompileError {
+                kind: CompileErrorKind::UndefinedFunction(name.name.clone()),
+                location: name.location,
+            })?;
+        let function = self.definitions[index];
+        if call.arguments.len() != function.parameters.len() {
+            return Err(CompileError {
+                kind: CompileErrorKind::ArgumentCount {
+                    function: name.name.clone(),
+                    expected: function.parameters.len(),
+                    found: call.arguments.len(),
+                },
+                location: name.location,
+            });
+        }
+        for (parameter, argument) in function.parameters.iter().zip(&call.arguments) {
+            self.expr(argument)?;
+            self.emit(Instruction::Argument(parameter.ty), argument.location);
+        }
+        self.emit(Instruction::Call(index), name.location);
+        Ok(())
+    }
+
+    fn load(&mut self, name: &'a Identifier) {
+        let instruction = match self.variable(&name.name) {
+            Variable::Local(slot) => Instruction::LoadLocal(slot),
+            Variable::Global(index) => Instruction::LoadGlobal(index),
+        };
+        self.emit(instruction, name.location);
+    }
+
+    fn store(&mut self, target: &'a Identifier) {
+        let instruction = match self.variable(&target.name) {
+            Variable::Local(slot) => Instruction::StoreLocal(slot),
+            Variable::Global(index) => Instruction::StoreGlobal(index),
+        };
+        self.emit(instruction, target.location);
+    }
+
+    /// Resolve a variable of the current function, assigning a slot to new local variables
+    fn variable(&mut self, name: &'a str) -> Variable {
+        if !self.locals.contains(name) {
+            if let Some(&index) = self.globals.get(name) {
+                return Variable::Global(index);
+            }
+        }
+        let names = &mut self.names;
+        let slot = *self.slots.entry(name).or_insert_with(|| {
+            names.push(name.to_string());
+            names.len() - 1
+        });
+        Variable::Local(slot)
+    }
+
+    fn constant(&mut self, value: Value, location: Location) {
+        let index = match self.module.constants.iter().position(|&c| c == value) {
+            Some(index) => index,
+            None => {
+                self.module.constants.push(value);
+                self.module.constants.len() - 1
+            }
+        };
+        self.emit(Instruction::Constant(index), location);
+    }
+
+    /// Append an instruction to the current function and return its index
+    fn emit(&mut self, instruction: Instruction, location: Location) -> usize {
+        self.code.push(instruction);
+        self.locations.push(location);
+        self.code.len() - 1
+    }
+
+    /// Let the jump at the given index jump to the next instruction
+    fn patch(&mut self, jump: usize) {
+        let next = self.code.len();
+        match &mut self.code[jump] {
+            Instruction::Jump(target)
+            | Instruction::JumpIfFalse(target)
+            | Instruction::JumpIfTrue(target)
+            | Instruction::ShortCircuit { target, .. } => *target = next,
+            instruction => unreachable!("{:?} is not a jump", instruction),
+        }
+    }
+}
+
+impl Module {
+    /// Render the module in a human readable form for debugging
+    ///
+    /// Each instruction is listed with its index and the line it was compiled from, which is only
+    /// shown when it differs from the line of the previous instruction. Operands that refer to
+    /// constants, strings, variables or functions are explained in a comment.
+    pub fn disassemble(&self) -> String {
+        let mut text = String::new();
+        if !self.globals.is_empty() {
+            text.push_str("globals:\n");
+            for (index, global) in self.globals.iter().enumerate() {
+                let _ = writeln!(
+                    text,
+                    "    {}: {} {} = {}",
+                    index,
+                    global.value.ty(),
+                    global.name,
+                    global.value
+                );
+            }
+        }
+        for (index, function) in self.functions.iter().enumerate() {
+            let parameters: Vec<String> = function
+                .parameters
+                .iter()
+                .zip(&function.locals)
+                .map(|(ty, name)| format!("{} {}", ty, name))
+                .collect();
+            let _ = writeln!(
+                text,
+                "function {}: {} {}({})",
+                index,
+                function.return_type,
+                function.name,
+                parameters.join(", ")
+            );
+            if function.locals.len() > function.parameters.len() {
+                let _ = writeln!(
+                    text,
+                    "    locals: {}",
+                    function.locals[function.parameters.len()..].join(", ")
+                );
+            }
+            let mut line = None;
+            for (ip, (instruction, location)) in
+                function.code.iter().zip(&function.locations).enumerate()
+            {
+                let line_column = if line == Some(location.line) {
+                    "   |".to_string()
+                } else {
+                    format!("{:4}", location.line)
+                };
+                line = Some(location.line);
+                let _ = match self.comment(function, *instruction) {
+                    Some(comment) => writeln!(
+                        text,
+                        "    {:04} {}  {:<22} ; {}",
+                        ip,
+                        line_column,
+                        instruction.to_string(),
+                        comment
+                    ),
+                    None => writeln!(text, "    {:04} {}  {}", ip, line_column, instruction),
+                };
+            }
+        }
+        text
+    }
+
+    /// Explain the operand of an instruction
+    fn comment(&self, function: &Function, instruction: Instruction) -> Option<String> {
+        let comment = match instruction {
+            Instruction::Constant(index) => self.constants.get(index)?.to_string(),
+            Instruction::PrintString(index) => format!("{:?}", self.strings.get(index)?),
+            Instruction::LoadLocal(slot)
+            | Instruction::StoreLocal(slot)
+            | Instruction::Declare { slot, .. } => function.locals.get(slot)?.clone(),
+            Instruction::LoadGlobal(index) | Instruction::StoreGlobal(index) => {
+                self.globals.get(index)?.name.clone()
+            }
+            Instruction::Call(index) => self.functions.get(index)?.name.clone(),
+            _ => return None,
+        };
+        Some(comment)
+    }
+}
+
+/// Formats instructions in the syntax of the disassembler
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Instruction::Constant(index) => write!(f, "constant {}", index),
+            Instruction::LoadLocal(slot) => write!(f, "load_local {}", slot),
+            Instruction::StoreLocal(slot) => write!(f, "store_local {}", slot),
+            Instruction::LoadGlobal(index) => write!(f, "load_global {}", index),
+            Instruction::StoreGlobal(index) => write!(f, "store_global {}", index),
+            Instruction::Declare { slot, ty } => write!(f, "declare {} {}", slot, ty),
+            Instruction::Argument(ty) => write!(f, "argument {}", ty),
+            Instruction::Unary(op) => write!(f, "unary {}", op),
+            Instruction::Binary(op) => write!(f, "binary {}", op),
+            Instruction::ShortCircuit { op, target } => {
+                write!(f, "short_circuit {} {:04}", op, target)
+            }
+            Instruction::Logical(op) => write!(f, "logical {}", op),
+            Instruction::Jump(target) => write!(f, "jump {:04}", target),
+            Instruction::JumpIfFalse(target) => write!(f, "jump_if_false {:04}", target),
+            Instruction::JumpIfTrue(target) => write!(f, "jump_if_true {:04}", target),
+            Instruction::Call(index) => write!(f, "call {}", index),
+            Instruction::Return => f.write_str("return"),
+            Instruction::ReturnVoid => f.write_str("return_void"),
+            Instruction::Print => f.write_str("print"),
+            Instruction::PrintString(index) => write!(f, "print_string {}", index),
+            Instruction::Pop => f.write_str("pop"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ast::{BinaryOperator, Type};
+    use crate::bytecode::{compile, CompileErrorKind, Instruction, Module};
+    use crate::interpreter::Value;
+    use crate::{C1Parser, Dialect};
+
+    fn compile_text(text: &str) -> Result<Module, CompileErrorKind> {
+        let program = C1Parser::parse_program_in(text, Dialect::C1).unwrap();
+        compile(&program).map_err(|error| error.kind)
+    }
+
+    #[test]
+    fn variable_resolution() {
+        let module = compile_text(
+            "int g = 1; float h;
+             int f(int a, int g) { b = a + g; return h; }
+             void main() { int h = g; x = h; g = 2; }",
+        )
+        .unwrap();
+        assert_eq!(module.globals.len(), 2);
+        assert_eq!(module.globals[1].value, Value::Float(0.0));
+        assert_eq!(module.main, Some(1));
+
+        // Parameters come first, the parameter g hides the global variable
+        let f = &module.functions[0];
+        assert_eq!(f.parameters, [Type::Int, Type::Int]);
+        assert_eq!(f.locals, ["a", "g", "b"]);
+        assert_eq!(
+            f.code[..5],
+            [
+                Instruction::LoadLocal(0),
+                Instruction::LoadLocal(1),
+                Instruction::Binary(BinaryOperator::Add),
+                Instruction::StoreLocal(2),
+                Instruction::LoadGlobal(1),
+            ]
+        );
+
+        // The declaration of h hides the global variable in all of main
+        let main = &module.functions[1];
+        assert_eq!(main.locals, ["h", "x"]);
+        assert_eq!(
+            main.code,
+            [
+                Instruction::LoadGlobal(0),
+                Instruction::Declare {
+                    slot: 0,
+                    ty: Type::Int
+                },
+                Instruction::LoadLocal(0),
+                Instruction::StoreLocal(1),
+                Instruction::Constant(0),
+                Instruction::StoreGlobal(0),
+                Instruction::ReturnVoid,
+            ]
+        );
+        // Initial values of global variables are not constants
+        assert_eq!(module.constants, [Value::Int(2)]);
+    }
+
+    #[test]
+    fn control_flow() {
+        let module = compile_text(
+            "void main() { if (a) x = 1; else { while (b) x = 2; } do {} while (c && d); }",
+        )
+        .unwrap();
+        assert_eq!(
+            module.functions[0].code,
+            [
+                Instruction::LoadLocal(0),
+                Instruction::JumpIfFalse(5),
+                Instruction::Constant(0),
+                Instruction::StoreLocal(1),
+                Instruction::Jump(10),
+                Instruction::LoadLocal(2),
+                Instruction::JumpIfFalse(10),
+                Instruction::Constant(1),
+                Instruction::StoreLocal(1),
+                Instruction::Jump(5),
+                // The loop body of the do-while loop is empty
+                Instruction::LoadLocal(3),
+                Instruction::ShortCircuit {
+                    op: BinaryOperator::And,
+                    target: 14
+                },
+                Instruction::LoadLocal(4),
+                Instruction::Logical(BinaryOperator::And),
+                Instruction::JumpIfTrue(10),
+                Instruction::ReturnVoid,
+            ]
+        );
+        let (code, locations) = (&module.functions[0].code, &module.functions[0].locations);
+        assert_eq!(code.len(), locations.len());
+    }
+
+    #[test]
+    fn calls_and_printf() {
+        let module = compile_text(
+            r#"int f(float x) { return 1; } void main() { f(1); printf(f(2)); printf("f\n"); }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            module.functions[1].code,
+            [
+                Instruction::Constant(0),
+                Instruction::Argument(Type::Float),
+                Instruction::Call(0),
+                Instruction::Pop,
+                Instruction::Constant(1),
+                Instruction::Argument(Type::Float),
+                Instruction::Call(0),
+                Instruction::Print,
+                Instruction::PrintString(0),
+                Instruction::ReturnVoid,
+            ]
+        );
+        assert_eq!(module.strings, ["f\n"]);
+    }
+
+    #[test]
+    fn duplicate_definitions() {
+        // Only the first definition of a name is compiled
+        let module =
+            compile_text("int g = 1; bool g; int f() {} void f() {} void main() {}").unwrap();
+        assert_eq!(module.globals.len(), 1);
+        assert_eq!(module.globals[0].value, Value::Int(1));
+        assert_eq!(module.functions.len(), 2);
+        assert_eq!(module.functions[0].return_type, Type::Int);
+        assert_eq!(compile_text("int f() {}").unwrap().main, None);
+    }
+
+    #[test]
+    fn compile_errors() {
+        assert_eq!(
+            compile_text("void main() { if (false) foo(); }"),
+            Err(CompileErrorKind::UndefinedFunction("foo".to_string()))
+        );
+        assert_eq!(
+            compile_text("void f(int a) {} void main() { f(); }"),
+            Err(CompileErrorKind::ArgumentCount {
+                function: "f".to_string(),
+                expected: 1,
+                found: 0
+            })
+        );
+        assert_eq!(
+            compile_text("int x = 1.5; void main() {}"),
+            Err(CompileErrorKind::InvalidInitializer {
+                expected: Type::Int,
+                found: Type::Float
+            })
+        );
+
+        let program = C1Parser::parse_program("void main() {\n  foo();\n}").unwrap();
+        let error = compile(&program).unwrap_err();
+        assert_eq!(error.location.line, 2);
+        assert_eq!(
+            error.to_string(),
+            "Call of undefined function 'foo' at line 2"
+        );
+    }
+
+    #[test]
+    fn disassemble_globals_and_jumps() {
+        let module = compile_text("int g = 3;\nint f(int a) {\n  if (a < g) return a;\n}").unwrap();
+        assert_eq!(
+            module.disassemble(),
+            "\
+globals:
+    0: int g = 3
+function 0: int f(int a)
+    0000    3  load_local 0           ; a
+    0001    |  load_global 0          ; g
+    0002    |  binary <
+    0003    |  jump_if_false 0006
+    0004    |  load_local 0           ; a
+    0005    |  return
+    0006    2  return_void
+"
+        );
+    }
+}
@@ -0,0 +1,1033 @@
+//! Binary file format for [bytecode modules](crate::bytecode::Module).
+//!
+//! [`write`] stores a compiled module, [`load`] reads it back and validates it, so a program can be
+//! compiled once and run many times. A loaded module is safe to run: every index refers to
+//! something that exists, jumps stay inside their function, and every instruction finds the
+//! operands it needs on the stack.
+//!
+//! All numbers are little endian. A file consists of
+//!
+//! | Section        | Content                                                                 |
+//! |----------------|-------------------------------------------------------------------------|
+//! | Header         | the magic number `C1BC`, the format version as `u16`                    |
+//! | Constant pool  | `u32` count, then one value per constant                                |
+//! | String pool    | `u32` count, then one string per entry                                  |
+//! | Globals        | `u32` count, then the name and initial value of each global variable    |
+//! | Main           | `u32` index of the `main` function, `0xffffffff` if there is none       |
+//! | Function table | `u32` count, then one entry per function                                |
+//! | Code section   | the code of all functions in the order of the function table            |
+//!
+//! where
+//! - a string is its `u32` length in bytes followed by its UTF-8 encoding,
+//! - a value is a tag byte, `0` for an `int` followed by an `i32`, `1` for a `float` followed by an
+//!   `f32`, `2` for a `bool` followed by a byte that is `0` or `1`, and `3` for `void`,
+//! - a type is a byte, `0` for `bool`, `1` for `float`, `2` for `int` and `3` for `void`,
+//! - a function table entry consists of the name, the return type, the `u32` number of parameters
+//!   and their types, the `u32` number of local variables and their names, the `u32` size of the
+//!   code in bytes and the line table,
+//! - the line table is a `u32` count followed by entries of five `u32`s: the index of the first
+//!   instruction that belongs to the entry and the line, column, start and end of its
+//!   [`Location`]. An entry applies to all instructions up to the next entry. The first entry
+//!   starts at instruction 0.
+//! - an instruction is an opcode byte followed by its operands in the order of the fields of the
+//!   [`Instruction`]. Indices and jump targets are `u32`s, types are encoded as above and
+//!   operators as a byte: the position of the operator in [`UnaryOperator`] or
+//!   [`BinaryOperator`], starting at 0. The opcodes are the positions of the instructions in
+//!   [`Instruction`], starting at 0.
+//!
+//! ```
+//! use cb_3::bytecode::{compile, file};
+//! use cb_3::C1Parser;
+//!
+//! let program = C1Parser::parse_program("void main() { printf(42); }").unwrap();
+//! let module = compile(&program).unwrap();
+//! let bytes = file::to_bytes(&module);
+//! assert_eq!(&bytes[..4], b"C1BC");
+//! assert_eq!(file::load(&bytes), Ok(module));
+//! assert!(file::load(&bytes[..bytes.len() - 1]).is_err());
+//! ```
+
+use crate::ast::{BinaryOperator, Location, Type, UnaryOperator};
+use crate::bytecode::{Function, Global, Instruction, Module};
+use crate::interpreter::Value;
+use std::fmt;
+use std::io;
+use std::io::Write;
+
+/// The first four bytes of every module file
+pub const MAGIC: &[u8; 4] = b"C1BC";
+
+/// The version of the format written by [`write`], the only version [`load`] accepts
+pub const FORMAT_VERSION: u16 = 1;
+
+/// Marks a module without `main` function
+const NO_MAIN: u32 = u32::MAX;
+
+const TYPES: [Type; 4] = [Type::Bool, Type::Float, Type::Int, Type::Void];
+
+const UNARY_OPERATORS: [UnaryOperator; 3] = [
+    UnaryOperator::Not,
+    UnaryOperator::Minus,
+    UnaryOperator::Plus,
+];
+
+const BINARY_OPERATORS: [BinaryOperator; 13] = [
+    BinaryOperator::Equal,
+    BinaryOperator::NotEqual,
+    BinaryOperator::LessEqual,
+    BinaryOperator::GreaterEqual,
+    BinaryOperator::Less,
+    BinaryOperator::Greater,
+    BinaryOperator::Add,
+    BinaryOperator::Subtract,
+    BinaryOperator::Or,
+    BinaryOperator::Multiply,
+    BinaryOperator::Divide,
+    BinaryOperator::Modulo,
+    BinaryOperator::And,
+];
+
+/// Write the module in the module file format
+pub fn write(module: &Module, writer: impl Write) -> io::Result<()> {
+    let mut writer = Writer { writer };
+    writer.bytes(MAGIC)?;
+    writer.bytes(&FORMAT_VERSION.to_le_bytes())?;
+
+    writer.count(module.constants.len())?;
+    for &constant in &module.constants {
+        writer.value(constant)?;
+    }
+    writer.count(module.strings.len())?;
+    for string in &module.strings {
+        writer.string(string)?;
+    }
+    writer.count(module.globals.len())?;
+    for global in &module.globals {
+        writer.string(&global.name)?;
+        writer.value(global.value)?;
+    }
+    match module.main {
+        Some(main) => writer.index(main)?,
+        None => writer.u32(NO_MAIN)?,
+    }
+
+    let code: Vec<Vec<u8>> = module
+        .functions
+        .iter()
+        .map(|function| {
+            let mut code = Writer { writer: Vec::new() };
+            for &instruction in &function.code {
+                code.instruction(instruction)?;
+            }
+            Ok(code.writer)
+        })
+        .collect::<io::Result<_>>()?;
+    writer.count(module.functions.len())?;
+    for (function, code) in module.functions.iter().zip(&code) {
+        writer.string(&function.name)?;
+        writer.ty(function.return_type)?;
+        writer.count(function.parameters.len())?;
+        for &ty in &function.parameters {
+            writer.ty(ty)?;
+        }
+        writer.count(function.locals.len())?;
+        for name in &function.locals {
+            writer.string(name)?;
+        }
+        writer.count(code.len())?;
+        writer.line_table(&function.locations)?;
+    }
+    for code in &code {
+        writer.bytes(code)?;
+    }
+    Ok(())
+}
+
+/// Return the module in the module file format
+pub fn to_bytes(module: &Module) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    write(module, &mut bytes).expect("modules fit the format");
+    bytes
+}
+
+/// Read and validate a module file
+pub fn load(bytes: &[u8]) -> Loaded<Module> {
+    let mut reader = Reader { bytes, offset: 0 };
+    if reader.take(MAGIC.len()).ok() != Some(&MAGIC[..]) {
+        return Err(LoadError {
+            kind: LoadErrorKind::NotAModule,
+            offset: 0,
+        });
+    }
+    let version = reader.u16()?;
+    if version != FORMAT_VERSION {
+        return Err(reader.error_at(
+            reader.offset - 2,
+            LoadErrorKind::UnsupportedVersion(version),
+        ));
+    }
+
+    let mut module = Module::default();
+    for _ in 0..reader.count()? {
+        module.constants.push(reader.value()?);
+    }
+    for _ in 0..reader.count()? {
+        module.strings.push(reader.string()?);
+    }
+    for _ in 0..reader.count()? {
+        let name = reader.string()?;
+        let value = reader.value()?;
+        module.globals.push(Global { name, value });
+    }
+    let main_offset = reader.offset;
+    let main = reader.u32()?;
+
+    // The function table, with the code that is read later
+    let mut tables = Vec::new();
+    for _ in 0..reader.count()? {
+        let name = reader.string()?;
+        let return_type = reader.ty()?;
+        let mut parameters = Vec::new();
+        for _ in 0..reader.count()? {
+            parameters.push(reader.ty()?);
+        }
+        let mut locals = Vec::new();
+        for _ in 0..reader.count()? {
+            locals.push(reader.string()?);
+        }
+        if locals.len() < parameters.len() {
+            return Err(reader.error(LoadErrorKind::MissingParameterSlots { function: name }));
+        }
+        let code_size = reader.u32()? as usize;
+        let line_table = reader.line_table()?;
+        module.functions.push(Function {
+            name,
+            return_type,
+            parameters,
+            locals,
+            code: Vec::new(),
+            locations: Vec::new(),
+        });
+        tables.push((code_size, line_table));
+    }
+
+    if main != NO_MAIN {
+        if main as usize >= module.functions.len() {
+            return Err(reader.error_at(
+                main_offset,
+                LoadErrorKind::IndexOutOfRange {
+                    what: "function",
+                    index: main as usize,
+                },
+            ));
+        }
+        module.main = Some(main as usize);
+    }
+
+    for (index, (code_size, (line_table_offset, line_table))) in tables.into_iter().enumerate() {
+        let start = reader.offset;
+        reader.take(code_size)?;
+        // An instruction that does not end with the code of its function is truncated
+        let mut code = Reader {
+            bytes: &bytes[..reader.offset],
+            offset: start,
+        };
+        let mut offsets = Vec::new();
+        while code.offset < reader.offset {
+            let offset = code.offset;
+            let instruction = code.instruction()?;
+            check_operands(&module, index, instruction)
+                .map_err(|kind| code.error_at(offset, kind))?;
+            module.functions[index].code.push(instruction);
+            offsets.push(offset);
+        }
+        let function = &mut module.functions[index];
+        function.locations = expand_line_table(&line_table, function.code.len())
+            .ok_or_else(|| reader.error_at(line_table_offset, LoadErrorKind::InvalidLineTable))?;
+        verify_stack(&module, index).map_err(|(ip, kind)| {
+            let offset = offsets.get(ip).copied().unwrap_or(start);
+            reader.error_at(offset, kind)
+        })?;
+    }
+
+    if reader.offset != bytes.len() {
+        return Err(reader.error(LoadErrorKind::TrailingData));
+    }
+    Ok(module)
+}
+
+/// Classification of an error in a module file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoadErrorKind {
+    /// The file does not start with [`MAGIC`]
+    NotAModule,
+    /// The file was written in a format version other than [`FORMAT_VERSION`]
+    UnsupportedVersion(u16),
+    /// The file ends in the middle of a section
+    Truncated,
+    /// There is data after the code section
+    TrailingData,
+    /// A tag, type, operator or opcode byte has an unknown value
+    InvalidEncoding { what: &'static str, value: u8 },
+    /// A string is not valid UTF-8
+    InvalidString,
+    /// An instruction or the main function refers to a constant, string, global variable,
+    /// function, local variable or instruction that does not exist
+    IndexOutOfRange { what: &'static str, index: usize },
+    /// A function has fewer local variables than parameters
+    MissingParameterSlots { function: String },
+    /// The entries of a line table do not start at the first instruction or are out of order
+    InvalidLineTable,
+    /// An instruction needs more operands than there are on the stack
+    StackUnderflow,
+    /// The stack height at an instruction depends on how it is reached, or the stack is not empty
+    /// when a function returns
+    InconsistentStack,
+    /// The code of a function can run past its last instruction
+    MissingReturn,
+}
+
+/// An error found by [`load`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadError {
+    pub kind: LoadErrorKind,
+    /// Offset of the offending byte, or of the instruction that is invalid
+    pub offset: usize,
+}
+
+impl LoadError {
+    /// Short description of what went wrong, without location information
+    pub fn message(&self) -> String {
+        match &self.kind {
+            LoadErrorKind::NotAModule => "Not a module file".to_string(),
+            LoadErrorKind::UnsupportedVersion(version) => format!(
+                "Unsupported format version {}, expected {}",
+                version, FORMAT_VERSION
+            ),
+            LoadErrorKind::Truncated => "Unexpected end of file".to_string(),
+            LoadErrorKind::TrailingData => "Unexpected data after the code section".to_string(),
+            LoadErrorKind::InvalidEncoding { what, value } => {
+                format!("Invalid {} {}", what, value)
+            }
+            LoadErrorKind::InvalidString => "String is not valid UTF-8".to_string(),
+            LoadErrorKind::IndexOutOfRange { what, index } => {
+                format!("There is no {} with index {}", what, index)
+            }
+            LoadErrorKind::MissingParameterSlots { function } => format!(
+                "Function '{}' has fewer local variables than parameters",
+                function
+            ),
+            LoadErrorKind::InvalidLineTable => "Invalid line table".to_string(),
+            LoadErrorKind::StackUnderflow => "Instruction pops from an empty stack".to_string(),
+            LoadErrorKind::InconsistentStack => "Inconsistent stack height".to_string(),
+            LoadErrorKind::MissingReturn => "Code runs past the end of the function".to_string(),
+        }
+    }
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at byte {}", self.message(), self.offset)
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+type Loaded<T> = Result<T, LoadError>;
+
+struct Writer<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> Writer<W> {
+    fn bytes(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.writer.write_all(bytes)
+    }
+
+    fn u8(&mut self, value: u8) -> io::Result<()> {
+        self.bytes(&[value])
+    }
+
+    fn u32(&mut self, value: u32) -> io::Result<()> {
+        self.bytes(&value.to_le_bytes())
+    }
+
+    /// Write an index, count or size as `u32`
+    fn index(&mut self, value: usize) -> io::Result<()> {
+        let value = u32::try_from(value)
+            .ok()
+            .filter(|&value| value != NO_MAIN)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "module is too large"))?;
+        self.u32(value)
+    }
+
+    fn count(&mut self, count: usize) -> io::Result<()> {
+        self.index(count)
+    }
+
+    fn string(&mut self, string: &str) -> io::Result<()> {
+        self.count(string.len())?;
+        self.bytes(string.as_bytes())
+    }
+
+    fn ty(&mut self, ty: Type) -> io::Result<()> {
+        self.u8(position(&TYPES, ty))
+    }
+
+    fn value(&mut self, value: Value) -> io::Result<()> {
+        match value {
+            Value::Int(value) => {
+                self.u8(0)?;
+                self.bytes(&value.to_le_bytes())
+            }
+            Value::Float(value) => {
+                self.u8(1)?;
+                self.bytes(&value.to_le_bytes())
+            }
+            Value::Bool(value) => {
+                self.u8(2)?;
+                self.u8(value as u8)
+            }
+            Value::Void => self.u8(3),
+        }
+    }
+
+    fn line_table(&mut self, locations: &[Location]) -> io::Result<()> {
+        let entries: Vec<(usize, Location)> = locations
+            .iter()
+            .enumerate()
+            .filter(|&(ip, location)| ip == 0 || locations[ip - 1] != *location)
+            .map(|(ip, &location)| (ip, location))
+            .collect();
+        self.count(entries.len())?;
+        for (ip, location) in entries {
+            self.index(ip)?;
+            for value in [location.line, location.column, location.start, location.end] {
+                self.index(value)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn instruction(&mut self, instruction: Instruction) -> io::Result<()> {
+        use Instruction::*;
+
+        match instruction {
+            Constant(index) => self.opcode(0, &[index]),
+            LoadLocal(slot) => self.opcode(1, &[slot]),
+            StoreLocal(slot) => self.opcode(2, &[slot]),
+            LoadGlobal(index) => self.opcode(3, &[index]),
+            StoreGlobal(index) => self.opcode(4, &[index]),
+            Declare { slot, ty } => {
+                self.opcode(5, &[slot])?;
+                self.ty(ty)
+            }
+            Argument(ty) => {
+                self.opcode(6, &[])?;
+                self.ty(ty)
+            }
+            Unary(op) => {
+                self.opcode(7, &[])?;
+                self.u8(position(&UNARY_OPERATORS, op))
+            }
+            Binary(op) => {
+                self.opcode(8, &[])?;
+                self.u8(position(&BINARY_OPERATORS, op))
+            }
+            ShortCircuit { op, target } => {
+                self.opcode(9, &[])?;
+                self.u8(position(&BINARY_OPERATORS, op))?;
+                self.index(target)
+            }
+            Logical(op) => {
+                self.opcode(10, &[])?;
+                self.u8(position(&BINARY_OPERATORS, op))
+            }
+            Jump(target) => self.opcode(11, &[target]),
+            JumpIfFalse(target) => self.opcode(12, &[target]),
+            JumpIfTrue(target) => self.opcode(13, &[target]),
+            Call(index) => self.opcode(14, &[index]),
+            Return => self.opcode(15, &[]),
+            ReturnVoid => self.opcode(16, &[]),
+            Print => self.opcode(17, &[]),
+            PrintString(index) => self.opcode(18, &[index]),
+            Pop => self.opcode(19, &[]),
+        }
+    }
+
+    /// Write an opcode followed by index operands
+    fn opcode(&mut self, opcode: u8, indices: &[usize]) -> io::Result<()> {
+        self.u8(opcode)?;
+        for &index in indices {
+            self.index(index)?;
+        }
+        Ok(())
+    }
+}
+
+fn position<T: PartialEq>(values: &[T], value: T) -> u8 {
+    values
+        .iter()
+        .position(|v| *v == value)
+        .expect("every value is encoded") as u8
+}
+
+struct Reader<'b> {
+    bytes: &'b [u8],
+    offset: usize,
+}
+
+impl<'b> Reader<'b> {
+    fn error(&self, kind: LoadErrorKind) -> LoadError {
+        self.error_at(self.offset, kind)
+    }
+
+    fn error_at(&self, offset: usize, kind: LoadErrorKind) -> LoadError {
+        LoadError { kind, offset }
+    }
+
+    fn take(&mut self, length: usize) -> Loaded<&'b [u8]> {
+        if self.bytes.len() - self.offset < length {
+            return Err(self.error(LoadErrorKind::Truncated));
+        }
+        let bytes = &self.bytes[self.offset..self.offset + length];
+        self.offset += length;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Loaded<[u8; N]> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> Loaded<u8> {
+        Ok(self.array::<1>()?[0])
+    }
+
+    fn u16(&mut self) -> Loaded<u16> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+
+    fn u32(&mut self) -> Loaded<u32> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn index(&mut self) -> Loaded<usize> {
+        Ok(self.u32()? as usize)
+    }
+
+    /// Read the number of entries of a section. Every entry takes at least one byte, so a count
+    /// larger than the rest of the file means that it is truncated.
+    fn count(&mut self) -> Loaded<usize> {
+        let count = self.index()?;
+        if count > self.bytes.len() - self.offset {
+            return Err(self.error(LoadErrorKind::Truncated));
+        }
+        Ok(count)
+    }
+
+    fn string(&mut self) -> Loaded<String> {
+        let length = self.index()?;
+        let offset = self.offset;
+        let bytes = self.take(length)?;
+        String::from_utf8(bytes.to_vec())
+            .map_err(|_| self.error_at(offset, LoadErrorKind::InvalidString))
+    }
+
+    /// Read a byte that selects one of `values`
+    fn select<T: Copy>(&mut self, what: &'static str, values: &[T]) -> Loaded<T> {
+        let value = self.u8()?;
+        values
+            .get(value as usize)
+            .copied()
+            .ok_or_else(|| self.invalid(what, value))
+    }
+
+    fn ty(&mut self) -> Loaded<Type> {
+        self.select("type", &TYPES)
+    }
+
+    fn value(&mut self) -> Loaded<Value> {
+        let value = match self.u8()? {
+            0 => Value::Int(i32::from_le_bytes(self.array()?)),
+            1 => Value::Float(f32::from_le_bytes(self.array()?)),
+            2 => Value::Bool(self.select("bool", &[false, true])?),
+            3 => Value::Void,
+            tag => return Err(self.invalid("value tag", tag)),
+        };
+        Ok(value)
+    }
+
+    /// Return an error for the byte that was read last
+    fn invalid(&self, what: &'static str, value: u8) -> LoadError {
+        self.error_at(
+            self.offset - 1,
+            LoadErrorKind::InvalidEncoding { what, value },
+        )
+    }
+
+    /// Read a line table and return its offset and entries
+    fn line_table(&mut self) -> Loaded<(usize, Vec<(usize, Location)>)> {
+        let offset = self.offset;
+        let mut entries = Vec::new();
+        for _ in 0..self.count()? {
+            let ip = self.index()?;
+            let location = Location {
+                line: self.index()?,
+                column: self.index()?,
+                start: self.index()?,
+                end: self.index()?,
+            };
+            entries.push((ip, location));
+        }
+        Ok((offset, entries))
+    }
+
+    fn instruction(&mut self) -> Loaded<Instruction> {
+        use Instruction::*;
+
+        let instruction = match self.u8()? {
+            0 => Constant(self.index()?),
+            1 => LoadLocal(self.index()?),
+            2 => StoreLocal(self.index()?),
+            3 => LoadGlobal(self.index()?),
+            4 => StoreGlobal(self.index()?),
+            5 => Declare {
+                slot: self.index()?,
+                ty: self.ty()?,
+            },
+            6 => Argument(self.ty()?),
+            7 => Unary(self.select("operator", &UNARY_OPERATORS)?),
+            8 => Binary(self.binary_operator(false)?),
+            9 => ShortCircuit {
+                op: self.binary_operator(true)?,
+                target: self.index()?,
+            },
+            10 => Logical(self.binary_operator(true)?),
+            11 => Jump(self.index()?),
+            12 => JumpIfFalse(self.index()?),
+            13 => JumpIfTrue(self.index()?),
+            14 => Call(self.index()?),
+            15 => Return,
+            16 => ReturnVoid,
+            17 => Print,
+            18 => PrintString(self.index()?),
+            19 => Pop,
+            opcode => return Err(self.invalid("opcode", opcode)),
+        };
+        Ok(instruction)
+    }
+
+    /// Read the operator of a [`Instruction::Binary`] or, if `logical`, of an instruction for `&&`
+    /// and `||`
+    fn binary_operator(&mut self, logical: bool) -> Loaded<BinaryOperator> {
+        let op = self.select("operator", &BINARY_OPERATORS)?;
+        let is_logical = matches!(op, BinaryOperator::And | BinaryOperator::Or);
+        if is_logical != logical {
+            return Err(self.invalid("operator", position(&BINARY_OPERATORS, op)));
+        }
+        Ok(op)
+    }
+}
+
+/// Check the indices of an instruction of the function with the given index, except for jump
+/// targets
+fn check_operands(
+    module: &Module,
+    function: usize,
+    instruction: Instruction,
+) -> Result<(), LoadErrorKind> {
+    let (what, index, count) = match instruction {
+        Instruction::Constant(index) => ("constant", index, module.constants.len()),
+        Instruction::PrintString(index) => ("string", index, module.strings.len()),
+        Instruction::LoadGlobal(index) | Instruction::StoreGlobal(index) => {
+            ("global variable", index, module.globals.len())
+        }
+        Instruction::LoadLocal(slot)
+        | Instruction::StoreLocal(slot)
+        | Instruction::Declare { slot, .. } => (
+            "local variable",
+            slot,
+            module.functions[function].locals.len(),
+        ),
+        Instruction::Call(index) => ("function", index, module.functions.len()),
+        _ => return Ok(()),
+    };
+    if index >= count {
+        return Err(LoadErrorKind::IndexOutOfRange { what, index });
+    }
+    Ok(())
+}
+
+/// Return the location of each of the `length` instructions of a function, `None` if the line
+/// table is invalid
+fn expand_line_table(entries: &[(usize, Location)], length: usize) -> Option<Vec<Location>> {
+    if length == 0 {
+        return entries.is_empty().then(Vec::new);
+    }
+    if entries.first()?.0 != 0 {
+        return None;
+    }
+    let mut locations = Vec::with_capacity(length);
+    for (index, &(ip, location)) in entries.iter().enumerate() {
+        let end = entries.get(index + 1).map_or(length, |&(next, _)| next);
+        if end <= ip || end > length {
+            return None;
+        }
+        locations.resize(end, location);
+    }
+    Some(locations)
+}
+
+/// Check that the code of the function with the given index always has the operands of its
+/// instructions on the stack, reaches every instruction with the same stack height, returns with
+/// an otherwise empty stack and never runs past its end. Errors are returned with the index of the
+/// offending instruction.
+fn verify_stack(module: &Module, function: usize) -> Result<(), (usize, LoadErrorKind)> {
+    let code = &module.functions[function].code;
+    if code.is_empty() {
+        return Err((0, LoadErrorKind::MissingReturn));
+    }
+    let mut heights: Vec<Option<usize>> = vec![None; code.len()];
+    heights[0] = Some(0);
+    let mut pending = vec![0];
+    while let Some(ip) = pending.pop() {
+        let height = heights[ip].unwrap();
+        let instruction = code[ip];
+        let (pops, pushes) = stack_effect(module, instruction);
+        if height < pops {
+            return Err((ip, LoadErrorKind::StackUnderflow));
+        }
+        let next = height - pops + pushes;
+
+        let successors = match instruction {
+            Instruction::Return | Instruction::ReturnVoid if next != 0 => {
+                return Err((ip, LoadErrorKind::InconsistentStack))
+            }
+            Instruction::Return | Instruction::ReturnVoid => vec![],
+            Instruction::Jump(target) => vec![(target, next)],
+            Instruction::JumpIfFalse(target) | Instruction::JumpIfTrue(target) => {
+                vec![(target, next), (ip + 1, next)]
+            }
+            // The left operand stays on the stack if the jump is taken
+            Instruction::ShortCircuit { target, .. } => vec![(target, height), (ip + 1, next)],
+            _ => vec![(ip + 1, next)],
+        };
+        for (successor, height) in successors {
+            if successor == code.len() {
+                return Err((ip, LoadErrorKind::MissingReturn));
+            }
+            if successor > code.len() {
+                let kind = LoadErrorKind::IndexOutOfRange {
+                    what: "instruction",
+                    index: successor,
+                };
+                return Err((ip, kind));
+            }
+            match heights[successor] {
+                None => {
+                    heights[successor] = Some(height);
+                    pending.push(successor);
+                }
+                Some(known) if known != height => {
+                    return Err((successor, LoadErrorKind::InconsistentStack))
+                }
+                Some(_) => {}
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Return how many values the instruction pops from and pushes onto the stack when it does not
+/// jump
+fn stack_effect(module: &Module, instruction: Instruction) -> (usize, usize) {
+    match instruction {
+        Instruction::Constant(_) | Instruction::LoadLocal(_) | Instruction::LoadGlobal(_) => (0, 1),
+        Instruction::StoreLocal(_)
+        | Instruction::StoreGlobal(_)
+        | Instruction::Declare { .. }
+        | Instruction::JumpIfFalse(_)
+        | Instruction::JumpIfTrue(_)
+        | Instruction::ShortCircuit { .. }
+        | Instruction::Return
+        | Instruction::Print
+        | Instruction::Pop => (1, 0),
+        Instruction::Argument(_) | Instruction::Unary(_) | Instruction::Logical(_) => (1, 1),
+        Instruction::Binary(_) => (2, 1),
+        Instruction::Call(index) => (module.functions[index].parameters.len(), 1),
+        Instruction::Jump(_) | Instruction::ReturnVoid | Instruction::PrintString(_) => (0, 0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ast::{BinaryOperator, Location, Type};
+    use crate::bytecode::file::{expand_line_table, load, to_bytes, LoadErrorKind, FORMAT_VERSION};
+    use crate::bytecode::{compile, Function, Instruction, Module};
+    use crate::interpreter::Value;
+    use crate::{C1Parser, Dialect};
+
+    fn compile_text(text: &str) -> Module {
+        compile(&C1Parser::parse_program_in(text, Dialect::C1).unwrap()).unwrap()
+    }
+
+    /// A module with a `main` function consisting of the given code
+    fn module_with_code(code: Vec<Instruction>) -> Module {
+        Module {
+            constants: vec![Value::Int(1)],
+            functions: vec![Function {
+                name: "main".to_string(),
+                return_type: Type::Void,
+                parameters: vec![],
+                locals: vec!["x".to_string()],
+                locations: vec![Location::default(); code.len()],
+                code,
+            }],
+            main: Some(0),
+            ..Module::default()
+        }
+    }
+
+    fn load_error(module: &Module) -> LoadErrorKind {
+        load(&to_bytes(module)).unwrap_err().kind
+    }
+
+    #[test]
+    fn round_trip() {
+        let modules = [
+            Module::default(),
+            compile_text(
+                r#"int g = 7; float h = 2; bool b = true; void v;
+                   float f(int a, float b) { return a * b + h; }
+                   int main() {
+                       x = f(2, 1.5) > 1 && !b || false;
+                       for (i = 0; i < 3; i = i + 1) { printf("%\t\n"); }
+                       do { float y; } while (!x);
+                       return g % 2;
+                   }"#,
+            ),
+            compile_text("int f() { return 1; }"),
+        ];
+        for module in modules {
+            let bytes = to_bytes(&module);
+            assert_eq!(load(&bytes), Ok(module));
+        }
+    }
+
+    #[test]
+    fn truncated_files() {
+        let bytes = to_bytes(&compile_text(
+            r#"int g; void main() { printf("text"); x = 1.5; g = 1 + g; }"#,
+        ));
+        for length in 0..bytes.len() {
+            let error = load(&bytes[..length]).unwrap_err();
+            let expected = if length < 4 {
+                LoadErrorKind::NotAModule
+            } else {
+                LoadErrorKind::Truncated
+            };
+            assert_eq!(error.kind, expected, "{} bytes", length);
+            assert!(error.offset <= length);
+        }
+
+        let mut bytes = bytes;
+        bytes.push(0);
+        let error = load(&bytes).unwrap_err();
+        assert_eq!(error.kind, LoadErrorKind::TrailingData);
+        assert_eq!(error.offset, bytes.len() - 1);
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "Unexpected data after the code section at byte {}",
+                error.offset
+            )
+        );
+    }
+
+    #[test]
+    fn invalid_header() {
+        let mut bytes = to_bytes(&Module::default());
+        bytes[0] = b'X';
+        assert_eq!(load(&bytes).unwrap_err().kind, LoadErrorKind::NotAModule);
+
+        let mut bytes = to_bytes(&Module::default());
+        bytes[4..6].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+        let error = load(&bytes).unwrap_err();
+        assert_eq!(
+            error.kind,
+            LoadErrorKind::UnsupportedVersion(FORMAT_VERSION + 1)
+        );
+        assert_eq!(error.offset, 4);
+        assert_eq!(
+            error.message(),
+            format!(
+                "Unsupported format version {}, expected {}",
+                FORMAT_VERSION + 1,
+                FORMAT_VERSION
+            )
+        );
+    }
+
+    #[test]
+    fn invalid_encoding() {
+        // The last byte is the opcode of the final return
+        let mut bytes = to_bytes(&module_with_code(vec![Instruction::ReturnVoid]));
+        *bytes.last_mut().unwrap() = 200;
+        let error = load(&bytes).unwrap_err();
+        assert_eq!(
+            error.kind,
+            LoadErrorKind::InvalidEncoding {
+                what: "opcode",
+                value: 200
+            }
+        );
+        assert_eq!(error.offset, bytes.len() - 1);
+
+        // && is not a binary operator for Instruction::Binary
+        let mut bytes = to_bytes(&module_with_code(vec![
+            Instruction::Constant(0),
+            Instruction::Constant(0),
+            Instruction::Binary(BinaryOperator::Add),
+            Instruction::Pop,
+            Instruction::ReturnVoid,
+        ]));
+        let operator = bytes.len() - 3;
+        bytes[operator] = 12;
+        assert_eq!(
+            load(&bytes).unwrap_err().kind,
+            LoadErrorKind::InvalidEncoding {
+                what: "operator",
+                value: 12
+            }
+        );
+
+        let mut module = Module::default();
+        module.strings.push("ä".to_string());
+        let mut bytes = to_bytes(&module);
+        let string = bytes
+            .windows(2)
+            .position(|window| window == "ä".as_bytes())
+            .unwrap();
+        bytes[string] = 0xff;
+        assert_eq!(load(&bytes).unwrap_err().kind, LoadErrorKind::InvalidString);
+    }
+
+    #[test]
+    fn invalid_indices() {
+        use Instruction::*;
+
+        for (instruction, what) in [
+            (Constant(1), "constant"),
+            (LoadLocal(1), "local variable"),
+            (LoadGlobal(0), "global variable"),
+            (PrintString(0), "string"),
+            (Call(1), "function"),
+        ] {
+            let module = module_with_code(vec![instruction, ReturnVoid]);
+            let error = load(&to_bytes(&module)).unwrap_err();
+            let index = match what {
+                "constant" | "local variable" | "function" => 1,
+                _ => 0,
+            };
+            assert_eq!(error.kind, LoadErrorKind::IndexOutOfRange { what, index });
+        }
+
+        let mut module = module_with_code(vec![ReturnVoid]);
+        module.main = Some(1);
+        assert_eq!(
+            load_error(&module),
+            LoadErrorKind::IndexOutOfRange {
+                what: "function",
+                index: 1
+            }
+        );
+
+        assert_eq!(
+            load_error(&module_with_code(vec![Jump(5)])),
+            LoadErrorKind::IndexOutOfRange {
+                what: "instruction",
+                index: 5
+            }
+        );
+
+        let mut module = module_with_code(vec![ReturnVoid]);
+        module.functions[0].parameters.push(Type::Int);
+        module.functions[0].locals.clear();
+        assert_eq!(
+            load_error(&module),
+            LoadErrorKind::MissingParameterSlots {
+                function: "main".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn invalid_code() {
+        use Instruction::*;
+
+        assert_eq!(
+            load_error(&module_with_code(vec![Pop, ReturnVoid])),
+            LoadErrorKind::StackUnderflow
+        );
+        assert_eq!(
+            load_error(&module_with_code(vec![Constant(0), ReturnVoid])),
+            LoadErrorKind::InconsistentStack
+        );
+        // The loop pushes a value in every iteration
+        assert_eq!(
+            load_error(&module_with_code(vec![Constant(0), Jump(0)])),
+            LoadErrorKind::InconsistentStack
+        );
+        assert_eq!(
+            load_error(&module_with_code(vec![Constant(0), Pop])),
+            LoadErrorKind::MissingReturn
+        );
+        assert_eq!(
+            load_error(&module_with_code(vec![])),
+            LoadErrorKind::MissingReturn
+        );
+
+        let bytes = to_bytes(&module_with_code(vec![
+            Constant(0),
+            StoreLocal(0),
+            Binary(BinaryOperator::Add),
+            ReturnVoid,
+        ]));
+        let error = load(&bytes).unwrap_err();
+        assert_eq!(error.kind, LoadErrorKind::StackUnderflow);
+        // The offset of the opcode of the binary instruction, behind two instructions of five bytes
+        assert_eq!(error.offset, bytes.len() - 3);
+        assert_eq!(bytes[error.offset], 8);
+
+        // Unreachable code is not checked
+        assert!(load(&to_bytes(&module_with_code(vec![ReturnVoid, Pop]))).is_ok());
+    }
+
+    #[test]
+    fn invalid_line_table() {
+        let location = |line| Location {
+            line,
+            ..Location::default()
+        };
+        assert_eq!(
+            expand_line_table(&[(0, location(1)), (2, location(2))], 3),
+            Some(vec![location(1), location(1), location(2)])
+        );
+        assert_eq!(expand_line_table(&[], 0), Some(vec![]));
+        assert_eq!(expand_line_table(&[], 1), None);
+        assert_eq!(expand_line_table(&[(1, location(1))], 2), None);
+        assert_eq!(
+            expand_line_table(&[(0, location(1)), (0, location(2))], 2),
+            None
+        );
+        assert_eq!(
+            expand_line_table(&[(0, location(1)), (2, location(2))], 2),
+            None
+        );
+
+        // A location without instruction
+        let mut module = module_with_code(vec![]);
+        module.functions[0].locations.push(location(1));
+        assert_eq!(load_error(&module), LoadErrorKind::InvalidLineTable);
+    }
+}
@@ -0,0 +1,1124 @@
+//! C code generator for C(-1) programs.
+//!
+//! [`emit`] translates a checked [`Program`] into a standalone ISO C99 source file that behaves
+//! like the [interpreter](crate::interpreter) when compiled with `cc -std=c99`:
+//! - `bool`, `int`, `float` and `void` are the C types of the same names, with `bool` from
+//!   `<stdbool.h>`.
+//! - Every function gets a prototype, so functions can call each other in any order. The C1
+//!   `main` function is called by a C `main` function, which returns its value if it is an `int`
+//!   and 0 otherwise.
+//! - The variables of a function are declared at its start, since a name refers to the same
+//!   variable everywhere in a C(-1) function. Variables that are only assigned are declared with
+//!   the type the [type checker](crate::typecheck) inferred for them.
+//! - `printf` prints an `int` with `%d`, a `float` with `%f` and a `bool` as `true` or `false`,
+//!   followed by a newline. Strings are written as they are.
+//! - `int` arithmetic wraps around on overflow and dividing by zero prints the runtime error to
+//!   `stderr` and exits with status 1. Both are implemented by small helper functions, which are
+//!   only emitted if the program needs them.
+//! - C leaves the evaluation order of operands and arguments unspecified. If it could make a
+//!   difference, the operands are assigned to temporary variables from left to right first.
+//!
+//! Names that are C keywords or are declared by the included headers get a `_` appended, functions
+//! whose name is also used for a variable get a `fn_` prefix. Since C(-1) identifiers cannot
+//! contain `_`, neither clashes with other names.
+//!
+//! Reading a variable before its first assignment reads zero instead of being an error, and the
+//! depth of nested calls is not limited.
+//!
+//! ```
+//! use cb_3::cgen::emit;
+//! use cb_3::typecheck::check;
+//! use cb_3::C1Parser;
+//!
+//! let program = C1Parser::parse_program("void main() { x = 6; printf(x * 7); }").unwrap();
+//! let types = check(&program).unwrap();
+//! assert_eq!(
+//!     emit(&program, &types),
+//!     "\
+//! #include <stdbool.h>
+//! #include <stdio.h>
+//! #include <stdlib.h>
+//!
+//! static int c1_mul(int lhs, int rhs) {
+//!     return (int)((unsigned)lhs * (unsigned)rhs);
+//! }
+//!
+//! static void fn_main(void);
+//!
+//! static void fn_main(void) {
+//!     int x = 0;
+//!
+//!     x = 6;
+//!     printf(\"%d\\n\", c1_mul(x, 7));
+//! }
+//!
+//! int main(void) {
+//!     fn_main();
+//!     return 0;
+//! }
+//! "
+//! );
+//! ```
+
+use crate::ast::{
+    BinaryOperator, Expr, ExprKind, FunctionCall, FunctionDefinition, Identifier, Literal,
+    PrintfArgument, Program, Statement, StatementKind, Type, UnaryOperator,
+};
+use crate::typecheck::{FunctionTypes, TypeInfo};
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt::Write;
+
+/// Names that cannot be used in the generated code: C keywords, the names declared by the included
+/// headers and `main`. Names containing `_` are left out, since they are no C(-1) identifiers.
+const RESERVED: &[&str] = &[
+    // Keywords of C99 and later standards
+    "alignas",
+    "alignof",
+    "auto",
+    "bool",
+    "break",
+    "case",
+    "char",
+    "const",
+    "constexpr",
+    "continue",
+    "default",
+    "do",
+    "double",
+    "else",
+    "enum",
+    "extern",
+    "false",
+    "float",
+    "for",
+    "goto",
+    "if",
+    "inline",
+    "int",
+    "long",
+    "nullptr",
+    "register",
+    "restrict",
+    "return",
+    "short",
+    "signed",
+    "sizeof",
+    "static",
+    "struct",
+    "switch",
+    "true",
+    "typedef",
+    "typeof",
+    "union",
+    "unsigned",
+    "void",
+    "volatile",
+    "while",
+    // <stdio.h>
+    "BUFSIZ",
+    "EOF",
+    "FILE",
+    "NULL",
+    "clearerr",
+    "fclose",
+    "feof",
+    "ferror",
+    "fflush",
+    "fgetc",
+    "fgetpos",
+    "fgets",
+    "fopen",
+    "fprintf",
+    "fputc",
+    "fputs",
+    "fread",
+    "freopen",
+    "fscanf",
+    "fseek",
+    "fsetpos",
+    "ftell",
+    "fwrite",
+    "getc",
+    "getchar",
+    "gets",
+    "perror",
+    "printf",
+    "putc",
+    "putchar",
+    "puts",
+    "remove",
+    "rename",
+    "rewind",
+    "scanf",
+    "setbuf",
+    "setvbuf",
+    "snprintf",
+    "sprintf",
+    "sscanf",
+    "stderr",
+    "stdin",
+    "stdout",
+    "tmpfile",
+    "tmpnam",
+    "ungetc",
+    "vfprintf",
+    "vfscanf",
+    "vprintf",
+    "vscanf",
+    "vsnprintf",
+    "vsprintf",
+    "vsscanf",
+    // <stdlib.h>
+    "abort",
+    "abs",
+    "atexit",
+    "atof",
+    "atoi",
+    "atol",
+    "atoll",
+    "bsearch",
+    "calloc",
+    "div",
+    "exit",
+    "free",
+    "getenv",
+    "labs",
+    "ldiv",
+    "llabs",
+    "lldiv",
+    "malloc",
+    "mblen",
+    "mbstowcs",
+    "mbtowc",
+    "qsort",
+    "rand",
+    "realloc",
+    "srand",
+    "strtod",
+    "strtof",
+    "strtol",
+    "strtold",
+    "strtoll",
+    "strtoul",
+    "strtoull",
+    "system",
+    "wcstombs",
+    "wctomb",
+    // The C entry point, which calls the C1 `main` function
+    "main",
+];
+
+/// Functions of the generated code that implement the semantics of C(-1) operators. They are
+/// defined in the order of the variants, which is also the order of their dependencies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Helper {
+    Add,
+    Subtract,
+    Multiply,
+    Negate,
+    DivisionByZero,
+    Divide,
+    Modulo,
+    PrintFloat,
+}
+
+impl Helper {
+    fn name(self) -> &'static str {
+        match self {
+            Helper::Add => "c1_add",
+            Helper::Subtract => "c1_sub",
+            Helper::Multiply => "c1_mul",
+            Helper::Negate => "c1_neg",
+            Helper::DivisionByZero => "c1_division_by_zero",
+            Helper::Divide => "c1_div",
+            Helper::Modulo => "c1_mod",
+            Helper::PrintFloat => "c1_print_float",
+        }
+    }
+
+    fn dependencies(self) -> &'static [Helper] {
+        match self {
+            Helper::Divide => &[Helper::Negate, Helper::DivisionByZero],
+            Helper::Modulo => &[Helper::DivisionByZero],
+            _ => &[],
+        }
+    }
+
+    /// The C definition. Converting an `unsigned` that does not fit into an `int` is
+    /// implementation-defined, all common compilers wrap around.
+    fn definition(self) -> &'static str {
+        match self {
+            Helper::Add => {
+                "static int c1_add(int lhs, int rhs) {\n    \
+                 return (int)((unsigned)lhs + (unsigned)rhs);\n}\n"
+            }
+            Helper::Subtract => {
+                "static int c1_sub(int lhs, int rhs) {\n    \
+                 return (int)((unsigned)lhs - (unsigned)rhs);\n}\n"
+            }
+            Helper::Multiply => {
+                "static int c1_mul(int lhs, int rhs) {\n    \
+                 return (int)((unsigned)lhs * (unsigned)rhs);\n}\n"
+            }
+            Helper::Negate => {
+                "static int c1_neg(int value) {\n    \
+                 return (int)(0u - (unsigned)value);\n}\n"
+            }
+            Helper::DivisionByZero => {
+                "static void c1_division_by_zero(int line) {\n    \
+                 fprintf(stderr, \"Division by zero at line %d\\n\", line);\n    \
+                 exit(EXIT_FAILURE);\n}\n"
+            }
+            // Dividing the smallest int by -1 overflows
+            Helper::Divide => {
+                "static int c1_div(int lhs, int rhs, int line) {\n    \
+                 if (rhs == 0) c1_division_by_zero(line);\n    \
+                 return rhs == -1 ? c1_neg(lhs) : lhs / rhs;\n}\n"
+            }
+            Helper::Modulo => {
+                "static int c1_mod(int lhs, int rhs, int line) {\n    \
+                 if (rhs == 0) c1_division_by_zero(line);\n    \
+                 return rhs == -1 ? 0 : lhs % rhs;\n}\n"
+            }
+            // Print NaN the way the interpreter does, the C library may add a sign
+            Helper::PrintFloat => {
+                "static void c1_print_float(float value) {\n    \
+                 if (value != value) puts(\"NaN\");\n    \
+                 else printf(\"%f\\n\", value);\n}\n"
+            }
+        }
+    }
+}
+
+/// Translate the program into C source code.
+///
+/// The program has to pass [`semantic::analyze`](crate::semantic::analyze) and
+/// [`typecheck::check`](crate::typecheck::check), which returned the types.
+pub fn emit(program: &Program, types: &TypeInfo) -> String {
+    let mut variables: HashSet<&str> = program
+        .globals
+        .iter()
+        .map(|global| global.name.name.as_str())
+        .collect();
+    for function in &program.functions {
+        let function_types = function_types(types, &function.name);
+        for (name, _) in function_types
+            .parameters
+            .iter()
+            .chain(&function_types.variables)
+        {
+            variables.insert(name);
+        }
+    }
+    let functions = program
+        .functions
+        .iter()
+        .map(|function| {
+            let name = function.name.name.as_str();
+            let c_name = if variables.contains(name) || RESERVED.contains(&name) {
+                format!("fn_{}", name)
+            } else {
+                name.to_string()
+            };
+            (name, c_name)
+        })
+        .collect();
+
+    let mut emitter = Emitter {
+        types,
+        functions,
+        helpers: BTreeSet::new(),
+        function: "",
+        return_type: Type::Void,
+        temporaries: Vec::new(),
+        code: String::new(),
+        indent: 0,
+    };
+    let mut sections = Vec::new();
+
+    let mut globals = String::new();
+    for global in &program.globals {
+        let name = variable_name(&global.name.name);
+        match &global.value {
+            Some(value) => writeln!(
+                globals,
+                "static {} {} = {};",
+                global.ty,
+                name,
+                literal(value)
+            ),
+            None => writeln!(globals, "static {} {};", global.ty, name),
+        }
+        .unwrap();
+    }
+
+    let mut prototypes = String::new();
+    let mut definitions = Vec::new();
+    for function in &program.functions {
+        writeln!(prototypes, "{};", emitter.signature(function)).unwrap();
+        definitions.push(emitter.function(function));
+    }
+
+    let mut helpers: Vec<Helper> = emitter.helpers.iter().copied().collect();
+    for helper in emitter.helpers.iter() {
+        helpers.extend(helper.dependencies());
+    }
+    helpers.sort();
+    helpers.dedup();
+
+    sections.push("#include <stdbool.h>\n#include <stdio.h>\n#include <stdlib.h>\n".to_string());
+    sections.extend(helpers.iter().map(|helper| helper.definition().to_string()));
+    sections.push(globals);
+    sections.push(prototypes);
+    sections.extend(definitions);
+    if let Some(main) = program
+        .functions
+        .iter()
+        .find(|function| function.name.name == "main")
+    {
+        let name = &emitter.functions["main"];
+        sections.push(if main.return_type == Type::Int {
+            format!("int main(void) {{\n    return {}();\n}}\n", name)
+        } else {
+            format!("int main(void) {{\n    {}();\n    return 0;\n}}\n", name)
+        });
+    }
+
+    sections.retain(|section| !section.is_empty());
+    sections.join("\n")
+}
+
+/// Return the name of a variable in the generated code
+fn variable_name(name: &str) -> Cow<'_, str> {
+    if RESERVED.contains(&name) {
+        Cow::Owned(format!("{}_", name))
+    } else {
+        Cow::Borrowed(name)
+    }
+}
+
+fn function_types<'t>(types: &'t TypeInfo, name: &Identifier) -> &'t FunctionTypes {
+    types
+        .function(&name.name)
+        .expect("function of a checked program")
+}
+
+fn literal(literal: &Literal) -> String {
+    match literal {
+        Literal::Int(value) => value.to_string(),
+        // The shortest representation that reads back as the same float
+        Literal::Float(value) if value.is_finite() => format!("{:?}f", value),
+        Literal::Float(_) => "(1.0f / 0.0f)".to_string(),
+        Literal::Bool(value) => value.to_string(),
+    }
+}
+
+/// The value of a declared variable without initializer
+fn zero(ty: Type) -> &'static str {
+    match ty {
+        Type::Bool => "false",
+        Type::Float => "0.0f",
+        _ => "0",
+    }
+}
+
+/// Return the string as a C string literal
+fn string_literal(text: &str) -> String {
+    let mut literal = String::from("\"");
+    let mut previous = '\0';
+    for c in text.chars() {
+        match c {
+            '"' => literal.push_str("\\\""),
+            '\\' => literal.push_str("\\\\"),
+            '\n' => literal.push_str("\\n"),
+            '\t' => literal.push_str("\\t"),
+            '\r' => literal.push_str("\\r"),
+            // Avoid trigraphs like ??/
+            '?' if previous == '?' => literal.push_str("\\?"),
+            ' '..='~' => literal.push(c),
+            _ => {
+                let mut bytes = [0; 4];
+                for byte in c.encode_utf8(&mut bytes).bytes() {
+                    write!(literal, "\\{:03o}", byte).unwrap();
+                }
+            }
+        }
+        previous = c;
+    }
+    literal.push('"');
+    literal
+}
+
+/// Add the variables the expression assigns and return whether it calls a function
+fn effects<'e>(expr: &'e Expr, assigned: &mut Vec<&'e str>) -> bool {
+    match &expr.kind {
+        ExprKind::Literal(_) | ExprKind::Variable(_) => false,
+        ExprKind::Call(call) => {
+            for argument in &call.arguments {
+                effects(argument, assigned);
+            }
+            true
+        }
+        ExprKind::Assignment { target, value } => {
+            assigned.push(&target.name);
+            effects(value, assigned)
+        }
+        ExprKind::Unary { operand, .. } => effects(operand, assigned),
+        ExprKind::Binary { lhs, rhs, .. } => {
+            let calls = effects(lhs, assigned);
+            effects(rhs, assigned) || calls
+        }
+    }
+}
+
+/// Add the variables the expression reads
+fn reads<'e>(expr: &'e Expr, names: &mut Vec<&'e str>) {
+    match &expr.kind {
+        ExprKind::Literal(_) => {}
+        ExprKind::Variable(name) => names.push(&name.name),
+        ExprKind::Call(call) => {
+            for argument in &call.arguments {
+                reads(argument, names);
+            }
+        }
+        ExprKind::Assignment { value, .. } => reads(value, names),
+        ExprKind::Unary { operand, .. } => reads(operand, names),
+        ExprKind::Binary { lhs, rhs, .. } => {
+            reads(lhs, names);
+            reads(rhs, names);
+        }
+    }
+}
+
+struct Emitter<'a> {
+    types: &'a TypeInfo,
+    /// The names of the functions in the generated code
+    functions: HashMap<&'a str, String>,
+    helpers: BTreeSet<Helper>,
+    /// Name of the current function
+    function: &'a str,
+    return_type: Type,
+    /// The types of the temporary variables of the current function
+    temporaries: Vec<Type>,
+    /// The statements of the current function
+    code: String,
+    indent: usize,
+}
+
+impl<'a> Emitter<'a> {
+    fn signature(&self, function: &FunctionDefinition) -> String {
+        let parameters = if function.parameters.is_empty() {
+            "void".to_string()
+        } else {
+            function
+                .parameters
+                .iter()
+                .map(|parameter| {
+                    format!("{} {}", parameter.ty, variable_name(&parameter.name.name))
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        format!(
+            "static {} {}({})",
+            function.return_type,
+            self.functions[function.name.name.as_str()],
+            parameters
+        )
+    }
+
+    fn function(&mut self, function: &'a FunctionDefinition) -> String {
+        self.function = &function.name.name;
+        self.return_type = function.return_type;
+        self.indent = 1;
+        for statement in &function.body {
+            self.statement(statement);
+        }
+
+        let mut declarations = String::new();
+        for (name, ty) in &function_types(self.types, &function.name).variables {
+            writeln!(
+                declarations,
+                "    {} {} = {};",
+                ty,
+                variable_name(name),
+                zero(*ty)
+            )
+            .unwrap();
+        }
+        for (i, ty) in self.temporaries.drain(..).enumerate() {
+            writeln!(declarations, "    {} tmp_{};", ty, i).unwrap();
+        }
+        if !declarations.is_empty() && !self.code.is_empty() {
+            declarations.push('\n');
+        }
+
+        let code = std::mem::take(&mut self.code);
+        format!(
+            "{} {{\n{}{}}}\n",
+            self.signature(function),
+            declarations,
+            code
+        )
+    }
+
+    fn line(&mut self, line: &str) {
+        for _ in 0..self.indent {
+            self.code.push_str("    ");
+        }
+        self.code.push_str(line);
+        self.code.push('\n');
+    }
+
+    /// Emit the body of a control statement, which is enclosed in braces by the caller
+    fn body(&mut self, statement: &Statement) {
+        self.indent += 1;
+        match &statement.kind {
+            StatementKind::Block(statements) => {
+                for statement in statements {
+                    self.statement(statement);
+                }
+            }
+            _ => self.statement(statement),
+        }
+        self.indent -= 1;
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        match &statement.kind {
+            StatementKind::Block(statements) => {
+                self.line("{");
+                self.indent += 1;
+                for statement in statements {
+                    self.statement(statement);
+                }
+                self.indent -= 1;
+                self.line("}");
+            }
+            StatementKind::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                let condition = self.expr(condition);
+                self.line(&format!("if ({}) {{", condition));
+                self.body(then_branch);
+                let mut else_branch = else_branch;
+                while let Some(statement) = else_branch {
+                    match &statement.kind {
+                        StatementKind::If {
+                            condition,
+                            then_branch,
+                            else_branch: next,
+                        } => {
+                            let condition = self.expr(condition);
+                            self.line(&format!("}} else if ({}) {{", condition));
+                            self.body(then_branch);
+                            else_branch = next;
+                        }
+                        _ => {
+                            self.line("} else {");
+                            self.body(statement);
+                            else_branch = &None;
+                        }
+                    }
+                }
+                self.line("}");
+            }
+            StatementKind::While { condition, body } => {
+                let condition = self.expr(condition);
+                self.line(&format!("while ({}) {{", condition));
+                self.body(body);
+                self.line("}");
+            }
+            StatementKind::DoWhile { body, condition } => {
+                self.line("do {");
+                self.body(body);
+                let condition = self.expr(condition);
+                self.line(&format!("}} while ({});", condition));
+            }
+            StatementKind::For {
+                init,
+                condition,
+                update,
+                body,
+            } => {
+                let mut header = String::from("for (");
+                if let Some(init) = init {
+                    header.push_str(&self.for_assignment(init));
+                }
+                header.push(';');
+                if let Some(condition) = condition {
+                    header.push(' ');
+                    header.push_str(&self.expr(condition));
+                }
+                header.push(';');
+                if let Some(update) = update {
+                    header.push(' ');
+                    header.push_str(&self.for_assignment(update));
+                }
+                header.push_str(") {");
+                self.line(&header);
+                self.body(body);
+                self.line("}");
+            }
+            StatementKind::Declaration { ty, name, value } => {
+                let value = match value {
+                    Some(value) => self.expr(value),
+                    None => zero(*ty).to_string(),
+                };
+                self.line(&format!("{} = {};", variable_name(&name.name), value));
+            }
+            StatementKind::Return(None) => self.line("return;"),
+            // A void function can return the result of a void function, which C does not allow
+            StatementKind::Return(Some(value)) if self.return_type == Type::Void => {
+                let value = self.expr(value);
+                self.line(&format!("{};", value));
+                self.line("return;");
+            }
+            StatementKind::Return(Some(value)) => {
+                let value = self.expr(value);
+                self.line(&format!("return {};", value));
+            }
+            // fputs would stop at a null character
+            StatementKind::Printf(PrintfArgument::String(text)) if text.contains('\0') => {
+                self.line(&format!(
+                    "fwrite({}, 1, {}, stdout);",
+                    string_literal(text),
+                    text.len()
+                ));
+            }
+            StatementKind::Printf(PrintfArgument::String(text)) => {
+                self.line(&format!("fputs({}, stdout);", string_literal(text)));
+            }
+            StatementKind::Printf(PrintfArgument::Value(value)) => {
+                let line = match self.types.expr_type(self.function, value) {
+                    Type::Int => format!("printf(\"%d\\n\", {});", self.expr(value)),
+                    Type::Float => {
+                        self.helpers.insert(Helper::PrintFloat);
+                        format!("c1_print_float({});", self.expr(value))
+                    }
+                    _ => format!("puts({} ? \"true\" : \"false\");", self.operand(value)),
+                };
+                self.line(&line);
+            }
+            StatementKind::Assignment { target, value } => {
+                let assignment = self.assignment(target, value);
+                self.line(&format!("{};", assignment));
+            }
+            StatementKind::Call(call) => {
+                let call = self.call(call);
+                self.line(&format!("{};", call));
+            }
+        }
+    }
+
+    /// Return the initialization or update of a for loop, which is an assignment statement
+    fn for_assignment(&mut self, statement: &Statement) -> String {
+        match &statement.kind {
+            StatementKind::Assignment { target, value } => self.assignment(target, value),
+            _ => unreachable!("for loops contain assignment statements"),
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) -> String {
+        match &expr.kind {
+            ExprKind::Literal(value) => literal(value),
+            ExprKind::Variable(name) => variable_name(&name.name).into_owned(),
+            ExprKind::Call(call) => self.call(call),
+            ExprKind::Assignment { target, value } => self.assignment(target, value),
+            ExprKind::Unary {
+                op: UnaryOperator::Plus,
+                operand,
+            } => self.expr(operand),
+            // Negating a literal cannot overflow
+            ExprKind::Unary {
+                op: UnaryOperator::Minus,
+                operand,
+            } if matches!(operand.kind, ExprKind::Literal(Literal::Int(_))) => {
+                format!("-{}", self.expr(operand))
+            }
+            ExprKind::Unary {
+                op: UnaryOperator::Minus,
+                operand,
+            } if self.is_int(operand) => {
+                self.helpers.insert(Helper::Negate);
+                format!("c1_neg({})", self.expr(operand))
+            }
+            ExprKind::Unary { op, operand } => {
+                let symbol = match op {
+                    UnaryOperator::Not => "!",
+                    _ => "-",
+                };
+                let mut operand = self.operand(operand);
+                // Keep - -x from becoming the decrement operator
+                if operand.starts_with('-') {
+                    operand = format!("({})", operand);
+                }
+                format!("{}{}", symbol, operand)
+            }
+            ExprKind::Binary { op, lhs, rhs } => {
+                // && and || evaluate their operands in order
+                if matches!(op, BinaryOperator::And | BinaryOperator::Or) {
+                    return format!("{} {} {}", self.operand(lhs), op, self.operand(rhs));
+                }
+                match self.helper(*op, lhs, rhs) {
+                    Some(helper) => {
+                        self.helpers.insert(helper);
+                        let (prefix, mut operands) = self.ordered(&[lhs, rhs], false);
+                        if matches!(helper, Helper::Divide | Helper::Modulo) {
+                            operands.push(expr.location.line.to_string());
+                        }
+                        sequenced(
+                            prefix,
+                            format!("{}({})", helper.name(), operands.join(", ")),
+                        )
+                    }
+                    None => {
+                        let (prefix, mut operands) = self.ordered(&[lhs, rhs], true);
+                        // C compilers reject dividing by a constant int zero even if the
+                        // quotient is a float
+                        if *op == BinaryOperator::Divide && self.is_int(rhs) {
+                            operands[1] = format!("(float){}", operands[1]);
+                        }
+                        sequenced(prefix, format!("{} {} {}", operands[0], op, operands[1]))
+                    }
+                }
+            }
+        }
+    }
+
+    /// Translate an assignment. If the value assigns the target as well, C would modify it twice
+    /// without a sequence point in between, so the value is assigned to a temporary variable
+    /// first.
+    fn assignment(&mut self, target: &Identifier, value: &Expr) -> String {
+        let name = variable_name(&target.name);
+        let code = self.expr(value);
+        let mut assigned = Vec::new();
+        effects(value, &mut assigned);
+        if !assigned.contains(&target.name.as_str()) {
+            return format!("{} = {}", name, code);
+        }
+        let ty = self.types.expr_type(self.function, value);
+        let temporary = format!("tmp_{}", self.temporaries.len());
+        self.temporaries.push(ty);
+        if matches!(value.kind, ExprKind::Assignment { .. }) {
+            format!("({} = ({}), {} = {})", temporary, code, name, temporary)
+        } else {
+            format!("({} = {}, {} = {})", temporary, code, name, temporary)
+        }
+    }
+
+    /// Return the helper function that implements an operator for the operands, if any
+    fn helper(&self, op: BinaryOperator, lhs: &Expr, rhs: &Expr) -> Option<Helper> {
+        if !(self.is_int(lhs) && self.is_int(rhs)) {
+            return None;
+        }
+        match op {
+            BinaryOperator::Add => Some(Helper::Add),
+            BinaryOperator::Subtract => Some(Helper::Subtract),
+            BinaryOperator::Multiply => Some(Helper::Multiply),
+            BinaryOperator::Divide => Some(Helper::Divide),
+            BinaryOperator::Modulo => Some(Helper::Modulo),
+            _ => None,
+        }
+    }
+
+    /// Return the expression as an operand of a C operator, in parentheses if needed
+    fn operand(&mut self, expr: &Expr) -> String {
+        let code = self.expr(expr);
+        if self.is_primary(expr) {
+            code
+        } else {
+            format!("({})", code)
+        }
+    }
+
+    /// Return whether the code of the expression binds tighter than any binary C operator
+    fn is_primary(&self, expr: &Expr) -> bool {
+        match &expr.kind {
+            ExprKind::Literal(_) | ExprKind::Variable(_) | ExprKind::Call(_) => true,
+            ExprKind::Assignment { .. } => false,
+            ExprKind::Unary { .. } => true,
+            ExprKind::Binary { op, lhs, rhs } => {
+                self.helper(*op, lhs, rhs).is_some()
+                    || (!matches!(op, BinaryOperator::And | BinaryOperator::Or)
+                        && self.dependent(&[lhs, rhs]).len() >= 2)
+            }
+        }
+    }
+
+    fn call(&mut self, call: &FunctionCall) -> String {
+        let arguments: Vec<_> = call.arguments.iter().collect();
+        let (prefix, arguments) = self.ordered(&arguments, false);
+        let code = format!(
+            "{}({})",
+            self.functions[call.name.name.as_str()],
+            arguments.join(", ")
+        );
+        sequenced(prefix, code)
+    }
+
+    /// Translate operands that are evaluated from left to right, as operands of a C operator or
+    /// as arguments. If the order can matter, all [dependent](Self::dependent) operands but the
+    /// last are assigned to temporary variables first. Return these assignments, each followed by
+    /// a comma, and the code of the operands.
+    fn ordered(&mut self, operands: &[&Expr], as_operands: bool) -> (String, Vec<String>) {
+        let mut code: Vec<_> = operands
+            .iter()
+            .map(|operand| match as_operands {
+                true => self.operand(operand),
+                false => self.expr(operand),
+            })
+            .collect();
+        let mut prefix = String::new();
+        let dependent = self.dependent(operands);
+        for &i in dependent.iter().take(dependent.len().saturating_sub(1)) {
+            let ty = self.types.expr_type(self.function, operands[i]);
+            let temporary = format!("tmp_{}", self.temporaries.len());
+            self.temporaries.push(ty);
+            if !as_operands && matches!(operands[i].kind, ExprKind::Assignment { .. }) {
+                write!(prefix, "{} = ({}), ", temporary, code[i]).unwrap();
+            } else {
+                write!(prefix, "{} = {}, ", temporary, code[i]).unwrap();
+            }
+            code[i] = temporary;
+        }
+        (prefix, code)
+    }
+
+    /// Return the indices of the operands whose evaluation can change the value of another
+    /// operand or whose value can be changed by the evaluation of another operand. If there are
+    /// fewer than two, the evaluation order does not matter.
+    fn dependent(&self, operands: &[&Expr]) -> Vec<usize> {
+        (0..operands.len())
+            .filter(|&i| {
+                let mut assigned = Vec::new();
+                if effects(operands[i], &mut assigned) || !assigned.is_empty() {
+                    return true;
+                }
+                let mut calls = false;
+                for (j, other) in operands.iter().enumerate() {
+                    if j != i {
+                        calls |= effects(other, &mut assigned);
+                    }
+                }
+                let mut names = Vec::new();
+                reads(operands[i], &mut names);
+                // Called functions can only change global variables
+                names
+                    .iter()
+                    .any(|name| assigned.contains(name) || (calls && !self.is_local(name)))
+            })
+            .collect()
+    }
+
+    fn is_local(&self, name: &str) -> bool {
+        self.types
+            .function(self.function)
+            .and_then(|types| types.variable(name))
+            .is_some()
+    }
+
+    fn is_int(&self, expr: &Expr) -> bool {
+        self.types.expr_type(self.function, expr) == Type::Int
+    }
+}
+
+/// Prefix the code with the assignments of its temporary variables
+fn sequenced(prefix: String, code: String) -> String {
+    if prefix.is_empty() {
+        code
+    } else {
+        format!("({}{})", prefix, code)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::emit;
+    use crate::semantic::analyze;
+    use crate::typecheck::check;
+    use crate::{C1Parser, Dialect};
+
+    fn emit_text(text: &str) -> String {
+        let program = C1Parser::parse_program_in(text, Dialect::C1).unwrap();
+        analyze(&program).unwrap();
+        let types = check(&program).unwrap();
+        emit(&program, &types)
+    }
+
+    /// Return the statements emitted for the body of main
+    fn main_body(body: &str) -> String {
+        let code = emit_text(&format!("void main() {{ {} }}", body));
+        let start = code.find("static void fn_main(void) {\n").unwrap();
+        let body = &code[start..];
+        let body = &body[body.find('\n').unwrap() + 1..body.find("\n}\n").unwrap()];
+        // Skip the declarations
+        let body = body
+            .rsplit_once("\n\n")
+            .map_or(body, |(_, statements)| statements);
+        body.lines()
+            .map(|line| line.trim_start())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn program_structure() {
+        let text = "
+float scale = 2;
+bool verbose;
+
+int twice(int n) { return helper(n) * 2; }
+
+int helper(int n) { printf(\"helping\\n\"); return n; }
+
+int main() { count = twice(3); float f = count; printf(f * scale); return count; }
+";
+        assert_eq!(
+            emit_text(text),
+            "\
+#include <stdbool.h>
+#include <stdio.h>
+#include <stdlib.h>
+
+static int c1_mul(int lhs, int rhs) {
+    return (int)((unsigned)lhs * (unsigned)rhs);
+}
+
+static void c1_print_float(float value) {
+    if (value != value) puts(\"NaN\");
+    else printf(\"%f\\n\", value);
+}
+
+static float scale = 2;
+static bool verbose;
+
+static int twice(int n);
+static int helper(int n);
+static int fn_main(void);
+
+static int twice(int n) {
+    return c1_mul(helper(n), 2);
+}
+
+static int helper(int n) {
+    fputs(\"helping\\n\", stdout);
+    return n;
+}
+
+static int fn_main(void) {
+    int count = 0;
+    float f = 0.0f;
+
+    count = twice(3);
+    f = count;
+    c1_print_float(f * scale);
+    return count;
+}
+
+int main(void) {
+    return fn_main();
+}
+"
+        );
+    }
+
+    #[test]
+    fn names() {
+        let code = emit_text(
+            "int puts = 1; int exit(int char) { return char; } \
+             int x() { return 1; } void main() { x = exit(puts); abs = x(); }",
+        );
+        assert!(code.contains("static int puts_ = 1;"));
+        assert!(code.contains("static int fn_exit(int char_) {\n    return char_;"));
+        assert!(code.contains("static int fn_x(void);"));
+        assert!(code.contains("    int abs_ = 0;\n"));
+        assert!(code.contains("    x = fn_exit(puts_);\n    abs_ = fn_x();\n"));
+    }
+
+    #[test]
+    fn expressions() {
+        assert_eq!(
+            main_body("f = 1.5; x = -f * - -f; y = -(x + f) / 2; z = - -3 - -y;"),
+            "f = 1.5f;\nx = -f * -(-f);\ny = -(x + f) / (float)2;\nz = c1_neg(-3) - -y;"
+        );
+        assert_eq!(
+            main_body("a = 1; b = ((+a % 3 < 2) == !true) && (((a = 2) != 7) || (1e39 >= 1));"),
+            "a = 1;\nb = ((c1_mod(a, 3, 1) < 2) == !true) && (((a = 2) != 7) || \
+             ((1.0f / 0.0f) >= 1));"
+        );
+        assert_eq!(
+            main_body("a = 1; printf(a / 2 + a - 3); printf(a == a); printf(a * 0.5);"),
+            "a = 1;\nprintf(\"%d\\n\", c1_sub(c1_add(c1_div(a, 2, 1), a), 3));\n\
+             puts((a == a) ? \"true\" : \"false\");\nc1_print_float(a * 0.5f);"
+        );
+    }
+
+    #[test]
+    fn evaluation_order() {
+        let code = emit_text(
+            "int g; int f(int a, int b) { g = g + 1; return a; } \
+             void main() { x = 1; y = f(x, 2) + f(g, x); v = g + f(g, 1); z = (x = 2) * x; \
+             w = x + f(x, x); }",
+        );
+        assert!(code.contains("    int tmp_0;\n    int tmp_1;\n    int tmp_2;\n\n"));
+        // Reading g and x in the second call is not affected by the first call
+        assert!(code.contains("    y = (tmp_0 = f(x, 2), c1_add(tmp_0, f(g, x)));\n"));
+        assert!(code.contains("    z = (tmp_2 = (x = 2), c1_mul(tmp_2, x));\n"));
+        assert!(code.contains("    v = (tmp_1 = g, c1_add(tmp_1, f(g, 1)));\n"));
+        // Calls cannot change local variables
+        assert!(code.contains("    w = c1_add(x, f(x, x));\n"));
+        // Assigning the target again in the value would modify it twice without a sequence point
+        assert_eq!(
+            main_body("y = 1; y = y = y * 2; x = (y = 3) + (x = y);"),
+            "y = 1;\n(tmp_0 = (y = c1_mul(y, 2)), y = tmp_0);\n\
+             (tmp_2 = (tmp_1 = (y = 3), c1_add(tmp_1, x = y)), x = tmp_2);"
+        );
+    }
+
+    #[test]
+    fn statements() {
+        assert_eq!(
+            main_body(
+                "if (true) x = 1; else if (false) { x = 2; } else { x = 3; x = 4; } \
+                 while (x < 5) x = x + 1; do { int y; } while (false); \
+                 for (;;) { return; } for (i = 0; i < 2; i = i + 1) {}"
+            ),
+            "\
+if (true) {
+x = 1;
+} else if (false) {
+x = 2;
+} else {
+x = 3;
+x = 4;
+}
+while (x < 5) {
+x = c1_add(x, 1);
+}
+do {
+y = 0;
+} while (false);
+for (;;) {
+return;
+}
+for (i = 0; i < 2; i = c1_add(i, 1)) {
+}"
+        );
+        let code = emit_text("void f() {} void g() { return f(); } void main() { g(); }");
+        assert!(code.contains("static void g(void) {\n    f();\n    return;\n}\n"));
+        assert!(code.contains("int main(void) {\n    fn_main();\n    return 0;\n}\n"));
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(
+            main_body(r#"printf("a \"b\"\t\\ ?? ??? \r\n"); printf("ä\0");"#),
+            "fputs(\"a \\\"b\\\"\\t\\\\ ?\\? ?\\?\\? \\r\\n\", stdout);\n\
+             fwrite(\"\\303\\244\\000\", 1, 3, stdout);"
+        );
+    }
+}
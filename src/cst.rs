@@ -0,0 +1,444 @@
+//! Lossless concrete syntax tree, as produced by
+//! [`C1Parser::parse_cst`](crate::C1Parser::parse_cst).
+//!
+//! The tree is split into two layers:
+//! - the immutable *green* tree ([`GreenNode`], [`GreenToken`]) stores the kinds and texts of nodes
+//!   and tokens, but no positions, so identical subtrees can be shared and edited trees can reuse
+//!   unchanged parts.
+//! - the *red* tree ([`SyntaxNode`], [`SyntaxToken`]) is a cheap view on top of the green tree that
+//!   knows the parent and the byte offset of every element.
+//!
+//! Nodes are labeled with the [`Nonterminal`] of the grammar rule they were parsed by, tokens with
+//! their [`C1Token`]. Comments, whitespace and linebreaks are kept as trivia tokens, so the text of
+//! the root node is exactly the parsed text.
+
+use crate::{C1Token, Nonterminal};
+use std::fmt;
+use std::ops::Range;
+use std::rc::Rc;
+
+/// An inner node of the green tree
+#[derive(Debug, Clone, PartialEq)]
+pub struct GreenNode {
+    kind: Nonterminal,
+    text_len: usize,
+    children: Vec<GreenElement>,
+}
+
+/// A token of the green tree
+#[derive(Debug, Clone, PartialEq)]
+pub struct GreenToken {
+    kind: C1Token,
+    text: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GreenElement {
+    Node(Rc<GreenNode>),
+    Token(Rc<GreenToken>),
+}
+
+impl GreenNode {
+    pub fn new(kind: Nonterminal, children: Vec<GreenElement>) -> GreenNode {
+        GreenNode {
+            kind,
+            text_len: children.iter().map(GreenElement::text_len).sum(),
+            children,
+        }
+    }
+
+    pub fn kind(&self) -> Nonterminal {
+        self.kind
+    }
+
+    /// Length of the text covered by the node in bytes
+    pub fn text_len(&self) -> usize {
+        self.text_len
+    }
+
+    pub fn children(&self) -> &[GreenElement] {
+        &self.children
+    }
+
+    fn write_text(&self, text: &mut String) {
+        for child in &self.children {
+            match child {
+                GreenElement::Node(node) => node.write_text(text),
+                GreenElement::Token(token) => text.push_str(&token.text),
+            }
+        }
+    }
+}
+
+impl GreenToken {
+    pub fn new(kind: C1Token, text: &str) -> GreenToken {
+        GreenToken {
+            kind,
+            text: text.to_string(),
+        }
+    }
+
+    pub fn kind(&self) -> C1Token {
+        self.kind
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+impl GreenElement {
+    pub fn text_len(&self) -> usize {
+        match self {
+            GreenElement::Node(node) => node.text_len(),
+            GreenElement::Token(token) => token.text.len(),
+        }
+    }
+}
+
+/// Builds a green tree from a preorder sequence of node starts, tokens and node ends
+#[derive(Default)]
+pub(crate) struct GreenNodeBuilder {
+    /// Kinds and children of the currently open nodes, innermost last
+    parents: Vec<(Nonterminal, Vec<GreenElement>)>,
+    root: Option<Rc<GreenNode>>,
+}
+
+impl GreenNodeBuilder {
+    /// Number of currently open nodes
+    pub(crate) fn depth(&self) -> usize {
+        self.parents.len()
+    }
+
+    pub(crate) fn start_node(&mut self, kind: Nonterminal) {
+        self.parents.push((kind, Vec::new()));
+    }
+
+    /// Return the position behind the last child of the innermost open node, see
+    /// [`start_node_at`](GreenNodeBuilder::start_node_at)
+    pub(crate) fn checkpoint(&self) -> usize {
+        self.parents
+            .last()
+            .map_or(0, |(_, children)| children.len())
+    }
+
+    /// Start a node whose first children are the children added to the innermost open node since
+    /// the checkpoint was taken. This allows deciding the kind of a node after parsing its start.
+    pub(crate) fn start_node_at(&mut self, checkpoint: usize, kind: Nonterminal) {
+        let (_, siblings) = self
+            .parents
+            .last_mut()
+            .expect("no open node to start a node in");
+        let children = siblings.split_off(checkpoint);
+        self.parents.push((kind, children));
+    }
+
+    /// Add a token to the innermost open node
+    pub(crate) fn token(&mut self, kind: C1Token, text: &str) {
+        if let Some((_, children)) = self.parents.last_mut() {
+            children.push(GreenElement::Token(Rc::new(GreenToken::new(kind, text))));
+        }
+    }
+
+    pub(crate) fn finish_node(&mut self) {
+        let (kind, children) = self.parents.pop().expect("no open node to finish");
+        let node = Rc::new(GreenNode::new(kind, children));
+        match self.parents.last_mut() {
+            Some((_, siblings)) => siblings.push(GreenElement::Node(node)),
+            None => self.root = Some(node),
+        }
+    }
+
+    /// Return the completed tree
+    pub(crate) fn finish(self) -> Rc<GreenNode> {
+        assert!(self.parents.is_empty(), "unfinished nodes left");
+        self.root.expect("no node was built")
+    }
+}
+
+/// A node of the red tree
+#[derive(Clone)]
+pub struct SyntaxNode(Rc<NodeData>);
+
+struct NodeData {
+    green: Rc<GreenNode>,
+    parent: Option<SyntaxNode>,
+    offset: usize,
+}
+
+/// A token of the red tree
+#[derive(Clone)]
+pub struct SyntaxToken {
+    green: Rc<GreenToken>,
+    parent: SyntaxNode,
+    offset: usize,
+}
+
+#[derive(Debug, Clone)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+impl SyntaxNode {
+    /// Create the red tree for the given green root node
+    pub fn new_root(green: Rc<GreenNode>) -> SyntaxNode {
+        SyntaxNode(Rc::new(NodeData {
+            green,
+            parent: None,
+            offset: 0,
+        }))
+    }
+
+    pub fn kind(&self) -> Nonterminal {
+        self.0.green.kind
+    }
+
+    pub fn green(&self) -> &Rc<GreenNode> {
+        &self.0.green
+    }
+
+    pub fn parent(&self) -> Option<SyntaxNode> {
+        self.0.parent.clone()
+    }
+
+    /// Byte range of the node in the parsed text
+    pub fn text_range(&self) -> Range<usize> {
+        self.0.offset..self.0.offset + self.0.green.text_len
+    }
+
+    /// The text covered by the node, including trivia
+    pub fn text(&self) -> String {
+        let mut text = String::with_capacity(self.0.green.text_len);
+        self.0.green.write_text(&mut text);
+        text
+    }
+
+    /// The direct children of the node, both nodes and tokens
+    pub fn children_with_tokens(&self) -> Vec<SyntaxElement> {
+        let mut offset = self.0.offset;
+        self.0
+            .green
+            .children
+            .iter()
+            .map(|child| {
+                let element = match child {
+                    GreenElement::Node(green) => {
+                        SyntaxElement::Node(SyntaxNode(Rc::new(NodeData {
+                            green: green.clone(),
+                            parent: Some(self.clone()),
+                            offset,
+                        })))
+                    }
+                    GreenElement::Token(green) => SyntaxElement::Token(SyntaxToken {
+                        green: green.clone(),
+                        parent: self.clone(),
+                        offset,
+                    }),
+                };
+                offset += child.text_len();
+                element
+            })
+            .collect()
+    }
+
+    /// The direct child nodes of the node
+    pub fn children(&self) -> Vec<SyntaxNode> {
+        self.children_with_tokens()
+            .into_iter()
+            .filter_map(|element| match element {
+                SyntaxElement::Node(node) => Some(node),
+                SyntaxElement::Token(_) => None,
+            })
+            .collect()
+    }
+
+    /// All tokens in the subtree of the node in text order, including trivia
+    pub fn tokens(&self) -> Vec<SyntaxToken> {
+        let mut tokens = Vec::new();
+        for element in self.children_with_tokens() {
+            match element {
+                SyntaxElement::Node(node) => tokens.extend(node.tokens()),
+                SyntaxElement::Token(token) => tokens.push(token),
+            }
+        }
+        tokens
+    }
+
+    /// Render the subtree with one element per line, e.g. `Factor@4..5` or `ConstInt@4..5 "1"`
+    pub fn debug_tree(&self) -> String {
+        let mut output = String::new();
+        self.write_debug_tree(&mut output, 0);
+        output
+    }
+
+    fn write_debug_tree(&self, output: &mut String, indent: usize) {
+        output.push_str(&format!("{}{:?}\n", "  ".repeat(indent), self));
+        for element in self.children_with_tokens() {
+            match element {
+                SyntaxElement::Node(node) => node.write_debug_tree(output, indent + 1),
+                SyntaxElement::Token(token) => {
+                    output.push_str(&format!("{}{:?}\n", "  ".repeat(indent + 1), token))
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Debug for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let range = self.text_range();
+        write!(f, "{:?}@{}..{}", self.kind(), range.start, range.end)
+    }
+}
+
+impl SyntaxToken {
+    pub fn kind(&self) -> C1Token {
+        self.green.kind
+    }
+
+    pub fn text(&self) -> &str {
+        &self.green.text
+    }
+
+    pub fn green(&self) -> &Rc<GreenToken> {
+        &self.green
+    }
+
+    pub fn parent(&self) -> SyntaxNode {
+        self.parent.clone()
+    }
+
+    /// Byte range of the token in the parsed text
+    pub fn text_range(&self) -> Range<usize> {
+        self.offset..self.offset + self.green.text.len()
+    }
+}
+
+impl fmt::Debug for SyntaxToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let range = self.text_range();
+        write!(
+            f,
+            "{:?}@{}..{} {:?}",
+            self.kind(),
+            range.start,
+            range.end,
+            self.text()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{C1Parser, C1Token, Dialect, Nonterminal};
+
+    #[test]
+    fn cst_is_lossless() {
+        let texts = [
+            "",
+            "  \n // only a comment",
+            "void main() { x = 1; }",
+            "/* head */ int  foo ( ) {\n\treturn -1 + 2 * (3);  // tail\n}\n\n",
+            include_str!("../tests/data/beispiel.c-1"),
+        ];
+        for text in texts {
+            let root = C1Parser::parse_cst(text).unwrap();
+            assert_eq!(root.kind(), Nonterminal::Program);
+            assert_eq!(root.text(), text);
+            assert_eq!(root.text_range(), 0..text.len());
+
+            let tokens = root.tokens();
+            let concatenated: String = tokens.iter().map(|token| token.text()).collect();
+            assert_eq!(concatenated, text);
+            for token in tokens {
+                assert_eq!(&text[token.text_range()], token.text());
+            }
+        }
+    }
+
+    #[test]
+    fn cst_structure() {
+        let root = C1Parser::parse_cst("int f() {\n  // c\n  return 1;\n}\n").unwrap();
+        assert_eq!(
+            root.debug_tree(),
+            r#"Program@0..31
+  FunctionDefinition@0..30
+    Type@0..3
+      KwInt@0..3 "int"
+    Whitespace@3..4 " "
+    Identifier@4..5 "f"
+    LeftParenthesis@5..6 "("
+    RightParenthesis@6..7 ")"
+    Whitespace@7..8 " "
+    LeftBrace@8..9 "{"
+    Linebreak@9..10 "\n"
+    Whitespace@10..12 "  "
+    CPPComment@12..17 "// c\n"
+    Whitespace@17..19 "  "
+    StatementList@19..28
+      Block@19..28
+        Statement@19..28
+          ReturnStatement@19..27
+            KwReturn@19..25 "return"
+            Whitespace@25..26 " "
+            Assignment@26..27
+              Expr@26..27
+                SimpExpr@26..27
+                  Term@26..27
+                    Unary@26..27
+                      Factor@26..27
+                        ConstInt@26..27 "1"
+          Semicolon@27..28 ";"
+    Linebreak@28..29 "\n"
+    RightBrace@29..30 "}"
+  Linebreak@30..31 "\n"
+"#
+        );
+    }
+
+    #[test]
+    fn cst_global_declaration() {
+        // The kind of the node is only known after its type and name were parsed
+        let root = C1Parser::parse_cst_in("// g\nint g = 1;", Dialect::C1).unwrap();
+        assert_eq!(
+            root.debug_tree(),
+            r#"Program@0..15
+  CPPComment@0..5 "// g\n"
+  GlobalDeclaration@5..15
+    Type@5..8
+      KwInt@5..8 "int"
+    Whitespace@8..9 " "
+    Identifier@9..10 "g"
+    Whitespace@10..11 " "
+    Assign@11..12 "="
+    Whitespace@12..13 " "
+    ConstInt@13..14 "1"
+    Semicolon@14..15 ";"
+"#
+        );
+    }
+
+    #[test]
+    fn cst_navigation() {
+        let root = C1Parser::parse_cst("void main() { foo(); }").unwrap();
+        let function = &root.children()[0];
+        assert_eq!(function.kind(), Nonterminal::FunctionDefinition);
+        assert_eq!(function.parent().unwrap().kind(), Nonterminal::Program);
+
+        let call = function
+            .tokens()
+            .into_iter()
+            .find(|token| token.text() == "foo")
+            .unwrap();
+        assert_eq!(call.kind(), C1Token::Identifier);
+        assert_eq!(call.parent().kind(), Nonterminal::FunctionCall);
+        assert_eq!(call.parent().text_range(), 14..19);
+        assert_eq!(call.parent().text(), "foo()");
+    }
+
+    #[test]
+    fn cst_requires_valid_program() {
+        assert!(C1Parser::parse_cst("void main() { x = ; }").is_err());
+    }
+}
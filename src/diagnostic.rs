@@ -0,0 +1,437 @@
+//! Rendering of errors as rustc-style diagnostics, showing the offending source line with the token
+//! underlined:
+//!
+//! ```text
+//! error: Expected ')'
+//!  --> main.c-1:2:11
+//!   |
+//! 2 |     x = (1;
+//!   |         - unclosed delimiter
+//!   |           ^ expected ')'
+//! ```
+
+use crate::semantic::{SemanticError, SemanticErrorKind};
+use crate::typecheck::{TypeError, TypeErrorKind};
+use crate::ParseError;
+use std::ops::Range;
+
+/// Number of columns a tab character is expanded to
+const TAB_WIDTH: usize = 4;
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const BOLD_RED: &str = "\x1b[1;31m";
+const BOLD_BLUE: &str = "\x1b[1;34m";
+
+/// Output style of [`Diagnostic::render`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorMode {
+    Plain,
+    /// Colored output using ANSI escape sequences, for terminals
+    Ansi,
+}
+
+/// A message attached to a byte range of the source text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+    /// Byte range in the source text. Ranges at or past the end of the source point at the end of
+    /// the input, offsets inside a character are widened to the whole character.
+    pub span: Range<usize>,
+    pub message: String,
+}
+
+impl Label {
+    pub fn new(span: Range<usize>, message: impl Into<String>) -> Label {
+        Label {
+            span,
+            message: message.into(),
+        }
+    }
+}
+
+/// An error message with a primary label marking the offending code, and optional secondary labels
+/// marking related code
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub message: String,
+    pub primary: Label,
+    pub secondary: Vec<Label>,
+}
+
+/// A label resolved against the source text
+#[derive(Clone)]
+struct Snippet<'a> {
+    line_number: usize,
+    /// 1-based column, counted in characters
+    column: usize,
+    line: &'a str,
+    /// Display column (0-based, tabs expanded) where the underline starts
+    start: usize,
+    /// Display width of the underline
+    width: usize,
+    message: &'a str,
+    primary: bool,
+}
+
+impl Diagnostic {
+    pub fn new(message: impl Into<String>, primary: Label) -> Diagnostic {
+        Diagnostic {
+            message: message.into(),
+            primary,
+            secondary: Vec::new(),
+        }
+    }
+
+    /// Add a secondary label
+    pub fn with_secondary(mut self, label: Label) -> Diagnostic {
+        self.secondary.push(label);
+        self
+    }
+
+    /// Render the diagnostic for the given source text, which is referred to by `file_name`
+    pub fn render(&self, source: &str, file_name: &str, mode: ColorMode) -> String {
+        let paint = |style: &str, text: &str| match mode {
+            ColorMode::Plain => text.to_string(),
+            ColorMode::Ansi => format!("{}{}{}", style, text, RESET),
+        };
+
+        let primary = Snippet::resolve(source, &self.primary, true);
+        let mut snippets: Vec<Snippet> = self
+            .secondary
+            .iter()
+            .map(|label| Snippet::resolve(source, label, false))
+            .collect();
+        snippets.push(primary.clone());
+        snippets.sort_by_key(|snippet| (snippet.line_number, snippet.start));
+
+        let gutter_width = snippets
+            .iter()
+            .map(|snippet| snippet.line_number.to_string().len())
+            .max()
+            .unwrap_or(1);
+        let empty_gutter = paint(BOLD_BLUE, &format!("{} |", " ".repeat(gutter_width)));
+
+        let mut output = format!(
+            "{}{}\n{}{} {}:{}:{}\n{}\n",
+            paint(BOLD_RED, "error"),
+            paint(BOLD, &format!(": {}", self.message)),
+            " ".repeat(gutter_width),
+            paint(BOLD_BLUE, "-->"),
+            file_name,
+            primary.line_number,
+            primary.column,
+            empty_gutter,
+        );
+
+        let mut previous_line = None;
+        for (index, snippet) in snippets.iter().enumerate() {
+            if previous_line != Some(snippet.line_number) {
+                if previous_line.is_some_and(|line| snippet.line_number > line + 1) {
+                    output.push_str(&paint(BOLD_BLUE, "...\n"));
+                }
+                let line_number = format!("{:>1$} |", snippet.line_number, gutter_width);
+                output.push_str(&format!(
+                    "{} {}\n",
+                    paint(BOLD_BLUE, &line_number),
+                    expand_tabs(snippet.line)
+                ));
+                previous_line = Some(snippet.line_number);
+            }
+
+            let (style, mark) = if snippet.primary {
+                (BOLD_RED, "^")
+            } else {
+                (BOLD_BLUE, "-")
+            };
+            let underline = format!("{} {}", mark.repeat(snippet.width), snippet.message);
+            output.push_str(&format!(
+                "{} {}{}\n",
+                empty_gutter,
+                " ".repeat(snippet.start),
+                paint(style, underline.trim_end())
+            ));
+
+            // Separate the labels of different lines
+            if snippets
+                .get(index + 1)
+                .is_some_and(|next| next.line_number != snippet.line_number)
+            {
+                output.push_str(&empty_gutter);
+                output.push('\n');
+            }
+        }
+        output
+    }
+}
+
+impl<'a> Snippet<'a> {
+    fn resolve(source: &'a str, label: &'a Label, primary: bool) -> Snippet<'a> {
+        // Spans at the end of the input point just behind the last visible character
+        let end_of_input = source.trim_end().len();
+        let start = floor_char_boundary(source, label.span.start.min(end_of_input));
+        let end = ceil_char_boundary(source, label.span.end.clamp(start, source.len()));
+
+        let line_start = source[..start].rfind('\n').map_or(0, |index| index + 1);
+        let line_end = source[start..]
+            .find('\n')
+            .map_or(source.len(), |index| start + index);
+        let line = source[line_start..line_end].trim_end_matches('\r');
+        let end = end.min(line_start + line.len());
+
+        Snippet {
+            line_number: source[..start].matches('\n').count() + 1,
+            column: source[line_start..start].chars().count() + 1,
+            line,
+            start: display_width(&source[line_start..start]),
+            width: display_width(&source[start..end]).max(1),
+            message: &label.message,
+            primary,
+        }
+    }
+}
+
+/// Round the offset down to the start of the character it points into
+fn floor_char_boundary(text: &str, mut index: usize) -> usize {
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+/// Round the offset up to the end of the character it points into
+fn ceil_char_boundary(text: &str, mut index: usize) -> usize {
+    while !text.is_char_boundary(index) {
+        index += 1;
+    }
+    index
+}
+
+fn display_width(text: &str) -> usize {
+    text.chars()
+        .map(|c| if c == '\t' { TAB_WIDTH } else { 1 })
+        .sum()
+}
+
+fn expand_tabs(text: &str) -> String {
+    text.replace('\t', &" ".repeat(TAB_WIDTH))
+}
+
+impl From<&ParseError> for Diagnostic {
+    fn from(error: &ParseError) -> Diagnostic {
+        let expected = match error.expected.as_slice() {
+            [] => String::new(),
+            [token] => format!("expected {}", token),
+            [tokens @ .., last] => format!(
+                "expected one of {} or {}",
+                tokens
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                last
+            ),
+        };
+        // Errors at EOF have no location and point at the end of the input
+        let span = error
+            .location
+            .map_or(usize::MAX..usize::MAX, |location| location.span());
+
+        let mut diagnostic = Diagnostic::new(error.reason(), Label::new(span, expected));
+        if let Some(opening) = error.unclosed_delimiter {
+            diagnostic =
+                diagnostic.with_secondary(Label::new(opening.span(), "unclosed delimiter"));
+        }
+        diagnostic
+    }
+}
+
+impl From<&SemanticError> for Diagnostic {
+    fn from(error: &SemanticError) -> Diagnostic {
+        let message = match &error.kind {
+            SemanticErrorKind::UndefinedFunction(_) => "not found in this program",
+            SemanticErrorKind::DuplicateFunction { .. }
+            | SemanticErrorKind::DuplicateParameter { .. }
+            | SemanticErrorKind::DuplicateVariable { .. }
+            | SemanticErrorKind::DuplicateName { .. } => "redefined here",
+            SemanticErrorKind::ArgumentCount { .. } => "wrong number of arguments",
+            SemanticErrorKind::MissingMain => "expected a main function",
+            SemanticErrorKind::InvalidMainType(_) => "expected int or void",
+            SemanticErrorKind::MainParameters => "expected no parameters",
+        };
+        let span = error
+            .location
+            .map_or(usize::MAX..usize::MAX, |location| location.span());
+
+        let mut diagnostic = Diagnostic::new(error.message(), Label::new(span, message));
+        if let SemanticErrorKind::DuplicateFunction {
+            first_definition, ..
+        }
+        | SemanticErrorKind::DuplicateParameter {
+            first_definition, ..
+        }
+        | SemanticErrorKind::DuplicateVariable {
+            first_definition, ..
+        }
+        | SemanticErrorKind::DuplicateName {
+            first_definition, ..
+        } = &error.kind
+        {
+            diagnostic = diagnostic
+                .with_secondary(Label::new(first_definition.span(), "first defined here"));
+        }
+        diagnostic
+    }
+}
+
+impl From<&TypeError> for Diagnostic {
+    fn from(error: &TypeError) -> Diagnostic {
+        let message = match &error.kind {
+            TypeErrorKind::Mismatch { expected, found } => {
+                format!("expected {}, found {}", expected, found)
+            }
+            TypeErrorKind::InvalidOperand { found, .. } => format!("this is {}", found),
+            TypeErrorKind::MismatchedOperands { lhs, rhs, .. } => {
+                format!("{} and {} cannot be compared", lhs, rhs)
+            }
+            TypeErrorKind::UndefinedVariable(_) => "not assigned before".to_string(),
+            TypeErrorKind::VoidVariable(_) => "has type void".to_string(),
+            TypeErrorKind::MissingReturn { expected, .. } => {
+                format!("expected to return {}", expected)
+            }
+        };
+        Diagnostic::new(error.message(), Label::new(error.location.span(), message))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diagnostic::{ColorMode, Diagnostic, Label};
+    use crate::semantic::analyze;
+    use crate::typecheck::check;
+    use crate::C1Parser;
+
+    fn render_parse_error(source: &str) -> String {
+        let error = C1Parser::parse_program(source).unwrap_err();
+        Diagnostic::from(&error).render(source, "test.c-1", ColorMode::Plain)
+    }
+
+    #[test]
+    fn render_unexpected_token() {
+        assert_eq!(
+            render_parse_error("void main() {\n\tx = 1 +;\n}"),
+            "error: Expected factor\n \
+            --> test.c-1:2:9\n  \
+              |\n\
+            2 |     x = 1 +;\n  \
+              |            ^ expected one of integer constant, float constant, boolean constant, \
+                identifier or '('\n"
+        );
+    }
+
+    #[test]
+    fn render_unclosed_delimiter() {
+        assert_eq!(
+            render_parse_error("void main() {\n    x = (1;\n}"),
+            "error: Expected ')'\n \
+            --> test.c-1:2:11\n  \
+              |\n\
+            2 |     x = (1;\n  \
+              |         - unclosed delimiter\n  \
+              |           ^ expected ')'\n"
+        );
+
+        assert_eq!(
+            render_parse_error("void main() {\n  printf(1);\n\n\n"),
+            "error: Expected '}'\n \
+            --> test.c-1:2:13\n  \
+              |\n\
+            1 | void main() {\n  \
+              |             - unclosed delimiter\n  \
+              |\n\
+            2 |   printf(1);\n  \
+              |             ^ expected '}'\n"
+        );
+    }
+
+    #[test]
+    fn render_gaps_and_wide_gutter() {
+        let source = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj bad\n";
+        let diagnostic = Diagnostic::new("Something", Label::new(20..23, "here"))
+            .with_secondary(Label::new(0..1, "related"));
+        assert_eq!(
+            diagnostic.render(source, "gaps.c-1", ColorMode::Plain),
+            "error: Something\n  \
+              --> gaps.c-1:10:3\n   \
+               |\n \
+             1 | a\n   \
+               | - related\n   \
+               |\n\
+            ...\n\
+            10 | j bad\n   \
+               |   ^^^ here\n"
+        );
+    }
+
+    #[test]
+    fn render_span_inside_character() {
+        let source = "x = \"äöü\";";
+        // Both ends of the span point into the middle of a two byte character
+        let diagnostic = Diagnostic::new("Something", Label::new(6..8, "here"));
+        assert_eq!(
+            diagnostic.render(source, "test.c-1", ColorMode::Plain),
+            "error: Something\n \
+            --> test.c-1:1:6\n  \
+              |\n\
+            1 | x = \"äöü\";\n  \
+              |      ^^ here\n"
+        );
+    }
+
+    #[test]
+    fn render_ansi() {
+        let source = "void main() { x = ; }";
+        let rendered = Diagnostic::from(&C1Parser::parse_program(source).unwrap_err()).render(
+            source,
+            "test.c-1",
+            ColorMode::Ansi,
+        );
+        assert!(rendered.starts_with("\x1b[1;31merror\x1b[0m\x1b[1m: Expected factor\x1b[0m\n"));
+        assert!(rendered.contains("\x1b[1;31m^ expected one of"));
+        assert!(rendered.contains("\x1b[1;34m1 |\x1b[0m void main() { x = ; }\n"));
+    }
+
+    #[test]
+    fn render_semantic_error() {
+        let source = "int f() {}
+void main() {}
+int f() {}
+";
+        let errors = analyze(&C1Parser::parse_program(source).unwrap()).unwrap_err();
+        assert_eq!(
+            Diagnostic::from(&errors[0]).render(source, "test.c-1", ColorMode::Plain),
+            "error: Function 'f' is defined multiple times\n \
+            --> test.c-1:3:5\n  \
+              |\n\
+            1 | int f() {}\n  \
+              |     - first defined here\n  \
+              |\n\
+            ...\n\
+            3 | int f() {}\n  \
+              |     ^ redefined here\n"
+        );
+    }
+
+    #[test]
+    fn render_type_error() {
+        let source = "int main() {\n    return 1 + true;\n}\n";
+        let errors = check(&C1Parser::parse_program(source).unwrap()).unwrap_err();
+        assert_eq!(
+            Diagnostic::from(&errors[0]).render(source, "test.c-1", ColorMode::Plain),
+            "error: Invalid operand of type bool for '+'\n \
+            --> test.c-1:2:16\n  \
+              |\n\
+            2 |     return 1 + true;\n  \
+              |                ^^^^ this is bool\n"
+        );
+    }
+}
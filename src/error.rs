@@ -0,0 +1,156 @@
+use crate::ast::Location;
+use crate::C1Token;
+use std::fmt;
+
+/// Classification of a syntax error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    /// A token was found that cannot continue the current nonterminal
+    UnexpectedToken,
+    /// The input ended while the current nonterminal was incomplete
+    UnexpectedEof,
+    /// The lexer could not recognize the input at this position
+    InvalidToken,
+    /// Tokens were left over after a complete program
+    ExpectedEof,
+    /// A constant token whose value cannot be represented, e.g. an integer that does not fit into
+    /// 32 bits
+    InvalidLiteral,
+}
+
+/// The grammar rule the parser was working on when an error occurred
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Nonterminal {
+    Program,
+    GlobalDeclaration,
+    FunctionDefinition,
+    FunctionCall,
+    StatementList,
+    Block,
+    Statement,
+    IfStatement,
+    WhileStatement,
+    DoWhileStatement,
+    ForStatement,
+    Declaration,
+    ReturnStatement,
+    Printf,
+    Type,
+    StatAssignment,
+    Assignment,
+    Expr,
+    SimpExpr,
+    Term,
+    Unary,
+    Factor,
+}
+
+impl fmt::Display for Nonterminal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Names as used in the grammar
+        let name = match self {
+            Nonterminal::Program => "program",
+            Nonterminal::GlobalDeclaration => "globaldeclaration",
+            Nonterminal::FunctionDefinition => "functiondefinition",
+            Nonterminal::FunctionCall => "functioncall",
+            Nonterminal::StatementList => "statementlist",
+            Nonterminal::Block => "block",
+            Nonterminal::Statement => "statement",
+            Nonterminal::IfStatement => "ifstatement",
+            Nonterminal::WhileStatement => "whilestatement",
+            Nonterminal::DoWhileStatement => "dowhilestatement",
+            Nonterminal::ForStatement => "forstatement",
+            Nonterminal::Declaration => "declaration",
+            Nonterminal::ReturnStatement => "returnstatement",
+            Nonterminal::Printf => "printf",
+            Nonterminal::Type => "type",
+            Nonterminal::StatAssignment => "statassignment",
+            Nonterminal::Assignment => "assignment",
+            Nonterminal::Expr => "expr",
+            Nonterminal::SimpExpr => "simpexpr",
+            Nonterminal::Term => "term",
+            Nonterminal::Unary => "unary",
+            Nonterminal::Factor => "factor",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A syntax error reported by [`C1Parser`](crate::C1Parser).
+///
+/// The [`Display`](fmt::Display) implementation produces the human readable message, e.g.
+/// `Expected ')' at line 3, got 'x' instead.`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    /// The nonterminal that was being parsed
+    pub nonterminal: Nonterminal,
+    /// The offending token, `None` at EOF
+    pub found: Option<C1Token>,
+    /// The text of the offending token, `None` at EOF
+    pub found_text: Option<String>,
+    /// The tokens that would have been accepted instead
+    pub expected: Vec<C1Token>,
+    /// Location of the offending token, `None` at EOF
+    pub location: Option<Location>,
+    /// Location of the opening "(" or "{" if the error is a missing closing delimiter
+    pub unclosed_delimiter: Option<Location>,
+    reason: &'static str,
+}
+
+impl ParseError {
+    pub(crate) fn new(
+        kind: ParseErrorKind,
+        nonterminal: Nonterminal,
+        found: Option<(C1Token, &str, Location)>,
+        expected: &[C1Token],
+        reason: &'static str,
+    ) -> ParseError {
+        ParseError {
+            kind,
+            nonterminal,
+            found: found.as_ref().map(|(token, _, _)| *token),
+            found_text: found.as_ref().map(|(_, text, _)| text.to_string()),
+            expected: expected.to_vec(),
+            location: found.map(|(_, _, location)| location),
+            unclosed_delimiter: None,
+            reason,
+        }
+    }
+
+    /// Record the location of the opening delimiter that the missing token would have closed
+    pub(crate) fn with_unclosed_delimiter(mut self, location: Location) -> ParseError {
+        self.unclosed_delimiter = Some(location);
+        self
+    }
+
+    /// Short description of what went wrong, without location information
+    pub fn reason(&self) -> &'static str {
+        self.reason
+    }
+
+    /// Line of the offending token, `None` at EOF
+    pub fn line(&self) -> Option<usize> {
+        self.location.map(|location| location.line)
+    }
+
+    /// Column of the offending token, `None` at EOF
+    pub fn column(&self) -> Option<usize> {
+        self.location.map(|location| location.column)
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.found_text, self.location) {
+            (Some(text), Some(location)) => write!(
+                f,
+                "{} at line {:?}, got '{}' instead.",
+                self.reason, location.line, text
+            ),
+            _ => write!(f, "{}. Reached EOF", self.reason),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
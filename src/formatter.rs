@@ -0,0 +1,610 @@
+//! Canonical source formatter for C(-1) programs.
+//!
+//! Programs are parsed in the [C1 dialect](Dialect::C1), which accepts every C(-1) program.
+//!
+//! The formatter re-emits the [concrete syntax tree](crate::cst) of a program with
+//! - four spaces of indentation per block level,
+//! - opening braces on the line of the function header, `if`, `while`, `do` or `for`, closing
+//!   braces on their own line, followed by the `else` of an if statement or the `while` of a
+//!   do-while loop,
+//! - `else if` on one line,
+//! - one statement per line, with the body of an `if` or a loop without braces on an indented
+//!   line,
+//! - a single space around binary operators and `=`, after the `;` in `for` loops and after the
+//!   `,` between parameters and arguments, and none inside parentheses or after unary operators
+//!   unless two `-` or `+` would merge,
+//! - one blank line between function definitions and around global declarations. Consecutive
+//!   global declarations are only separated by a blank line if the source has one.
+//!
+//! Comments are kept: comments on their own line stay on their own line, comments behind code stay
+//! behind it. A single blank line between statements or after comments is kept as well. Formatting
+//! is idempotent.
+
+use crate::cst::{SyntaxElement, SyntaxNode, SyntaxToken};
+use crate::{C1Parser, C1Token, Dialect, Nonterminal, ParseError};
+
+const INDENT: &str = "    ";
+
+/// Format the given program
+pub fn format(text: &str) -> Result<String, ParseError> {
+    let root = C1Parser::parse_cst_in(text, Dialect::C1)?;
+    let mut formatter = Formatter::default();
+    formatter.node(&root);
+    Ok(formatter.finish())
+}
+
+/// Check whether the given program is formatted, i.e. whether [`format`] would leave it unchanged
+pub fn check(text: &str) -> Result<bool, ParseError> {
+    Ok(format(text)? == text)
+}
+
+/// Whitespace to put in front of the next token, from least to most
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+enum Separator {
+    #[default]
+    None,
+    Space,
+    Newline,
+    BlankLine,
+}
+
+/// A comment waiting to be written in front of the next token
+struct Comment {
+    text: String,
+    /// Number of linebreaks between the previous token and the comment
+    newlines_before: usize,
+}
+
+#[derive(Default)]
+struct Formatter {
+    output: String,
+    indent: usize,
+    comments: Vec<Comment>,
+    /// Whitespace requested in front of the next token by the nodes it starts
+    pending: Separator,
+    /// Number of linebreaks in the source since the last token or comment
+    newlines: usize,
+    /// Whether the next token has to start on a new line, e.g. after a line comment
+    force_newline: bool,
+    /// Whether a space has to separate the next token, e.g. after an inline block comment
+    force_space: bool,
+    last_token: Option<C1Token>,
+}
+
+impl Formatter {
+    fn node(&mut self, node: &SyntaxNode) {
+        let mut index = 0;
+        for element in node.children_with_tokens() {
+            if let SyntaxElement::Token(token) = &element {
+                if token.kind().is_trivia() {
+                    self.trivia(token);
+                    continue;
+                }
+            }
+
+            match (node.kind(), &element) {
+                // A body without braces is put on an indented line
+                (
+                    Nonterminal::IfStatement
+                    | Nonterminal::WhileStatement
+                    | Nonterminal::DoWhileStatement
+                    | Nonterminal::ForStatement,
+                    SyntaxElement::Node(block),
+                ) if block.kind() == Nonterminal::Block
+                    && !is_braced(block)
+                    && !self.at_else_if(block) =>
+                {
+                    self.indent += 1;
+                    self.element(Separator::Newline, &element);
+                    self.indent -= 1;
+                }
+                _ => {
+                    let separator = self.separator(node, index, &element);
+                    self.element(separator, &element);
+                }
+            }
+
+            // Everything between braces is indented. The indentation ends in `separator`, in front
+            // of the closing brace.
+            if matches!(&element, SyntaxElement::Token(token) if token.kind() == C1Token::LeftBrace)
+            {
+                self.indent += 1;
+            }
+            index += 1;
+        }
+    }
+
+    /// Decide which whitespace goes in front of the `index`th non-trivia child of `node`
+    fn separator(&mut self, node: &SyntaxNode, index: usize, element: &SyntaxElement) -> Separator {
+        let token = match element {
+            SyntaxElement::Token(token) => Some(token.kind()),
+            SyntaxElement::Node(_) => None,
+        };
+        if token == Some(C1Token::RightBrace) {
+            // Comments in front of the brace belong to the indented body
+            self.flush_comments(Separator::Newline);
+            self.indent -= 1;
+            return if self.last_token == Some(C1Token::LeftBrace) && !self.force_newline {
+                Separator::None
+            } else {
+                Separator::Newline
+            };
+        }
+        if token == Some(C1Token::Semicolon) || token == Some(C1Token::Comma) {
+            return Separator::None;
+        }
+        // Parameters and arguments after the first one
+        if self.last_token == Some(C1Token::Comma) {
+            return Separator::Space;
+        }
+
+        match node.kind() {
+            Nonterminal::Program if index > 0 => {
+                // Consecutive global declarations are only separated by a blank line if the source
+                // has one, like statements
+                let is_global = |node: &SyntaxNode| node.kind() == Nonterminal::GlobalDeclaration;
+                let consecutive = is_global(&node.children()[index - 1])
+                    && matches!(element, SyntaxElement::Node(child) if is_global(child));
+                if consecutive && self.newlines < 2 {
+                    Separator::Newline
+                } else {
+                    Separator::BlankLine
+                }
+            }
+            // The function name, the parameter names and the braced body
+            Nonterminal::FunctionDefinition => match token {
+                Some(C1Token::Identifier) | Some(C1Token::LeftBrace) => Separator::Space,
+                _ => Separator::None,
+            },
+            Nonterminal::Block => {
+                if token == Some(C1Token::LeftBrace) && index > 0 {
+                    Separator::Space
+                } else {
+                    Separator::None
+                }
+            }
+            Nonterminal::StatementList => {
+                // Keep a single blank line between statements, but not at the start of a block
+                if index > 0 && self.newlines >= 2 {
+                    Separator::BlankLine
+                } else {
+                    Separator::Newline
+                }
+            }
+            Nonterminal::IfStatement | Nonterminal::WhileStatement => match token {
+                // "} else" after a braced block, otherwise the block is on its own line
+                Some(C1Token::KwElse) if is_braced(&node.children()[1]) => Separator::Space,
+                Some(C1Token::KwElse) => Separator::Newline,
+                Some(C1Token::LeftParenthesis) => Separator::Space,
+                // The braced blocks and the if of an else if
+                None if index > 2 => Separator::Space,
+                _ => Separator::None,
+            },
+            Nonterminal::DoWhileStatement => match token {
+                // "} while" after a braced body, otherwise the body is on its own line
+                Some(C1Token::KwWhile) if is_braced(&node.children()[0]) => Separator::Space,
+                Some(C1Token::KwWhile) => Separator::Newline,
+                Some(C1Token::LeftParenthesis) => Separator::Space,
+                // The braced block
+                None if index == 1 => Separator::Space,
+                _ => Separator::None,
+            },
+            Nonterminal::ForStatement => match element {
+                // The braced block
+                SyntaxElement::Node(child) if child.kind() == Nonterminal::Block => {
+                    Separator::Space
+                }
+                _ if token == Some(C1Token::LeftParenthesis) => Separator::Space,
+                // The clauses after the semicolons
+                _ if self.last_token == Some(C1Token::Semicolon)
+                    && token != Some(C1Token::RightParenthesis) =>
+                {
+                    Separator::Space
+                }
+                _ => Separator::None,
+            },
+            Nonterminal::GlobalDeclaration
+            | Nonterminal::Declaration
+            | Nonterminal::ReturnStatement
+                if index > 0 =>
+            {
+                Separator::Space
+            }
+            Nonterminal::StatAssignment | Nonterminal::Assignment if index > 0 => Separator::Space,
+            Nonterminal::Expr | Nonterminal::SimpExpr | Nonterminal::Term if index > 0 => {
+                Separator::Space
+            }
+            // Keep `- -x` from turning into `--x`
+            Nonterminal::Unary if index > 0 => {
+                let first_token = match element {
+                    SyntaxElement::Node(operand) => operand
+                        .tokens()
+                        .into_iter()
+                        .find(|token| !token.kind().is_trivia())
+                        .map(|token| token.kind()),
+                    SyntaxElement::Token(_) => None,
+                };
+                if matches!(first_token, Some(C1Token::Minus | C1Token::Plus))
+                    && first_token == self.last_token
+                {
+                    Separator::Space
+                } else {
+                    Separator::None
+                }
+            }
+            _ => Separator::None,
+        }
+    }
+
+    /// Check whether the block follows an `else` and consists of another if statement
+    fn at_else_if(&self, block: &SyntaxNode) -> bool {
+        self.last_token == Some(C1Token::KwElse)
+            && block.children().first().is_some_and(|statement| {
+                statement
+                    .children()
+                    .first()
+                    .is_some_and(|child| child.kind() == Nonterminal::IfStatement)
+            })
+    }
+
+    fn element(&mut self, separator: Separator, element: &SyntaxElement) {
+        // The separator of a node applies to its first token
+        self.pending = self.pending.max(separator);
+        match element {
+            SyntaxElement::Node(node) => self.node(node),
+            SyntaxElement::Token(token) => self.token(token),
+        }
+    }
+
+    /// Record trivia found in the tree: count linebreaks and collect comments
+    fn trivia(&mut self, token: &SyntaxToken) {
+        match token.kind() {
+            C1Token::CComment | C1Token::CPPComment => {
+                self.comments.push(Comment {
+                    text: token.text().trim_end().to_string(),
+                    newlines_before: self.newlines,
+                });
+                // A line comment includes the linebreak that ends it
+                self.newlines = token.text().matches('\n').count();
+            }
+            C1Token::Linebreak => self.newlines += 1,
+            _ => {}
+        }
+    }
+
+    /// Write the collected comments. `separator` is the whitespace that the next token asks for.
+    fn flush_comments(&mut self, separator: Separator) -> Separator {
+        let mut separator = separator;
+        for comment in std::mem::take(&mut self.comments) {
+            if comment.newlines_before == 0 && !self.output.is_empty() {
+                // Comment behind code
+                self.output.push(' ');
+                self.output.push_str(&comment.text);
+            } else {
+                let blank_line = separator == Separator::BlankLine
+                    || (comment.newlines_before >= 2
+                        && !self.output.is_empty()
+                        && self.last_token != Some(C1Token::LeftBrace));
+                self.write_separator(if blank_line {
+                    Separator::BlankLine
+                } else {
+                    Separator::Newline
+                });
+                self.output.push_str(&comment.text);
+                // The comment replaces a blank line in front of the next token
+                if separator == Separator::BlankLine {
+                    separator = Separator::Newline;
+                }
+                self.force_newline = true;
+            }
+            // Nothing can follow a line comment on the same line
+            if comment.text.starts_with("//") {
+                self.force_newline = true;
+            } else {
+                self.force_space = true;
+            }
+        }
+        separator
+    }
+
+    /// Write the comments and the separator in front of the next token
+    fn write_pending(&mut self, token: C1Token) {
+        let separator = std::mem::take(&mut self.pending);
+        // A blank line between the comments and the token is kept, like between statements
+        let blank_line_after_comments = !self.comments.is_empty() && self.newlines >= 2;
+        let separator = self.flush_comments(separator);
+        let separator = match separator {
+            _ if self.output.is_empty() => Separator::None,
+            Separator::None | Separator::Space if self.force_newline => Separator::Newline,
+            // Punctuation sticks to an inline comment in front of it like to any other token
+            Separator::None
+                if self.force_space
+                    && !matches!(
+                        token,
+                        C1Token::Semicolon | C1Token::Comma | C1Token::RightParenthesis
+                    ) =>
+            {
+                Separator::Space
+            }
+            separator => separator,
+        };
+        let separator = if separator == Separator::Newline && blank_line_after_comments {
+            Separator::BlankLine
+        } else {
+            separator
+        };
+        self.write_separator(separator);
+        self.force_newline = false;
+        self.force_space = false;
+    }
+
+    fn write_separator(&mut self, separator: Separator) {
+        if self.output.is_empty() {
+            return;
+        }
+        match separator {
+            Separator::None => {}
+            Separator::Space => self.output.push(' '),
+            Separator::Newline | Separator::BlankLine => {
+                self.output.push('\n');
+                if separator == Separator::BlankLine {
+                    self.output.push('\n');
+                }
+                self.output.push_str(&INDENT.repeat(self.indent));
+            }
+        }
+    }
+
+    fn token(&mut self, token: &SyntaxToken) {
+        self.write_pending(token.kind());
+        self.output.push_str(token.text());
+        self.last_token = Some(token.kind());
+        self.newlines = 0;
+    }
+
+    fn finish(mut self) -> String {
+        self.flush_comments(Separator::Newline);
+        if !self.output.is_empty() {
+            self.output.push('\n');
+        }
+        self.output
+    }
+}
+
+/// Check whether a block node is a braced block rather than a single statement
+fn is_braced(block: &SyntaxNode) -> bool {
+    block.children_with_tokens().iter().any(
+        |child| matches!(child, SyntaxElement::Token(token) if token.kind() == C1Token::LeftBrace),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formatter::{check, format};
+
+    fn assert_formats_to(text: &str, expected: &str) {
+        let formatted = format(text).unwrap();
+        assert_eq!(formatted, expected);
+        // Formatting is idempotent
+        assert_eq!(format(&formatted).unwrap(), formatted);
+        assert!(check(&formatted).unwrap());
+    }
+
+    #[test]
+    fn format_example() {
+        assert_formats_to(
+            include_str!("../tests/data/beispiel.c-1"),
+            "int blub() {
+    blub1 = 23;
+    blub2 = 17;
+    blub3 = 42;
+    blub4 = blub1 * (blub2 + blub3);
+    if (blub1 < blub4)
+        return blub2;
+    return blub3;
+}
+
+float blah() {
+    a = 1;
+    b = 2;
+    if (a < blub()) {
+        if (b > blub()) {
+            printf(blub() + blub());
+        }
+    }
+    return 3.14159;
+}
+
+void main() {
+    a = 1;
+    b = 2;
+
+    if (a <= b)
+        printf(a + b);
+    if (a >= b)
+        printf(a - b);
+
+    printf(blub());
+    printf(blah());
+}
+",
+        );
+    }
+
+    #[test]
+    fn format_spacing() {
+        assert_formats_to(
+            "int   f ( ){x=-1+2*(3-y)==z||a&&b;if(x!=1){}return;}void g(){}",
+            "int f() {
+    x = -1 + 2 * (3 - y) == z || a && b;
+    if (x != 1) {}
+    return;
+}
+
+void g() {}
+",
+        );
+        assert_formats_to(
+            "void f() { x = - - x%2 * ! ( a ) - + y + ! !b; }",
+            "void f() {
+    x = - -x % 2 * !(a) - +y + !!b;
+}
+",
+        );
+        assert_formats_to(
+            "void f() { {x = y = 1;} if (a) if (b) { foo ( ) ; } while(x<3)x=x+1; while(a){} }",
+            "void f() {
+    {
+        x = y = 1;
+    }
+    if (a)
+        if (b) {
+            foo();
+        }
+    while (x < 3)
+        x = x + 1;
+    while (a) {}
+}
+",
+        );
+        assert_formats_to(
+            "void f() { do{x=x+1;}while(x<3); do x=x-1;while(x>0); }",
+            "void f() {
+    do {
+        x = x + 1;
+    } while (x < 3);
+    do
+        x = x - 1;
+    while (x > 0);
+}
+",
+        );
+        assert_formats_to(
+            "void f() { for(i=0;i<3;i=i+1){x=i;} for(;;)x=1; for(;x;) {} for(i = 0 ;;) {} }",
+            "void f() {
+    for (i = 0; i < 3; i = i + 1) {
+        x = i;
+    }
+    for (;;)
+        x = 1;
+    for (; x;) {}
+    for (i = 0;;) {}
+}
+",
+        );
+        assert_formats_to(
+            "void f() { if(a){x=1;}else{x=2;} if(a)x=1;else x=2;
+              if(a) {} else if(b) x=1; else if(c){} else {}
+              if (a) if (b) x = 1; else x = 2; }",
+            "void f() {
+    if (a) {
+        x = 1;
+    } else {
+        x = 2;
+    }
+    if (a)
+        x = 1;
+    else
+        x = 2;
+    if (a) {} else if (b)
+        x = 1;
+    else if (c) {} else {}
+    if (a)
+        if (b)
+            x = 1;
+        else
+            x = 2;
+}
+",
+        );
+        assert_formats_to(
+            r#"void f() { printf ( "a  \"b\"\n" ) ; }"#,
+            r#"void f() {
+    printf("a  \"b\"\n");
+}
+"#,
+        );
+        assert_formats_to(
+            "int a=1;float b;\n\n  bool c = true ;void f() {} int d;",
+            "int a = 1;
+float b;
+
+bool c = true;
+
+void f() {}
+
+int d;
+",
+        );
+        assert_formats_to(
+            "void f() { int x;float y=x+1 ; bool b=c=true; }",
+            "void f() {
+    int x;
+    float y = x + 1;
+    bool b = c = true;
+}
+",
+        );
+        assert_formats_to(
+            "float calc ( int a,float b ){return f(a ,b)+g( );}",
+            "float calc(int a, float b) {
+    return f(a, b) + g();
+}
+",
+        );
+    }
+
+    #[test]
+    fn format_comments() {
+        assert_formats_to(
+            "// header\n\n\n/* doc */\nvoid main() { // open\n\n\n  // own line\n  \
+             x = 1; /* behind */ y = 2;\n  // before brace\n}  // end\n// trailing",
+            "// header
+
+/* doc */
+void main() { // open
+    // own line
+    x = 1; /* behind */
+    y = 2;
+    // before brace
+} // end
+// trailing
+",
+        );
+        assert_formats_to(
+            "void main() { x = /* inline */ 1; }\n",
+            "void main() {\n    x = /* inline */ 1;\n}\n",
+        );
+        assert_formats_to(
+            "// header\n\nint x;\nvoid main() { // open\n  // own line\n\n  x = 1; }\n",
+            "// header
+
+int x;
+
+void main() { // open
+    // own line
+
+    x = 1;
+}
+",
+        );
+        assert_formats_to(
+            "void main() { f(a /* a */ , b /* b */ ); return /* x */ ; }",
+            "void main() {\n    f(a /* a */, b /* b */);\n    return /* x */;\n}\n",
+        );
+    }
+
+    #[test]
+    fn format_empty() {
+        assert_formats_to("", "");
+        assert_formats_to("  \n\n", "");
+        assert_formats_to(" /* only */ ", "/* only */\n");
+    }
+
+    #[test]
+    fn check_formatting() {
+        assert!(!check("void main() {x=1;}").unwrap());
+        assert!(check("void main() {\n    x = 1;\n}\n").unwrap());
+        assert!(check("void main() {").is_err());
+    }
+}
@@ -0,0 +1,1108 @@
+//! Tree-walking interpreter for C(-1) programs.
+//!
+//! The interpreter executes a [`Program`] starting at its `main` function. Values are typed at
+//! runtime:
+//! - `int` is a 32 bit two's complement integer, arithmetic wraps around on overflow,
+//! - `float` is a 32 bit IEEE 754 number,
+//! - `bool` is separate from the numeric types: conditions and the operands of `&&` and `||` have
+//!   to be `bool`, and numbers are never implicitly converted to `bool` or vice versa.
+//!
+//! As in C, an `int` operand is converted to `float` if the other operand is a `float`, `&&` and
+//! `||` only evaluate their right operand if needed, and the result of a comparison is a `bool`.
+//!
+//! Global variables are initialized before `main` is called. Other variables are local to the
+//! function invocation that declares or assigns them: a name refers to a global variable unless
+//! the function has a parameter or declares a local variable with that name. Parameters are
+//! variables that are assigned the arguments of the call, declared variables without initializer
+//! start as [zero](Value::zero). Reading a variable before its first assignment, dividing an `int`
+//! by zero and calling a function that does not exist or with the wrong number of arguments are
+//! runtime errors.
+//!
+//! `printf` writes a value followed by a newline. A string is written as it is, without newline.
+//!
+//! ```
+//! use cb_3::interpreter::{Interpreter, Value};
+//! use cb_3::C1Parser;
+//!
+//! let text = "int main() { x = 6; printf(x * 7); return 1; }";
+//! let program = C1Parser::parse_program(text).unwrap();
+//! let mut output = Vec::new();
+//! let exit_value = Interpreter::new(&program, &mut output).run().unwrap();
+//! assert_eq!(exit_value, Value::Int(1));
+//! assert_eq!(output, b"42\n");
+//! ```
+
+use crate::ast::{
+    BinaryOperator, Expr, ExprKind, FunctionCall, FunctionDefinition, Identifier, Literal,
+    Location, PrintfArgument, Program, Statement, StatementKind, Type, UnaryOperator,
+};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io::Write;
+use std::sync::Arc;
+use std::thread;
+
+/// Maximum number of nested function calls before a [`RuntimeErrorKind::StackOverflow`]
+pub const MAX_CALL_DEPTH: usize = 10_000;
+
+/// Native stack size reserved for each nested call. Calls recurse on the native stack, which takes
+/// about 16 KiB per call without optimizations.
+const STACK_SIZE_PER_CALL: usize = 64 * 1024;
+
+/// A runtime value
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value {
+    Int(i32),
+    Float(f32),
+    Bool(bool),
+    /// The result of a function without a return value
+    Void,
+}
+
+impl Value {
+    /// The value of a declared variable without initializer: `0`, `0.0` or `false`
+    pub fn zero(ty: Type) -> Value {
+        match ty {
+            Type::Int => Value::Int(0),
+            Type::Float => Value::Float(0.0),
+            Type::Bool => Value::Bool(false),
+            Type::Void => Value::Void,
+        }
+    }
+
+    pub fn ty(&self) -> Type {
+        match self {
+            Value::Int(_) => Type::Int,
+            Value::Float(_) => Type::Float,
+            Value::Bool(_) => Type::Bool,
+            Value::Void => Type::Void,
+        }
+    }
+}
+
+/// Formats values the way `printf` prints them: integers as with C's `%d`, floats as with `%f`
+/// and booleans as `true` or `false`
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Int(value) => write!(f, "{}", value),
+            Value::Float(value) => write!(f, "{:.6}", value),
+            Value::Bool(value) => write!(f, "{}", value),
+            Value::Void => f.write_str("void"),
+        }
+    }
+}
+
+/// Classification of a runtime error
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuntimeErrorKind {
+    /// The program has no `main` function
+    MissingMain,
+    UndefinedFunction(String),
+    /// A function was called with the wrong number of arguments
+    ArgumentCount {
+        function: String,
+        expected: usize,
+        found: usize,
+    },
+    /// An argument does not match the type of its parameter
+    InvalidArgument {
+        expected: Type,
+        found: Type,
+    },
+    /// The initializer of a declaration does not match the type of the variable
+    InvalidInitializer {
+        expected: Type,
+        found: Type,
+    },
+    /// A variable was read before it was assigned
+    UndefinedVariable(String),
+    /// An integer division or remainder by zero
+    DivisionByZero,
+    /// An operator, `printf` or an assignment was applied to a value of the wrong type
+    InvalidOperand {
+        operator: String,
+        found: Type,
+    },
+    /// The condition of an `if` statement or a loop is not a `bool`
+    InvalidCondition(Type),
+    /// A function returned a value that does not match its return type. Reaching the end of a
+    /// function that has a return value counts as returning `void`.
+    InvalidReturnValue {
+        expected: Type,
+        found: Type,
+    },
+    /// The maximum call depth, which is given, was exceeded. It is [`MAX_CALL_DEPTH`] for the
+    /// interpreter.
+    StackOverflow(usize),
+    /// Writing the output of `printf` failed
+    Output(String),
+}
+
+/// An error that occurred while running a program
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeError {
+    pub kind: RuntimeErrorKind,
+    /// Location of the offending expression or statement, `None` if the program has no `main`
+    pub location: Option<Location>,
+}
+
+impl RuntimeError {
+    pub(crate) fn new(kind: RuntimeErrorKind, location: Location) -> RuntimeError {
+        RuntimeError {
+            kind,
+            location: Some(location),
+        }
+    }
+
+    pub(crate) fn invalid_operand(
+        operator: impl ToString,
+        found: Type,
+        location: Location,
+    ) -> RuntimeError {
+        RuntimeError::new(
+            RuntimeErrorKind::InvalidOperand {
+                operator: operator.to_string(),
+                found,
+            },
+            location,
+        )
+    }
+
+    /// Short description of what went wrong, without location information
+    pub fn message(&self) -> String {
+        match &self.kind {
+            RuntimeErrorKind::MissingMain => "No main function".to_string(),
+            RuntimeErrorKind::UndefinedFunction(name) => format!("Undefined function '{}'", name),
+            RuntimeErrorKind::ArgumentCount {
+                function,
+                expected,
+                found,
+            } => format!(
+                "Function '{}' takes {} arguments, but {} were given",
+                function, expected, found
+            ),
+            RuntimeErrorKind::InvalidArgument { expected, found } => {
+                format!("Expected an argument of type {}, got {}", expected, found)
+            }
+            RuntimeErrorKind::InvalidInitializer { expected, found } => {
+                format!(
+                    "Expected an initial value of type {}, got {}",
+                    expected, found
+                )
+            }
+            RuntimeErrorKind::UndefinedVariable(name) => {
+                format!("Variable '{}' is used before it is assigned", name)
+            }
+            RuntimeErrorKind::DivisionByZero => "Division by zero".to_string(),
+            RuntimeErrorKind::InvalidOperand { operator, found } => {
+                format!("Invalid operand of type {} for '{}'", found, operator)
+            }
+            RuntimeErrorKind::InvalidCondition(found) => {
+                format!("Expected a condition of type bool, got {}", found)
+            }
+            RuntimeErrorKind::InvalidReturnValue { expected, found } => {
+                format!(
+                    "Expected a return value of type {}, got {}",
+                    expected, found
+                )
+            }
+            RuntimeErrorKind::StackOverflow(limit) => {
+                format!("Exceeded the maximum call depth of {}", limit)
+            }
+            RuntimeErrorKind::Output(error) => format!("Could not write output: {}", error),
+        }
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.location {
+            Some(location) => write!(f, "{} at line {}", self.message(), location.line),
+            None => f.write_str(&self.message()),
+        }
+    }
+}
+
+impl std::error::Error for RuntimeError {}
+
+pub(crate) type Evaluated<T> = Result<T, RuntimeError>;
+
+/// What happens after a statement was executed
+enum Flow {
+    Continue,
+    Return(Value),
+}
+
+/// Executes a program, writing the output of `printf` to `output`
+pub struct Interpreter<'a, W: Write> {
+    program: &'a Program,
+    functions: HashMap<&'a str, &'a FunctionDefinition>,
+    /// The names that refer to local variables in each function, see
+    /// [`FunctionDefinition::locals`]
+    locals: HashMap<&'a str, Arc<HashSet<&'a str>>>,
+    globals: HashMap<&'a str, Value>,
+    output: W,
+    depth: usize,
+}
+
+/// The variables of a function invocation
+struct Frame<'a> {
+    locals: Arc<HashSet<&'a str>>,
+    variables: HashMap<&'a str, Value>,
+}
+
+impl<'a, W: Write> Interpreter<'a, W> {
+    pub fn new(program: &'a Program, output: W) -> Interpreter<'a, W> {
+        let mut functions = HashMap::new();
+        let mut locals = HashMap::new();
+        for function in &program.functions {
+            // Later definitions of the same name are ignored
+            let name = function.name.name.as_str();
+            if functions.contains_key(name) {
+                continue;
+            }
+            functions.insert(name, function);
+            let names = function
+                .locals()
+                .into_iter()
+                .map(|name| name.name.as_str())
+                .collect();
+            locals.insert(name, Arc::new(names));
+        }
+        Interpreter {
+            program,
+            functions,
+            locals,
+            globals: HashMap::new(),
+            output,
+            depth: 0,
+        }
+    }
+
+    /// Run the `main` function and return its return value, which is the exit value of the program.
+    /// Calls recurse on the stack of the calling thread, which can overflow long before
+    /// [`MAX_CALL_DEPTH`] nested calls. Use [`run_with_large_stack`](Self::run_with_large_stack)
+    /// for programs with deep recursion.
+    pub fn run(&mut self) -> Evaluated<Value> {
+        self.globals.clear();
+        for global in &self.program.globals {
+            let value = match global.value {
+                Some(literal) => {
+                    let value = literal_value(literal);
+                    convert(global.ty, value).ok_or_else(|| {
+                        RuntimeError::new(
+                            RuntimeErrorKind::InvalidInitializer {
+                                expected: global.ty,
+                                found: value.ty(),
+                            },
+                            global.name.location,
+                        )
+                    })?
+                }
+                None => Value::zero(global.ty),
+            };
+            // Later declarations of the same name are ignored
+            self.globals.entry(&global.name.name).or_insert(value);
+        }
+
+        let main = self.functions.get("main").copied().ok_or(RuntimeError {
+            kind: RuntimeErrorKind::MissingMain,
+            location: None,
+        })?;
+        self.call_function(main, HashMap::new(), main.location)
+    }
+
+    /// [Run](Self::run) the program on a separate thread with a stack large enough for
+    /// [`MAX_CALL_DEPTH`] nested calls
+    pub fn run_with_large_stack(&mut self) -> Evaluated<Value>
+    where
+        W: Send,
+    {
+        thread::scope(|scope| {
+            thread::Builder::new()
+                .stack_size(MAX_CALL_DEPTH * STACK_SIZE_PER_CALL)
+                .spawn_scoped(scope, || self.run())
+                .expect("failed to spawn the interpreter thread")
+                .join()
+                .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+        })
+    }
+
+    /// Return the writer the output is written to
+    pub fn into_output(self) -> W {
+        self.output
+    }
+
+    fn call(&mut self, call: &'a FunctionCall, frame: &mut Frame<'a>) -> Evaluated<Value> {
+        let name = &call.name;
+        let function = self
+            .functions
+            .get(name.name.as_str())
+            .copied()
+            .ok_or_else(|| {
+                RuntimeError::new(
+                    RuntimeErrorKind::UndefinedFunction(name.name.clone()),
+                    name.location,
+                )
+            })?;
+        if call.arguments.len() != function.parameters.len() {
+            return Err(RuntimeError::new(
+                RuntimeErrorKind::ArgumentCount {
+                    function: name.name.clone(),
+                    expected: function.parameters.len(),
+                    found: call.arguments.len(),
+                },
+                name.location,
+            ));
+        }
+
+        let mut parameters = HashMap::new();
+        for (parameter, argument) in function.parameters.iter().zip(&call.arguments) {
+            let value = self.expr(argument, frame)?;
+            let value = convert(parameter.ty, value).ok_or_else(|| {
+                RuntimeError::new(
+                    RuntimeErrorKind::InvalidArgument {
+                        expected: parameter.ty,
+                        found: value.ty(),
+                    },
+                    argument.location,
+                )
+            })?;
+            parameters.insert(parameter.name.name.as_str(), value);
+        }
+        self.call_function(function, parameters, name.location)
+    }
+
+    /// Run the function with the given parameter values
+    fn call_function(
+        &mut self,
+        function: &'a FunctionDefinition,
+        variables: HashMap<&'a str, Value>,
+        location: Location,
+    ) -> Evaluated<Value> {
+        if self.depth == MAX_CALL_DEPTH {
+            return Err(RuntimeError::new(
+                RuntimeErrorKind::StackOverflow(MAX_CALL_DEPTH),
+                location,
+            ));
+        }
+        let mut frame = Frame {
+            locals: Arc::clone(&self.locals[function.name.name.as_str()]),
+            variables,
+        };
+        self.depth += 1;
+        let result = self.statements(&function.body, &mut frame);
+        self.depth -= 1;
+
+        let (value, location) = match result? {
+            Flow::Return(value) => (value, location),
+            Flow::Continue => (Value::Void, function.location),
+        };
+        convert(function.return_type, value).ok_or_else(|| {
+            RuntimeError::new(
+                RuntimeErrorKind::InvalidReturnValue {
+                    expected: function.return_type,
+                    found: value.ty(),
+                },
+                location,
+            )
+        })
+    }
+
+    fn statements(
+        &mut self,
+        statements: &'a [Statement],
+        frame: &mut Frame<'a>,
+    ) -> Evaluated<Flow> {
+        for statement in statements {
+            if let Flow::Return(value) = self.statement(statement, frame)? {
+                return Ok(Flow::Return(value));
+            }
+        }
+        Ok(Flow::Continue)
+    }
+
+    fn statement(&mut self, statement: &'a Statement, frame: &mut Frame<'a>) -> Evaluated<Flow> {
+        match &statement.kind {
+            StatementKind::Block(statements) => return self.statements(statements, frame),
+            StatementKind::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                if self.condition(condition, frame)? {
+                    return self.statement(then_branch, frame);
+                } else if let Some(else_branch) = else_branch {
+                    return self.statement(else_branch, frame);
+                }
+            }
+            StatementKind::While { condition, body } => {
+                while self.condition(condition, frame)? {
+                    if let Flow::Return(value) = self.statement(body, frame)? {
+                        return Ok(Flow::Return(value));
+                    }
+                }
+            }
+            StatementKind::DoWhile { body, condition } => loop {
+                if let Flow::Return(value) = self.statement(body, frame)? {
+                    return Ok(Flow::Return(value));
+                }
+                if !self.condition(condition, frame)? {
+                    break;
+                }
+            },
+            StatementKind::For {
+                init,
+                condition,
+                update,
+                body,
+            } => {
+                if let Some(init) = init {
+                    self.statement(init, frame)?;
+                }
+                while match condition {
+                    Some(condition) => self.condition(condition, frame)?,
+                    None => true,
+                } {
+                    if let Flow::Return(value) = self.statement(body, frame)? {
+                        return Ok(Flow::Return(value));
+                    }
+                    if let Some(update) = update {
+                        self.statement(update, frame)?;
+                    }
+                }
+            }
+            StatementKind::Declaration { ty, name, value } => {
+                let value = match value {
+                    Some(value) => {
+                        let location = value.location;
+                        let value = self.expr(value, frame)?;
+                        convert(*ty, value).ok_or_else(|| {
+                            RuntimeError::new(
+                                RuntimeErrorKind::InvalidInitializer {
+                                    expected: *ty,
+                                    found: value.ty(),
+                                },
+                                location,
+                            )
+                        })?
+                    }
+                    None => Value::zero(*ty),
+                };
+                frame.variables.insert(&name.name, value);
+            }
+            StatementKind::Return(value) => {
+                let value = match value {
+                    Some(value) => self.expr(value, frame)?,
+                    None => Value::Void,
+                };
+                return Ok(Flow::Return(value));
+            }
+            StatementKind::Printf(argument) => {
+                let result = match argument {
+                    PrintfArgument::String(string) => write!(self.output, "{}", string),
+                    PrintfArgument::Value(value) => {
+                        let value = self.expr(value, frame)?;
+                        if value == Value::Void {
+                            return Err(RuntimeError::invalid_operand(
+                                "printf",
+                                Type::Void,
+                                statement.location,
+                            ));
+                        }
+                        writeln!(self.output, "{}", value)
+                    }
+                };
+                result.map_err(|error| {
+                    RuntimeError::new(
+                        RuntimeErrorKind::Output(error.to_string()),
+                        statement.location,
+                    )
+                })?;
+            }
+            StatementKind::Assignment { target, value } => {
+                self.assign(target, value, frame)?;
+            }
+            StatementKind::Call(call) => {
+                self.call(call, frame)?;
+            }
+        }
+        Ok(Flow::Continue)
+    }
+
+    fn condition(&mut self, condition: &'a Expr, frame: &mut Frame<'a>) -> Evaluated<bool> {
+        match self.expr(condition, frame)? {
+            Value::Bool(value) => Ok(value),
+            value => Err(RuntimeError::new(
+                RuntimeErrorKind::InvalidCondition(value.ty()),
+                condition.location,
+            )),
+        }
+    }
+
+    fn assign(
+        &mut self,
+        target: &'a Identifier,
+        value: &'a Expr,
+        frame: &mut Frame<'a>,
+    ) -> Evaluated<Value> {
+        let value = self.expr(value, frame)?;
+        if value == Value::Void {
+            return Err(RuntimeError::invalid_operand(
+                "=",
+                Type::Void,
+                target.location,
+            ));
+        }
+        let variables = self.scope(frame, &target.name);
+        let value = assigned_value(variables.get(target.name.as_str()).copied(), value);
+        variables.insert(&target.name, value);
+        Ok(value)
+    }
+
+    /// Return the variables that contain the variable with the given name: the global variables if
+    /// the name refers to a global variable in the function of the frame, otherwise the local ones
+    fn scope<'s>(
+        &'s mut self,
+        frame: &'s mut Frame<'a>,
+        name: &str,
+    ) -> &'s mut HashMap<&'a str, Value> {
+        if !frame.locals.contains(name) && self.globals.contains_key(name) {
+            &mut self.globals
+        } else {
+            &mut frame.variables
+        }
+    }
+
+    fn expr(&mut self, expr: &'a Expr, frame: &mut Frame<'a>) -> Evaluated<Value> {
+        match &expr.kind {
+            ExprKind::Literal(literal) => Ok(literal_value(*literal)),
+            ExprKind::Variable(name) => {
+                let variables = self.scope(frame, &name.name);
+                variables.get(name.name.as_str()).copied().ok_or_else(|| {
+                    RuntimeError::new(
+                        RuntimeErrorKind::UndefinedVariable(name.name.clone()),
+                        name.location,
+                    )
+                })
+            }
+            ExprKind::Call(call) => self.call(call, frame),
+            ExprKind::Assignment { target, value } => self.assign(target, value, frame),
+            ExprKind::Unary { op, operand } => {
+                let value = self.expr(operand, frame)?;
+                unary(*op, value, expr.location)
+            }
+            ExprKind::Binary {
+                op: op @ (BinaryOperator::And | BinaryOperator::Or),
+                lhs,
+                rhs,
+            } => {
+                let lhs = logical(*op, self.expr(lhs, frame)?, expr.location)?;
+                // The right operand is only evaluated if it decides the result
+                if lhs == (*op == BinaryOperator::Or) {
+                    return Ok(Value::Bool(lhs));
+                }
+                let rhs = logical(*op, self.expr(rhs, frame)?, expr.location)?;
+                Ok(Value::Bool(rhs))
+            }
+            ExprKind::Binary { op, lhs, rhs } => {
+                let lhs = self.expr(lhs, frame)?;
+                let rhs = self.expr(rhs, frame)?;
+                binary(*op, lhs, rhs, expr.location)
+            }
+        }
+    }
+}
+
+pub(crate) fn literal_value(literal: Literal) -> Value {
+    match literal {
+        Literal::Int(value) => Value::Int(value),
+        Literal::Float(value) => Value::Float(value),
+        Literal::Bool(value) => Value::Bool(value),
+    }
+}
+
+/// Convert the value for storing it where a value of the `expected` type is needed, which only
+/// works for values of that type and `int`s that are needed as `float`s
+pub(crate) fn convert(expected: Type, value: Value) -> Option<Value> {
+    match (expected, value) {
+        (Type::Float, Value::Int(value)) => Some(Value::Float(value as f32)),
+        (expected, value) if expected == value.ty() => Some(value),
+        _ => None,
+    }
+}
+
+/// Return the value a variable has after `value` is assigned to it. A `float` variable stays a
+/// `float`, so an `int` assigned to it is converted. Any other variable takes the type of the
+/// value, for example assigning a `float` to an `int` variable makes it a `float`.
+pub(crate) fn assigned_value(previous: Option<Value>, value: Value) -> Value {
+    match (previous, value) {
+        (Some(Value::Float(_)), Value::Int(value)) => Value::Float(value as f32),
+        _ => value,
+    }
+}
+
+pub(crate) fn unary(op: UnaryOperator, value: Value, location: Location) -> Evaluated<Value> {
+    match (op, value) {
+        (UnaryOperator::Minus, Value::Int(value)) => Ok(Value::Int(value.wrapping_neg())),
+        (UnaryOperator::Minus, Value::Float(value)) => Ok(Value::Float(-value)),
+        (UnaryOperator::Plus, Value::Int(_) | Value::Float(_)) => Ok(value),
+        (UnaryOperator::Not, Value::Bool(value)) => Ok(Value::Bool(!value)),
+        (op, value) => Err(RuntimeError::invalid_operand(op, value.ty(), location)),
+    }
+}
+
+/// Check that `value` is an operand of `&&` or `||`
+pub(crate) fn logical(op: BinaryOperator, value: Value, location: Location) -> Evaluated<bool> {
+    match value {
+        Value::Bool(value) => Ok(value),
+        value => Err(RuntimeError::invalid_operand(op, value.ty(), location)),
+    }
+}
+
+/// Evaluate a binary operator other than `&&` and `||`
+pub(crate) fn binary(
+    op: BinaryOperator,
+    lhs: Value,
+    rhs: Value,
+    location: Location,
+) -> Evaluated<Value> {
+    use BinaryOperator::*;
+
+    let value = match (lhs, rhs) {
+        (Value::Int(lhs), Value::Int(rhs)) => match op {
+            Add => Value::Int(lhs.wrapping_add(rhs)),
+            Subtract => Value::Int(lhs.wrapping_sub(rhs)),
+            Multiply => Value::Int(lhs.wrapping_mul(rhs)),
+            Divide | Modulo if rhs == 0 => {
+                return Err(RuntimeError::new(
+                    RuntimeErrorKind::DivisionByZero,
+                    location,
+                ))
+            }
+            Divide => Value::Int(lhs.wrapping_div(rhs)),
+            Modulo => Value::Int(lhs.wrapping_rem(rhs)),
+            _ => Value::Bool(compare(op, lhs, rhs)),
+        },
+        // `%` only applies to `int`s
+        (Value::Int(_) | Value::Float(_), Value::Int(_) | Value::Float(_)) if op != Modulo => {
+            let (lhs, rhs) = (as_float(lhs), as_float(rhs));
+            match op {
+                Add => Value::Float(lhs + rhs),
+                Subtract => Value::Float(lhs - rhs),
+                Multiply => Value::Float(lhs * rhs),
+                Divide => Value::Float(lhs / rhs),
+                _ => Value::Bool(compare(op, lhs, rhs)),
+            }
+        }
+        (Value::Bool(lhs), Value::Bool(rhs)) if matches!(op, Equal | NotEqual) => {
+            Value::Bool(compare(op, lhs, rhs))
+        }
+        (lhs, rhs) => {
+            // Report the operand that does not fit the operator or the other operand
+            let found = match (lhs, op) {
+                (Value::Void, _) | (Value::Bool(_), Add | Subtract | Multiply | Divide) => lhs,
+                (Value::Bool(_), Less | LessEqual | Greater | GreaterEqual) => lhs,
+                (Value::Bool(_) | Value::Float(_), Modulo) => lhs,
+                _ => rhs,
+            };
+            return Err(RuntimeError::invalid_operand(op, found.ty(), location));
+        }
+    };
+    Ok(value)
+}
+
+fn compare<T: PartialOrd>(op: BinaryOperator, lhs: T, rhs: T) -> bool {
+    match op {
+        BinaryOperator::Equal => lhs == rhs,
+        BinaryOperator::NotEqual => lhs != rhs,
+        BinaryOperator::Less => lhs < rhs,
+        BinaryOperator::LessEqual => lhs <= rhs,
+        BinaryOperator::Greater => lhs > rhs,
+        BinaryOperator::GreaterEqual => lhs >= rhs,
+        _ => unreachable!("{} is not a comparison", op),
+    }
+}
+
+fn as_float(value: Value) -> f32 {
+    match value {
+        Value::Int(value) => value as f32,
+        Value::Float(value) => value,
+        _ => unreachable!("{:?} is not a number", value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ast::Type;
+    use crate::interpreter::{Interpreter, RuntimeError, RuntimeErrorKind, Value, MAX_CALL_DEPTH};
+    use crate::{C1Parser, Dialect};
+    use std::io::Write;
+
+    /// Run the program and return the exit value and the output
+    fn run(text: &str) -> (Result<Value, RuntimeError>, String) {
+        let program = C1Parser::parse_program_in(text, Dialect::C1).unwrap();
+        let mut output = Vec::new();
+        let result = Interpreter::new(&program, &mut output).run_with_large_stack();
+        (result, String::from_utf8(output).unwrap())
+    }
+
+    fn run_main(body: &str) -> Result<String, RuntimeErrorKind> {
+        match run(&format!("void main() {{ {} }}", body)) {
+            (Ok(_), output) => Ok(output),
+            (Err(error), _) => Err(error.kind),
+        }
+    }
+
+    #[test]
+    fn run_example() {
+        let (result, output) = run(include_str!("../tests/data/beispiel.c-1"));
+        assert_eq!(result, Ok(Value::Void));
+        assert_eq!(output, "3\n17\n3.141590\n");
+    }
+
+    #[test]
+    fn run_on_current_thread() {
+        // The writer does not have to be Send
+        let output: Box<dyn Write> = Box::new(Vec::new());
+        let program = C1Parser::parse_program("int main() { printf(1); return 2; }").unwrap();
+        assert_eq!(Interpreter::new(&program, output).run(), Ok(Value::Int(2)));
+    }
+
+    #[test]
+    fn exit_value() {
+        assert_eq!(run("int main() { return 3; }").0, Ok(Value::Int(3)));
+        assert_eq!(run("void main() { return; }").0, Ok(Value::Void));
+        assert_eq!(run("float main() { return 1; }").0, Ok(Value::Float(1.0)));
+    }
+
+    #[test]
+    fn arithmetic() {
+        assert_eq!(
+            run_main("printf(1 + 2 * 3); printf(7 / 2); printf(-7 / 2); printf(1 - 2 - 3);"),
+            Ok("7\n3\n-3\n-4\n".to_string())
+        );
+        assert_eq!(
+            run_main("printf(7 / 2.0); printf(0.5 * 3); printf(-1.5 + 1);"),
+            Ok("3.500000\n1.500000\n-0.500000\n".to_string())
+        );
+        // Integers wrap around
+        assert_eq!(
+            run_main("x = 2147483647; printf(x + 1); printf(-2147483647 - 2);"),
+            Ok("-2147483648\n2147483647\n".to_string())
+        );
+        assert_eq!(run_main("printf(1.0 / 0);"), Ok("inf\n".to_string()));
+        assert_eq!(
+            run_main("printf(1 / 0);"),
+            Err(RuntimeErrorKind::DivisionByZero)
+        );
+    }
+
+    #[test]
+    fn comparisons_and_logic() {
+        assert_eq!(
+            run_main("printf(1 < 2); printf(2 <= 1.5); printf(1 == 1.0); printf(true != false);"),
+            Ok("true\nfalse\ntrue\ntrue\n".to_string())
+        );
+        assert_eq!(
+            run_main("printf(true && false || true); printf((1 > 2) || (2 > 1) && false);"),
+            Ok("true\nfalse\n".to_string())
+        );
+        // The right operand is not evaluated, so the undefined function is never called
+        assert_eq!(
+            run_main("printf(false && undefined()); printf(true || undefined());"),
+            Ok("false\ntrue\n".to_string())
+        );
+    }
+
+    #[test]
+    fn unary_and_modulo() {
+        assert_eq!(
+            run_main("x = 3; y = -2; printf(-x * -y); printf(- -x); printf(+y); printf(-+1.5);"),
+            Ok("-6\n3\n-2\n-1.500000\n".to_string())
+        );
+        assert_eq!(
+            run_main("done = false; printf(!done); printf(!!done); printf(!(1 < 2) || true);"),
+            Ok("true\nfalse\ntrue\n".to_string())
+        );
+        // The sign of the remainder follows the dividend, as in C
+        assert_eq!(
+            run_main("printf(7 % 3); printf(-7 % 3); printf(7 % -3); printf(1 + 5 % 3 * 2);"),
+            Ok("1\n-1\n1\n5\n".to_string())
+        );
+        assert_eq!(
+            run_main("x = -2147483647 - 1; printf(x % -1); printf(-x);"),
+            Ok("0\n-2147483648\n".to_string())
+        );
+        assert_eq!(
+            run_main("printf(1 % 0);"),
+            Err(RuntimeErrorKind::DivisionByZero)
+        );
+        assert_eq!(
+            run_main("printf(5.0 % 2);"),
+            Err(RuntimeErrorKind::InvalidOperand {
+                operator: "%".to_string(),
+                found: Type::Float
+            })
+        );
+        assert_eq!(
+            run_main("printf(!1);"),
+            Err(RuntimeErrorKind::InvalidOperand {
+                operator: "!".to_string(),
+                found: Type::Int
+            })
+        );
+        assert_eq!(
+            run_main("printf(+true);"),
+            Err(RuntimeErrorKind::InvalidOperand {
+                operator: "+".to_string(),
+                found: Type::Bool
+            })
+        );
+    }
+
+    #[test]
+    fn variables_and_calls() {
+        assert_eq!(
+            run("int f() { x = 1; return x; }
+                 void main() { x = 2; y = x = x + f(); printf(x); printf(y); }")
+            .1,
+            "3\n3\n"
+        );
+        assert_eq!(
+            run_main("x = 0.5; printf(x = 2); printf(x / 4);"),
+            Ok("2.000000\n0.500000\n".to_string())
+        );
+        assert_eq!(
+            run_main("x = 1; x = 2.5; printf(x); int y = 3; y = true; printf(y);"),
+            Ok("2.500000\ntrue\n".to_string())
+        );
+        assert_eq!(
+            run_main("if (true) { printf(1); return; } printf(2);"),
+            Ok("1\n".to_string())
+        );
+    }
+
+    #[test]
+    fn declarations() {
+        assert_eq!(
+            run_main(
+                "int i; float f; bool b; printf(i); printf(f); printf(b);
+                 float x = 1; x = x / 2; int y = 7; y = y / 2; printf(x); printf(y);"
+            ),
+            Ok("0\n0.000000\nfalse\n0.500000\n3\n".to_string())
+        );
+        assert_eq!(
+            run_main("for (i = 0; i < 2; i = i + 1) { int n; printf(n); n = i + 5; }"),
+            Ok("0\n0\n".to_string())
+        );
+        assert_eq!(
+            run_main("int i = true;"),
+            Err(RuntimeErrorKind::InvalidInitializer {
+                expected: Type::Int,
+                found: Type::Bool
+            })
+        );
+    }
+
+    #[test]
+    fn printf_strings() {
+        assert_eq!(
+            run_main(r#"printf("x = "); printf(1); printf("\ttab \"quoted\"\n"); printf("");"#),
+            Ok("x = 1\n\ttab \"quoted\"\n".to_string())
+        );
+    }
+
+    #[test]
+    fn globals() {
+        assert_eq!(
+            run("int count; float total = 1; bool done = true;
+                 void add(int count) { total = total + count; }
+                 void tick() { count = count + 1; x = count; }
+                 void main() {
+                     tick(); tick(); add(10); int done = 5;
+                     printf(count); printf(total); printf(done); printf(flag());
+                 }
+                 bool flag() { return done; }")
+            .1,
+            "2\n11.000000\n5\ntrue\n"
+        );
+        assert_eq!(
+            run("int i = 1.5; void main() {}").0.unwrap_err().kind,
+            RuntimeErrorKind::InvalidInitializer {
+                expected: Type::Int,
+                found: Type::Float
+            }
+        );
+    }
+
+    #[test]
+    fn parameters() {
+        assert_eq!(
+            run(
+                "int factorial(int n) { if (n <= 1) return 1; return n * factorial(n - 1); }
+                 float scale(float x, int factor) { x = x * factor; return x; }
+                 void main() {
+                     printf(factorial(10)); printf(scale(3, 2)); n = 1; factorial(5); printf(n);
+                 }"
+            )
+            .1,
+            "3628800\n6.000000\n1\n"
+        );
+        assert_eq!(
+            run("void f(int a, int b) {} void main() { f(1); }")
+                .0
+                .unwrap_err()
+                .kind,
+            RuntimeErrorKind::ArgumentCount {
+                function: "f".to_string(),
+                expected: 2,
+                found: 1
+            }
+        );
+        assert_eq!(
+            run("void f(int a) {} void main() { f(1.5); }")
+                .0
+                .unwrap_err()
+                .kind,
+            RuntimeErrorKind::InvalidArgument {
+                expected: Type::Int,
+                found: Type::Float
+            }
+        );
+    }
+
+    #[test]
+    fn if_else() {
+        assert_eq!(
+            run("void main() {
+                   for (x = -1; x <= 1; x = x + 1)
+                     if (x < 0) printf(-1); else if (x == 0) printf(0); else printf(1);
+                 }")
+            .1,
+            "-1\n0\n1\n"
+        );
+        // The else belongs to the inner if
+        assert_eq!(
+            run_main("if (false) if (true) printf(1); else printf(2); printf(3);"),
+            Ok("3\n".to_string())
+        );
+        assert_eq!(
+            run_main("if (true) if (false) printf(1); else printf(2);"),
+            Ok("2\n".to_string())
+        );
+    }
+
+    #[test]
+    fn while_loops() {
+        assert_eq!(
+            run_main("i = 0; while (i < 3) { printf(i); i = i + 1; } printf(i);"),
+            Ok("0\n1\n2\n3\n".to_string())
+        );
+        assert_eq!(
+            run(
+                "int f() { i = 1; while (true) { i = i * 2; if (i > 100) return i; } }
+                 void main() { while (false) printf(0); printf(f()); }"
+            )
+            .1,
+            "128\n"
+        );
+        // The body of a do-while loop runs at least once
+        assert_eq!(
+            run_main(
+                "i = 5; do { printf(i); i = i + 1; } while (i < 3);
+                 do i = i - 1; while (i > 0); printf(i);"
+            ),
+            Ok("5\n0\n".to_string())
+        );
+        assert_eq!(
+            run_main("for (i = 0; i < 3; i = i + 1) printf(i); printf(i);"),
+            Ok("0\n1\n2\n3\n".to_string())
+        );
+        assert_eq!(
+            run(
+                "int f() { i = 0; for (;;) { i = i + 1; if (i == 4) return i; } }
+                 void main() { for (; false;) printf(0); printf(f()); }"
+            )
+            .1,
+            "4\n"
+        );
+        assert_eq!(
+            run_main("while (1) {}"),
+            Err(RuntimeErrorKind::InvalidCondition(Type::Int))
+        );
+    }
+
+    #[test]
+    fn runtime_errors() {
+        let (result, _) = run("void main() {\n  printf(x);\n}");
+        let error = result.unwrap_err();
+        assert_eq!(
+            error.kind,
+            RuntimeErrorKind::UndefinedVariable("x".to_string())
+        );
+        assert_eq!(error.location.map(|location| location.column), Some(10));
+        assert_eq!(
+            error.to_string(),
+            "Variable 'x' is used before it is assigned at line 2"
+        );
+
+        assert_eq!(
+            run("int f() { return 1; }").0.unwrap_err().kind,
+            RuntimeErrorKind::MissingMain
+        );
+        assert_eq!(
+            run_main("foo();"),
+            Err(RuntimeErrorKind::UndefinedFunction("foo".to_string()))
+        );
+        assert_eq!(
+            run_main("if (1) printf(1);"),
+            Err(RuntimeErrorKind::InvalidCondition(Type::Int))
+        );
+        assert_eq!(
+            run_main("printf(1 + true);"),
+            Err(RuntimeErrorKind::InvalidOperand {
+                operator: "+".to_string(),
+                found: Type::Bool
+            })
+        );
+        assert_eq!(
+            run("void f() { } void main() { printf(f()); }")
+                .0
+                .unwrap_err()
+                .kind,
+            RuntimeErrorKind::InvalidOperand {
+                operator: "printf".to_string(),
+                found: Type::Void
+            }
+        );
+        assert_eq!(
+            run("int main() { }").0.unwrap_err().kind,
+            RuntimeErrorKind::InvalidReturnValue {
+                expected: Type::Int,
+                found: Type::Void
+            }
+        );
+        assert_eq!(
+            run("void f() { f(); } void main() { f(); }")
+                .0
+                .unwrap_err()
+                .kind,
+            RuntimeErrorKind::StackOverflow(MAX_CALL_DEPTH)
+        );
+    }
+
+    #[test]
+    fn deep_recursion() {
+        let text = format!(
+            "int down(int n) {{ if (n == 0) return 0; printf(n); return down(n - 1) + 1; }}
+             int main() {{ return down({}); }}",
+            MAX_CALL_DEPTH - 2
+        );
+        // main and the calls of down with n = MAX_CALL_DEPTH - 2, ..., 0
+        let (result, output) = run(&text);
+        assert_eq!(result, Ok(Value::Int(MAX_CALL_DEPTH as i32 - 2)));
+        assert!(output.ends_with("\n2\n1\n"));
+    }
+}
@@ -1,4 +1,6 @@
 use logos::{Lexer, Logos};
+use std::fmt;
+use std::ops::Range;
 
 #[derive(Logos, Debug, PartialEq, Copy, Clone)]
 pub enum C1Token {
@@ -47,6 +49,13 @@ pub enum C1Token {
     #[token("/")]
     Slash,
 
+    #[token("%")]
+    Percent,
+
+    #[token("!")]
+    /// !
+    Not,
+
     #[token("=")]
     /// =
     Assign,
@@ -114,21 +123,23 @@ pub enum C1Token {
     #[regex("true|false")]
     ConstBoolean,
 
-    #[regex("\"[^\n\"]*\"")]
+    /// A string on a single line. A backslash escapes the following character, so `\"` does not end
+    /// the string.
+    #[regex(r#""([^\n"\\]|\\[^\n])*""#)]
     ConstString,
 
     #[regex("[a-zA-Z]+[0-9a-zA-Z]*")]
     Identifier,
 
-    #[regex(r"/\*[^\*/]*\*/", logos::skip)]
+    // Comments, whitespace and linebreaks are trivia. They are filtered out by C1Lexer, which
+    // only keeps them in trivia-preserving mode (see C1Lexer::with_trivia).
+    #[regex(r"/\*[^\*/]*\*/")]
     CComment,
 
-    #[regex("//[^\n]*(\n)?", logos::skip)]
+    #[regex("//[^\n]*(\n)?")]
     CPPComment,
 
-    // We can also use this variant to define whitespace,
-    // or any other matches we wish to skip.
-    #[regex(r"[ \t\f\r]+", logos::skip)]
+    #[regex(r"[ \t\f\r]+")]
     Whitespace,
 
     #[regex(r"[\n]")]
@@ -140,10 +151,78 @@ pub enum C1Token {
     Error,
 }
 
+impl C1Token {
+    /// Check whether the token is trivia, i.e. a comment, whitespace or a linebreak, which carries
+    /// no meaning for the parser
+    pub fn is_trivia(&self) -> bool {
+        matches!(
+            self,
+            C1Token::CComment | C1Token::CPPComment | C1Token::Whitespace | C1Token::Linebreak
+        )
+    }
+}
+
+/// A comment, whitespace or linebreak that was skipped by the lexer in trivia-preserving mode
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Trivia<'a> {
+    pub kind: C1Token,
+    pub text: &'a str,
+}
+
+impl fmt::Display for C1Token {
+    /// Describe the token as it would appear in an error message, e.g. `'=='` or `identifier`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let description = match self {
+            C1Token::KwBoolean => "'bool'",
+            C1Token::KwDo => "'do'",
+            C1Token::KwElse => "'else'",
+            C1Token::KwFloat => "'float'",
+            C1Token::KwFor => "'for'",
+            C1Token::KwIf => "'if'",
+            C1Token::KwInt => "'int'",
+            C1Token::KwPrintf => "'printf'",
+            C1Token::KwReturn => "'return'",
+            C1Token::KwVoid => "'void'",
+            C1Token::KwWhile => "'while'",
+            C1Token::Plus => "'+'",
+            C1Token::Minus => "'-'",
+            C1Token::Asterisk => "'*'",
+            C1Token::Slash => "'/'",
+            C1Token::Percent => "'%'",
+            C1Token::Not => "'!'",
+            C1Token::Assign => "'='",
+            C1Token::Equal => "'=='",
+            C1Token::NotEqual => "'!='",
+            C1Token::Less => "'<'",
+            C1Token::Greater => "'>'",
+            C1Token::LessEqual => "'<='",
+            C1Token::GreaterEqual => "'>='",
+            C1Token::And => "'&&'",
+            C1Token::Or => "'||'",
+            C1Token::Comma => "','",
+            C1Token::Semicolon => "';'",
+            C1Token::LeftParenthesis => "'('",
+            C1Token::RightParenthesis => "')'",
+            C1Token::LeftBrace => "'{'",
+            C1Token::RightBrace => "'}'",
+            C1Token::ConstInt => "integer constant",
+            C1Token::ConstFloat => "float constant",
+            C1Token::ConstBoolean => "boolean constant",
+            C1Token::ConstString => "string constant",
+            C1Token::Identifier => "identifier",
+            C1Token::CComment | C1Token::CPPComment => "comment",
+            C1Token::Whitespace => "whitespace",
+            C1Token::Linebreak => "line break",
+            C1Token::Error => "invalid token",
+        };
+        f.write_str(description)
+    }
+}
+
 /// # Overview
 /// Extended lexer based on the logos crate. The lexer keeps track of the current token and the next token
-/// in the lexed text. Furthermore, the lexer keeps track of the line number in which each token is
-/// located, and of the text associated with each token.
+/// in the lexed text. Furthermore, the lexer keeps track of the line number, the column and the byte
+/// range in which each token is located, and of the text associated with each token.
 ///
 /// # Examples
 /// ```
@@ -173,20 +252,48 @@ pub enum C1Token {
 /// assert_eq!(lexer.peek_token(), Some(C1Token::Identifier));
 /// assert_eq!(lexer.peek_text(), Some("x"));
 /// assert_eq!(lexer.peek_line_number(), Some(2));
+/// assert_eq!(lexer.peek_column(), Some(33));
 /// ```
 pub struct C1Lexer<'a> {
     logos_lexer: Lexer<'a, C1Token>,
     logos_line_number: usize,
+    keep_trivia: bool,
+    trailing_trivia: Vec<Trivia<'a>>,
     current_token: Option<TokenData<'a>>,
     peek_token: Option<TokenData<'a>>,
 }
 
 impl<'a> C1Lexer<'a> {
     /// Initialize a new C1Lexer for the given string slice
-    pub fn new(text: &'a str) -> C1Lexer {
+    pub fn new(text: &'a str) -> C1Lexer<'a> {
+        Self::initialize(text, false)
+    }
+
+    /// Initialize a new C1Lexer for the given string slice, which records the comments, whitespace
+    /// and linebreaks in front of each token.
+    /// ```
+    /// use cb_3::{C1Lexer, C1Token};
+    /// let mut lexer = C1Lexer::with_trivia("x /* c */ y\n");
+    ///
+    /// assert!(lexer.current_trivia().is_empty());
+    /// lexer.eat();
+    /// let trivia: Vec<_> = lexer.current_trivia().iter().map(|trivia| trivia.text).collect();
+    /// assert_eq!(trivia, [" ", "/* c */", " "]);
+    ///
+    /// lexer.eat();
+    /// assert_eq!(lexer.current_token(), None);
+    /// assert_eq!(lexer.trailing_trivia()[0].kind, C1Token::Linebreak);
+    /// ```
+    pub fn with_trivia(text: &'a str) -> C1Lexer<'a> {
+        Self::initialize(text, true)
+    }
+
+    fn initialize(text: &'a str, keep_trivia: bool) -> C1Lexer<'a> {
         let mut lexer = C1Lexer {
             logos_lexer: C1Token::lexer(text),
             logos_line_number: 1,
+            keep_trivia,
+            trailing_trivia: Vec::new(),
             current_token: None,
             peek_token: None,
         };
@@ -245,6 +352,56 @@ impl<'a> C1Lexer<'a> {
         self.peek_token.line_number()
     }
 
+    /// Return the 1-based column (counted in characters) where the current token starts
+    /// ```
+    /// use cb_3::C1Lexer;
+    /// let lexer = C1Lexer::new("int\n  x");
+    ///
+    /// assert_eq!(lexer.current_column(), Some(1));
+    /// assert_eq!(lexer.peek_column(), Some(3));
+    /// ```
+    pub fn current_column(&self) -> Option<usize> {
+        self.current_token.column()
+    }
+
+    /// Return the 1-based column (counted in characters) where the next token starts
+    pub fn peek_column(&self) -> Option<usize> {
+        self.peek_token.column()
+    }
+
+    /// Return the byte range of the current token in the lexed text
+    /// ```
+    /// use cb_3::C1Lexer;
+    /// let text = "foo  bar";
+    /// let lexer = C1Lexer::new(text);
+    ///
+    /// assert_eq!(lexer.current_span(), Some(0..3));
+    /// assert_eq!(lexer.peek_span(), Some(5..8));
+    /// assert_eq!(&text[lexer.peek_span().unwrap()], "bar");
+    /// ```
+    pub fn current_span(&self) -> Option<Range<usize>> {
+        self.current_token.span()
+    }
+
+    /// Return the byte range of the next token in the lexed text
+    pub fn peek_span(&self) -> Option<Range<usize>> {
+        self.peek_token.span()
+    }
+
+    /// Return the trivia between the previous token and the current token. Always empty unless the
+    /// lexer was created with [`C1Lexer::with_trivia`].
+    pub fn current_trivia(&self) -> &[Trivia<'a>] {
+        self.current_token
+            .as_ref()
+            .map_or(&[], |data| data.leading_trivia.as_slice())
+    }
+
+    /// Return the trivia after the last token of the text. Complete once the current token is
+    /// `None`; always empty unless the lexer was created with [`C1Lexer::with_trivia`].
+    pub fn trailing_trivia(&self) -> &[Trivia<'a>] {
+        &self.trailing_trivia
+    }
+
     /// Drop the current token and retrieve the next token in the text.
     /// ```
     /// use cb_3::{C1Lexer, C1Token};
@@ -273,24 +430,41 @@ impl<'a> C1Lexer<'a> {
     /// Private method for reading the next token from the logos::Lexer and extracting the required data
     /// from it
     fn next_token(&mut self) -> Option<TokenData<'a>> {
+        let mut leading_trivia = Vec::new();
         // Retrieve the next token from the internal lexer
-        if let Some(c1_token) = self.logos_lexer.next() {
-            match c1_token {
-                C1Token::Linebreak => {
-                    // If the token is a linebreak, increase the line number and get the next token
-                    self.logos_line_number += 1;
-                    self.next_token()
+        while let Some(c1_token) = self.logos_lexer.next() {
+            let text = self.logos_lexer.slice();
+            if c1_token.is_trivia() {
+                // If the token is trivia, count the linebreaks it contains and get the next token
+                self.logos_line_number += text.matches('\n').count();
+                if self.keep_trivia {
+                    leading_trivia.push(Trivia {
+                        kind: c1_token,
+                        text,
+                    });
                 }
-                _ => Some(TokenData {
-                    // If the token is not a linebreak, initialize and return a TokenData instance
-                    token_type: c1_token,
-                    token_text: self.logos_lexer.slice(),
-                    token_line: self.logos_line_number,
-                }),
+                continue;
             }
-        } else {
-            None
+
+            // Otherwise initialize and return a TokenData instance
+            let span = self.logos_lexer.span();
+            let line_start = self.logos_lexer.source()[..span.start]
+                .rfind('\n')
+                .map_or(0, |index| index + 1);
+            return Some(TokenData {
+                token_type: c1_token,
+                token_text: text,
+                token_line: self.logos_line_number,
+                token_column: self.logos_lexer.source()[line_start..span.start]
+                    .chars()
+                    .count()
+                    + 1,
+                token_span: span,
+                leading_trivia,
+            });
         }
+        self.trailing_trivia.append(&mut leading_trivia);
+        None
     }
 }
 
@@ -299,6 +473,9 @@ struct TokenData<'a> {
     token_type: C1Token,
     token_text: &'a str,
     token_line: usize,
+    token_column: usize,
+    token_span: Range<usize>,
+    leading_trivia: Vec<Trivia<'a>>,
 }
 
 /// Hidden trait that makes it possible to implemented the required getter functionality directly for
@@ -310,6 +487,10 @@ trait TokenDataProvider<'a> {
     fn text(&self) -> Option<&str>;
     /// Return the line number of the token
     fn line_number(&self) -> Option<usize>;
+    /// Return the column of the token
+    fn column(&self) -> Option<usize>;
+    /// Return the byte range of the token
+    fn span(&self) -> Option<Range<usize>>;
 }
 
 impl<'a> TokenDataProvider<'a> for Option<TokenData<'a>> {
@@ -324,6 +505,14 @@ impl<'a> TokenDataProvider<'a> for Option<TokenData<'a>> {
     fn line_number(&self) -> Option<usize> {
         self.as_ref().map(|data| data.token_line)
     }
+
+    fn column(&self) -> Option<usize> {
+        self.as_ref().map(|data| data.token_column)
+    }
+
+    fn span(&self) -> Option<Range<usize>> {
+        self.as_ref().map(|data| data.token_span.clone())
+    }
 }
 
 #[cfg(test)]
@@ -358,6 +547,109 @@ mod tests {
         assert_eq!(lexer2.peek_line_number(), Some(1));
     }
 
+    #[test]
+    fn lines_in_comments_are_counted() {
+        let mut lexer = C1Lexer::new("a // comment\nb /* two\nlines */ c");
+        lexer.eat();
+        assert_eq!(lexer.current_line_number(), Some(2));
+        assert_eq!(lexer.peek_line_number(), Some(3));
+    }
+
+    #[test]
+    fn trivia_is_preserved() {
+        let text = "  int // comment\n\tx /* c */=1;\n";
+        let mut lexer = C1Lexer::with_trivia(text);
+        let mut reconstructed = String::new();
+        while let Some(token_text) = lexer.current_text() {
+            assert!(lexer.current_trivia().iter().all(|t| t.kind.is_trivia()));
+            reconstructed.extend(lexer.current_trivia().iter().map(|t| t.text));
+            reconstructed.push_str(token_text);
+            lexer.eat();
+        }
+        reconstructed.extend(lexer.trailing_trivia().iter().map(|t| t.text));
+        assert_eq!(reconstructed, text);
+
+        // Without trivia mode nothing is recorded
+        let mut lexer = C1Lexer::new(text);
+        lexer.eat();
+        assert!(lexer.current_trivia().is_empty());
+        assert_eq!(lexer.current_token(), Some(C1Token::Identifier));
+    }
+
+    #[test]
+    fn string_recognition() {
+        let text = r#"printf("a \"quoted\" \\ string\n") "" "unterminated"#;
+        let mut lexer = C1Lexer::new(text);
+        lexer.eat();
+        lexer.eat();
+        assert_eq!(lexer.current_token(), Some(C1Token::ConstString));
+        assert_eq!(lexer.current_text(), Some(r#""a \"quoted\" \\ string\n""#));
+        lexer.eat();
+        lexer.eat();
+        assert_eq!(lexer.current_token(), Some(C1Token::ConstString));
+        assert_eq!(lexer.current_text(), Some(r#""""#));
+        lexer.eat();
+        assert_eq!(lexer.current_token(), Some(C1Token::Error));
+
+        let lexer = C1Lexer::new("\"line\nbreak\"");
+        assert_eq!(lexer.current_token(), Some(C1Token::Error));
+    }
+
+    #[test]
+    fn operator_recognition() {
+        let mut lexer = C1Lexer::new("!a != b % c");
+        let mut tokens = Vec::new();
+        while let Some(token) = lexer.current_token() {
+            tokens.push(token);
+            lexer.eat();
+        }
+        assert_eq!(
+            tokens,
+            [
+                C1Token::Not,
+                C1Token::Identifier,
+                C1Token::NotEqual,
+                C1Token::Identifier,
+                C1Token::Percent,
+                C1Token::Identifier
+            ]
+        );
+    }
+
+    #[test]
+    fn columns_are_counted() {
+        let mut lexer = C1Lexer::new("int main\n\t x=1;");
+        assert_eq!(lexer.current_column(), Some(1));
+        assert_eq!(lexer.peek_column(), Some(5));
+        lexer.eat();
+        lexer.eat();
+        // tabs count as a single column
+        assert_eq!(lexer.current_column(), Some(3));
+        assert_eq!(lexer.peek_column(), Some(4));
+
+        // columns are counted in characters, not bytes
+        let lexer = C1Lexer::new("/* ü */ x");
+        assert_eq!(lexer.current_column(), Some(9));
+        assert_eq!(lexer.current_span(), Some(9..10));
+    }
+
+    #[test]
+    fn spans_are_byte_ranges() {
+        let text = "void main() {\n  x = 42;\n}";
+        let mut lexer = C1Lexer::new(text);
+        let mut tokens = Vec::new();
+        while let Some(span) = lexer.current_span() {
+            tokens.push(&text[span]);
+            lexer.eat();
+        }
+        assert_eq!(
+            tokens,
+            ["void", "main", "(", ")", "{", "x", "=", "42", ";", "}"]
+        );
+        assert_eq!(lexer.current_span(), None);
+        assert_eq!(lexer.current_column(), None);
+    }
+
     #[test]
     fn float_recognition() {
         let lexer = C1Lexer::new("1.2");
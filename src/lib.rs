@@ -1,12 +1,31 @@
+// Errors carry their full location and context and are only built on the failure path, so their
+// size does not matter for the happy path
+#![allow(clippy::result_large_err)]
+
+pub mod ast;
+pub mod bytecode;
+pub mod cgen;
+pub mod cst;
+pub mod diagnostic;
+mod error;
+pub mod formatter;
+pub mod interpreter;
 mod lexer;
+pub mod semantic;
+pub mod typecheck;
+pub mod vm;
+pub mod wat;
+pub mod x86_64;
 
 // Type definition for the Result that is being used by the parser. You may change it to anything
 // you want
 pub type ParseResult = Result<(), String>;
 
+pub use error::{Nonterminal, ParseError, ParseErrorKind};
 pub use lexer::C1Lexer;
 pub use lexer::C1Token;
+pub use lexer::Trivia;
 
 // You will need a re-export of your C1Parser definition. Here is an example:
 mod parser;
-pub use parser::C1Parser;
+pub use parser::{C1Parser, Dialect};
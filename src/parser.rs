@@ -1,80 +1,408 @@
+use crate::ast::{
+    BinaryOperator, Expr, ExprKind, FunctionCall, FunctionDefinition, GlobalDeclaration,
+    Identifier, Literal, Location, Parameter, PrintfArgument, Program, Statement, StatementKind,
+    Type, UnaryOperator,
+};
+use crate::cst::{GreenNodeBuilder, SyntaxNode};
+use crate::error::{Nonterminal, ParseError, ParseErrorKind};
 use crate::lexer::{C1Lexer, C1Token};
 use crate::ParseResult;
-use std::ops::{Deref, DerefMut};
+use std::ops::{Deref, DerefMut, Range};
+
+/// Result type of the individual parsing methods, carrying the parsed AST node
+type Parsed<T> = Result<T, ParseError>;
+
+/// type ::= <KW_BOOLEAN> | <KW_FLOAT> | <KW_INT> | <KW_VOID>
+const TYPE_KEYWORDS: &[C1Token] = &[
+    C1Token::KwBoolean,
+    C1Token::KwFloat,
+    C1Token::KwInt,
+    C1Token::KwVoid,
+];
+
+/// Tokens that can start a statement in C(-1)
+const STATEMENT_START: &[C1Token] = &[
+    C1Token::KwIf,
+    C1Token::KwReturn,
+    C1Token::KwPrintf,
+    C1Token::Identifier,
+];
+
+/// Tokens that can start a statement in C1
+const C1_STATEMENT_START: &[C1Token] = &[
+    C1Token::KwIf,
+    C1Token::KwWhile,
+    C1Token::KwDo,
+    C1Token::KwFor,
+    C1Token::KwBoolean,
+    C1Token::KwFloat,
+    C1Token::KwInt,
+    C1Token::KwVoid,
+    C1Token::KwReturn,
+    C1Token::KwPrintf,
+    C1Token::Identifier,
+];
+
+/// constant ::= <CONST_INT> | <CONST_FLOAT> | <CONST_BOOLEAN>
+const CONSTANTS: &[C1Token] = &[
+    C1Token::ConstInt,
+    C1Token::ConstFloat,
+    C1Token::ConstBoolean,
+];
+
+/// Tokens that can start a factor
+const FACTOR_START: &[C1Token] = &[
+    C1Token::ConstInt,
+    C1Token::ConstFloat,
+    C1Token::ConstBoolean,
+    C1Token::Identifier,
+    C1Token::LeftParenthesis,
+];
+
+/// The language accepted by the parser
+///
+/// Every C(-1) program is also a C1 program. The grammars are documented in `c-1-syntax.ebnf` and
+/// `c1-syntax.ebnf`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Dialect {
+    /// The C(-1) subset of C1 used in the exercises
+    #[default]
+    CMinus1,
+    /// C1, which adds global and local variable declarations, parameters and arguments, `while`,
+    /// `do`-`while` and `for` loops, `else` branches and string constants in `printf`
+    C1,
+}
+
+/// A top-level element of a program
+enum Definition {
+    Global(GlobalDeclaration),
+    Function(FunctionDefinition),
+}
 
-pub struct C1Parser<'a>(C1Lexer<'a>);
+pub struct C1Parser<'a> {
+    lexer: C1Lexer<'a>,
+    dialect: Dialect,
+    /// Whether syntax errors are collected and skipped instead of aborting the parse
+    recovering: bool,
+    /// Errors collected in recovery mode
+    errors: Vec<ParseError>,
+    /// Builder for the concrete syntax tree, if one is requested
+    cst: Option<GreenNodeBuilder>,
+    /// Whether the trivia in front of the current token has already been added to the concrete
+    /// syntax tree
+    trivia_recorded: bool,
+}
 // Implement Deref and DerefMut to enable the direct use of the lexer's methods
 impl<'a> Deref for C1Parser<'a> {
     type Target = C1Lexer<'a>;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.lexer
     }
 }
 
-impl<'a> DerefMut for C1Parser<'a> {
+impl DerefMut for C1Parser<'_> {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+        &mut self.lexer
     }
 }
 
 impl<'a> C1Parser<'a> {
+    /// Check the given text for syntax errors without keeping the parsed program
     pub fn parse(text: &str) -> ParseResult {
-        let mut parser = Self::initialize_parser(text);
+        Self::parse_program(text)
+            .map(|_| ())
+            .map_err(|error| error.to_string())
+    }
+
+    /// Parse the given C(-1) text into its abstract syntax tree
+    pub fn parse_program(text: &str) -> Result<Program, ParseError> {
+        Self::parse_program_in(text, Dialect::CMinus1)
+    }
+
+    /// Parse the given text of the given dialect into its abstract syntax tree
+    pub fn parse_program_in(text: &str, dialect: Dialect) -> Result<Program, ParseError> {
+        let mut parser = Self::initialize_parser(text, dialect);
         parser.program()
     }
 
-    fn initialize_parser(text: &str) -> C1Parser {
-        C1Parser(C1Lexer::new(text))
+    /// Parse the given C(-1) text, reporting all syntax errors instead of stopping at the first one.
+    ///
+    /// After an error the parser skips ahead to the next `;`, `}` or type keyword and continues from
+    /// there. The returned program contains everything that could be parsed; it is only complete if
+    /// no errors were reported.
+    pub fn parse_with_recovery(text: &str) -> (Program, Vec<ParseError>) {
+        Self::parse_with_recovery_in(text, Dialect::CMinus1)
+    }
+
+    /// Parse the given text of the given dialect with error recovery, see
+    /// [`parse_with_recovery`](C1Parser::parse_with_recovery)
+    pub fn parse_with_recovery_in(text: &str, dialect: Dialect) -> (Program, Vec<ParseError>) {
+        let mut parser = Self::initialize_parser(text, dialect);
+        parser.recovering = true;
+        // In recovery mode `program` records every error and never fails
+        let program = parser.program().unwrap_or(Program {
+            globals: Vec::new(),
+            functions: Vec::new(),
+        });
+        (program, parser.errors)
     }
 
-    /// program ::= ( functiondefinition )* <EOF>
-    fn program(&mut self) -> ParseResult {
-        while let Some(_) = self.current_token() {
-            self.function_definition()?;
+    /// Parse the given C(-1) text into a lossless concrete syntax tree, which keeps every token
+    /// including comments, whitespace and linebreaks. Its nodes are the nonterminals of the grammar.
+    pub fn parse_cst(text: &str) -> Result<SyntaxNode, ParseError> {
+        Self::parse_cst_in(text, Dialect::CMinus1)
+    }
+
+    /// Parse the given text of the given dialect into a lossless concrete syntax tree, see
+    /// [`parse_cst`](C1Parser::parse_cst)
+    pub fn parse_cst_in(text: &str, dialect: Dialect) -> Result<SyntaxNode, ParseError> {
+        let mut parser = C1Parser {
+            lexer: C1Lexer::with_trivia(text),
+            cst: Some(GreenNodeBuilder::default()),
+            ..Self::initialize_parser(text, dialect)
+        };
+        parser.program()?;
+        let green = parser.cst.take().map(GreenNodeBuilder::finish);
+        Ok(SyntaxNode::new_root(
+            green.expect("the builder is only taken here"),
+        ))
+    }
+
+    fn initialize_parser(text: &str, dialect: Dialect) -> C1Parser<'_> {
+        C1Parser {
+            lexer: C1Lexer::new(text),
+            dialect,
+            recovering: false,
+            errors: Vec::new(),
+            cst: None,
+            trivia_recorded: false,
+        }
+    }
+
+    /// program ::= ( functiondefinition | globaldeclaration )* <EOF>
+    ///
+    /// Global declarations are only allowed in C1.
+    fn program(&mut self) -> Parsed<Program> {
+        self.start_node(Nonterminal::Program);
+        let mut globals = Vec::new();
+        let mut functions = Vec::new();
+        while self.current_token().is_some() {
+            let start = self.current_span();
+            match self.definition() {
+                Ok(Definition::Global(global)) => globals.push(global),
+                Ok(Definition::Function(function)) => functions.push(function),
+                Err(error) => {
+                    self.recover(error)?;
+                    self.synchronize_function(start);
+                }
+            }
         }
 
         // <EOF> == Error?
         match self.current_token() {
-            Some(_) => {
-                return Err(self.error_message_current("Expected EOF"));
-            }
+            Some(_) => Err(self.error_current_with_kind(
+                ParseErrorKind::ExpectedEof,
+                Nonterminal::Program,
+                &[],
+                "Expected EOF",
+            )),
             None => {
-                return Ok(());
+                self.finish_program();
+                Ok(Program { globals, functions })
             }
         }
     }
 
-    /// functiondefinition ::= type <ID> "(" ")" "{" statementlist "}"
-    fn function_definition(&mut self) -> ParseResult {
-        self.return_type()?;
-        self.check_and_eat_token(&C1Token::Identifier, "Expected function name")?;
-        self.check_and_eat_token(&C1Token::LeftParenthesis, "Expected '('")?;
-        self.check_and_eat_token(&C1Token::RightParenthesis, "Expected ')'")?;
-        self.check_and_eat_token(&C1Token::LeftBrace, "Expected '{'")?;
-        self.statement_list()?;
-        self.check_and_eat_token(&C1Token::RightBrace, "Expected '}'")?;
-        Ok(())
+    /// Parse a function definition or a global declaration. Both start with type <ID>, only the
+    /// token after the identifier tells them apart: a function definition continues with "(".
+    fn definition(&mut self) -> Parsed<Definition> {
+        let checkpoint = self.checkpoint();
+        let location = self.current_location();
+        let ty = self.return_type()?;
+        if self.dialect == Dialect::C1
+            && self.current_matches(&C1Token::Identifier)
+            && !self.next_matches(&C1Token::LeftParenthesis)
+        {
+            let global = self.global_declaration(checkpoint, ty, location)?;
+            Ok(Definition::Global(global))
+        } else {
+            let function = self.function_definition(checkpoint, ty, location)?;
+            Ok(Definition::Function(function))
+        }
+    }
+
+    /// globaldeclaration ::= type <ID> ( "=" constant )? ";"
+    ///
+    /// The type at the given checkpoint has already been parsed.
+    fn global_declaration(
+        &mut self,
+        checkpoint: usize,
+        ty: Type,
+        location: Location,
+    ) -> Parsed<GlobalDeclaration> {
+        self.start_node_at(checkpoint, Nonterminal::GlobalDeclaration);
+        let name = self.identifier(Nonterminal::GlobalDeclaration, "Expected variable name")?;
+        let value = if self.current_matches(&C1Token::Assign) {
+            self.eat();
+            Some(self.constant(Nonterminal::GlobalDeclaration)?)
+        } else {
+            None
+        };
+        self.check_and_eat_token(
+            &C1Token::Semicolon,
+            Nonterminal::GlobalDeclaration,
+            "Expected ';' after global declaration",
+        )?;
+        self.finish_node();
+        Ok(GlobalDeclaration {
+            ty,
+            name,
+            value,
+            location,
+        })
+    }
+
+    /// functiondefinition ::= type <ID> "(" ( parameterlist )? ")" "{" statementlist "}"
+    ///
+    /// The return type at the given checkpoint has already been parsed. The parameter list is only
+    /// allowed in C1.
+    fn function_definition(
+        &mut self,
+        checkpoint: usize,
+        return_type: Type,
+        location: Location,
+    ) -> Parsed<FunctionDefinition> {
+        self.start_node_at(checkpoint, Nonterminal::FunctionDefinition);
+        let name = self.identifier(Nonterminal::FunctionDefinition, "Expected function name")?;
+        self.check_and_eat_token(
+            &C1Token::LeftParenthesis,
+            Nonterminal::FunctionDefinition,
+            "Expected '('",
+        )?;
+        let parameters =
+            if self.dialect == Dialect::C1 && !self.current_matches(&C1Token::RightParenthesis) {
+                self.parameter_list()?
+            } else {
+                Vec::new()
+            };
+        self.check_and_eat_token(
+            &C1Token::RightParenthesis,
+            Nonterminal::FunctionDefinition,
+            "Expected ')'",
+        )?;
+        let opening = self.current_location();
+        self.check_and_eat_token(
+            &C1Token::LeftBrace,
+            Nonterminal::FunctionDefinition,
+            "Expected '{'",
+        )?;
+        let body = self.statement_list()?;
+        if let Err(error) = self.check_and_eat_closing(
+            &C1Token::RightBrace,
+            opening,
+            Nonterminal::FunctionDefinition,
+            "Expected '}'",
+        ) {
+            self.recover(error)?;
+        }
+        self.finish_node();
+        Ok(FunctionDefinition {
+            return_type,
+            name,
+            parameters,
+            body,
+            location,
+        })
+    }
+
+    /// parameterlist ::= type <ID> ( "," type <ID> )*
+    fn parameter_list(&mut self) -> Parsed<Vec<Parameter>> {
+        let mut parameters = Vec::new();
+        loop {
+            let ty = self.return_type()?;
+            let name =
+                self.identifier(Nonterminal::FunctionDefinition, "Expected parameter name")?;
+            parameters.push(Parameter { ty, name });
+            if !self.current_matches(&C1Token::Comma) {
+                return Ok(parameters);
+            }
+            self.eat();
+        }
     }
 
     fn next_can_be_function_call(&mut self) -> bool {
         self.current_matches(&C1Token::Identifier) && self.next_matches(&C1Token::LeftParenthesis)
     }
 
-    /// functioncall ::= <ID> "(" ")"
-    fn function_call(&mut self) -> ParseResult {
-        self.check_and_eat_token(&C1Token::Identifier, "Expected function name")?;
-        self.check_and_eat_token(&C1Token::LeftParenthesis, "Expected '('")?;
-        self.check_and_eat_token(&C1Token::RightParenthesis, "Expected ')'")?;
-        Ok(())
+    /// functioncall ::= <ID> "(" ( assignment ( "," assignment )* )? ")"
+    ///
+    /// Arguments are only allowed in C1.
+    fn function_call(&mut self) -> Parsed<FunctionCall> {
+        self.start_node(Nonterminal::FunctionCall);
+        let name = self.identifier(Nonterminal::FunctionCall, "Expected function name")?;
+        let opening = self.current_location();
+        self.check_and_eat_token(
+            &C1Token::LeftParenthesis,
+            Nonterminal::FunctionCall,
+            "Expected '('",
+        )?;
+        let mut arguments = Vec::new();
+        if self.dialect == Dialect::C1 && !self.current_matches(&C1Token::RightParenthesis) {
+            arguments.push(self.assignment()?);
+            while self.current_matches(&C1Token::Comma) {
+                self.eat();
+                arguments.push(self.assignment()?);
+            }
+        }
+        self.check_and_eat_closing(
+            &C1Token::RightParenthesis,
+            opening,
+            Nonterminal::FunctionCall,
+            "Expected ')'",
+        )?;
+        self.finish_node();
+        Ok(FunctionCall { name, arguments })
     }
 
     /// statementlist ::= ( block )*
-    fn statement_list(&mut self) -> ParseResult {
-        while self.next_can_be_block() {
-            self.block()?;
+    fn statement_list(&mut self) -> Parsed<Vec<Statement>> {
+        self.start_node(Nonterminal::StatementList);
+        let mut statements = Vec::new();
+        loop {
+            if self.next_can_be_block() {
+                match self.block() {
+                    Ok(statement) => statements.push(statement),
+                    Err(error) => {
+                        self.recover(error)?;
+                        self.synchronize_statement();
+                    }
+                }
+            } else if self.recovering && !self.at_statement_list_end() {
+                // Skip tokens that cannot start a statement instead of leaving them to the caller
+                let mut expected = self.statement_start().to_vec();
+                expected.extend([C1Token::LeftBrace, C1Token::RightBrace]);
+                let error =
+                    self.error_current(Nonterminal::StatementList, &expected, "Expected statement");
+                self.recover(error)?;
+                self.synchronize_statement();
+            } else {
+                self.finish_node();
+                return Ok(statements);
+            }
+        }
+    }
+
+    /// Check whether the current token ends a statement list, i.e. is a "}", a type keyword starting
+    /// the next function definition, or EOF. In C1 a type keyword starts a declaration instead.
+    fn at_statement_list_end(&self) -> bool {
+        match self.current_token() {
+            None => true,
+            Some(token) => {
+                token == C1Token::RightBrace
+                    || (self.dialect == Dialect::CMinus1 && TYPE_KEYWORDS.contains(&token))
+            }
         }
-        Ok(())
     }
 
     fn next_can_be_block(&mut self) -> bool {
@@ -82,218 +410,744 @@ impl<'a> C1Parser<'a> {
     }
 
     /// block ::= "{" statementlist "}" | statement
-    fn block(&mut self) -> ParseResult {
+    fn block(&mut self) -> Parsed<Statement> {
+        self.start_node(Nonterminal::Block);
         if self.current_matches(&C1Token::LeftBrace) {
+            let location = self.current_location();
             self.eat();
-            self.statement_list()?;
-            self.check_and_eat_token(&C1Token::RightBrace, "Expected '}'")?;
-            return Ok(());
+            let statements = self.statement_list()?;
+            if let Err(error) = self.check_and_eat_closing(
+                &C1Token::RightBrace,
+                location,
+                Nonterminal::Block,
+                "Expected '}'",
+            ) {
+                self.recover(error)?;
+            }
+            self.finish_node();
+            return Ok(Statement {
+                kind: StatementKind::Block(statements),
+                location,
+            });
         }
 
         // if self.next_can_be_statement()  // this is checked in statement_list
-        self.statement()?;
-        Ok(())
+        let statement = self.statement()?;
+        self.finish_node();
+        Ok(statement)
     }
 
     fn next_can_be_statement(&mut self) -> bool {
         self.current_matches(&C1Token::KwIf)
+            || self.current_matches_c1(&C1Token::KwWhile)
+            || self.current_matches_c1(&C1Token::KwDo)
+            || self.current_matches_c1(&C1Token::KwFor)
             || self.current_matches(&C1Token::KwReturn)
             || self.current_matches(&C1Token::KwPrintf)
             || (self.current_matches(&C1Token::Identifier) && self.next_matches(&C1Token::Assign))
             || (self.current_matches(&C1Token::Identifier)
                 && self.next_matches(&C1Token::LeftParenthesis))
+            || self.next_can_be_declaration()
+    }
+
+    fn next_can_be_declaration(&self) -> bool {
+        self.dialect == Dialect::C1
+            && self
+                .current_token()
+                .is_some_and(|token| TYPE_KEYWORDS.contains(&token))
     }
 
-    /// statement ::= ifstatement | returnstatement ";" | printf ";" | statassignment ";" | functioncall ";"
-    fn statement(&mut self) -> ParseResult {
-        if self.current_matches(&C1Token::KwIf) {
-            self.if_statement()?;
+    /// statement ::= ifstatement | whilestatement | dowhilestatement | forstatement | declaration
+    ///             | returnstatement ";" | printf ";" | statassignment ";" | functioncall ";"
+    ///
+    /// Loops and declarations are only allowed in C1.
+    fn statement(&mut self) -> Parsed<Statement> {
+        self.start_node(Nonterminal::Statement);
+        let location = self.current_location();
+        let kind = if self.current_matches(&C1Token::KwIf) {
+            self.if_statement()?
+        } else if self.current_matches_c1(&C1Token::KwWhile) {
+            self.while_statement()?
+        } else if self.current_matches_c1(&C1Token::KwDo) {
+            self.do_while_statement()?
+        } else if self.current_matches_c1(&C1Token::KwFor) {
+            self.for_statement()?
+        } else if self.next_can_be_declaration() {
+            self.declaration()?
         } else if self.current_matches(&C1Token::KwReturn) {
-            self.return_statement()?;
-            self.check_and_eat_token(&C1Token::Semicolon, "Expected ';' after return statement")?;
+            let kind = self.return_statement()?;
+            self.check_and_eat_token(
+                &C1Token::Semicolon,
+                Nonterminal::Statement,
+                "Expected ';' after return statement",
+            )?;
+            kind
         } else if self.current_matches(&C1Token::KwPrintf) {
-            self.printf()?;
-            self.check_and_eat_token(&C1Token::Semicolon, "Expected ';' after printf statement")?;
-        } else if self.current_matches(&C1Token::Identifier)
-            && self.next_matches(&C1Token::Assign)
+            let kind = self.printf()?;
+            self.check_and_eat_token(
+                &C1Token::Semicolon,
+                Nonterminal::Statement,
+                "Expected ';' after printf statement",
+            )?;
+            kind
+        } else if self.current_matches(&C1Token::Identifier) && self.next_matches(&C1Token::Assign)
         {
-            self.stat_assignment()?;
-            self.check_and_eat_token(&C1Token::Semicolon, "Expected ';' after assignment")?;
+            let kind = self.stat_assignment()?;
+            self.check_and_eat_token(
+                &C1Token::Semicolon,
+                Nonterminal::Statement,
+                "Expected ';' after assignment",
+            )?;
+            kind
         } else if self.current_matches(&C1Token::Identifier)
             && self.next_matches(&C1Token::LeftParenthesis)
         {
-            self.function_call()?;
-            self.check_and_eat_token(&C1Token::Semicolon, "Expected ';' after function call")?;
+            let call = self.function_call()?;
+            self.check_and_eat_token(
+                &C1Token::Semicolon,
+                Nonterminal::Statement,
+                "Expected ';' after function call",
+            )?;
+            StatementKind::Call(call)
         } else {
-            return Err(self.error_message_current("Expected statement"));
+            return Err(self.error_current(
+                Nonterminal::Statement,
+                self.statement_start(),
+                "Expected statement",
+            ));
+        };
+        self.finish_node();
+        Ok(Statement { kind, location })
+    }
+
+    /// Return the tokens that can start a statement in the dialect
+    fn statement_start(&self) -> &'static [C1Token] {
+        match self.dialect {
+            Dialect::CMinus1 => STATEMENT_START,
+            Dialect::C1 => C1_STATEMENT_START,
         }
-        Ok(())
     }
 
-    /// ifstatement ::= <KW_IF> "(" assignment ")" block
-    fn if_statement(&mut self) -> ParseResult {
-        self.check_and_eat_token(&C1Token::KwIf, "Expected 'if' keyword")?;
-        self.check_and_eat_token(&C1Token::LeftParenthesis, "Expected '('")?;
-        self.assignment()?;
-        self.check_and_eat_token(&C1Token::RightParenthesis, "Expected ')'")?;
-        self.block()?;
-        Ok(())
+    /// ifstatement ::= <KW_IF> "(" assignment ")" block ( <KW_ELSE> block )?
+    ///
+    /// The else branch is only allowed in C1.
+    fn if_statement(&mut self) -> Parsed<StatementKind> {
+        self.start_node(Nonterminal::IfStatement);
+        self.check_and_eat_token(
+            &C1Token::KwIf,
+            Nonterminal::IfStatement,
+            "Expected 'if' keyword",
+        )?;
+        let opening = self.current_location();
+        self.check_and_eat_token(
+            &C1Token::LeftParenthesis,
+            Nonterminal::IfStatement,
+            "Expected '('",
+        )?;
+        let condition = self.assignment()?;
+        self.check_and_eat_closing(
+            &C1Token::RightParenthesis,
+            opening,
+            Nonterminal::IfStatement,
+            "Expected ')'",
+        )?;
+        let then_branch = Box::new(self.block()?);
+        // Taking the else here binds it to the innermost if, which resolves the dangling else
+        let else_branch = if self.current_matches_c1(&C1Token::KwElse) {
+            self.eat();
+            Some(Box::new(self.block()?))
+        } else {
+            None
+        };
+        self.finish_node();
+        Ok(StatementKind::If {
+            condition,
+            then_branch,
+            else_branch,
+        })
     }
 
-    /// returnstatement ::= <KW_RETURN> ( assignment )?
-    fn return_statement(&mut self) -> ParseResult {
-        self.check_and_eat_token(&C1Token::KwReturn, "Expected 'return' keyword")?;
+    /// whilestatement ::= <KW_WHILE> "(" assignment ")" block
+    fn while_statement(&mut self) -> Parsed<StatementKind> {
+        self.start_node(Nonterminal::WhileStatement);
+        self.check_and_eat_token(
+            &C1Token::KwWhile,
+            Nonterminal::WhileStatement,
+            "Expected 'while' keyword",
+        )?;
+        let opening = self.current_location();
+        self.check_and_eat_token(
+            &C1Token::LeftParenthesis,
+            Nonterminal::WhileStatement,
+            "Expected '('",
+        )?;
+        let condition = self.assignment()?;
+        self.check_and_eat_closing(
+            &C1Token::RightParenthesis,
+            opening,
+            Nonterminal::WhileStatement,
+            "Expected ')'",
+        )?;
+        let body = Box::new(self.block()?);
+        self.finish_node();
+        Ok(StatementKind::While { condition, body })
+    }
 
-        match self.current_token() {
-            Some(_) => {
-                if !self.current_matches(&C1Token::Semicolon) {
-                    self.assignment()?;
-                }
-            }
-            None => {
-                // This case can only happen without failing in test enviroment
-                // In other enviroments there would be an error expecting ";"
-            }
-        }
+    /// dowhilestatement ::= <KW_DO> block <KW_WHILE> "(" assignment ")" ";"
+    fn do_while_statement(&mut self) -> Parsed<StatementKind> {
+        self.start_node(Nonterminal::DoWhileStatement);
+        self.check_and_eat_token(
+            &C1Token::KwDo,
+            Nonterminal::DoWhileStatement,
+            "Expected 'do' keyword",
+        )?;
+        let body = Box::new(self.block()?);
+        self.check_and_eat_token(
+            &C1Token::KwWhile,
+            Nonterminal::DoWhileStatement,
+            "Expected 'while' after the body of the do-while loop",
+        )?;
+        let opening = self.current_location();
+        self.check_and_eat_token(
+            &C1Token::LeftParenthesis,
+            Nonterminal::DoWhileStatement,
+            "Expected '('",
+        )?;
+        let condition = self.assignment()?;
+        self.check_and_eat_closing(
+            &C1Token::RightParenthesis,
+            opening,
+            Nonterminal::DoWhileStatement,
+            "Expected ')'",
+        )?;
+        self.check_and_eat_token(
+            &C1Token::Semicolon,
+            Nonterminal::DoWhileStatement,
+            "Expected ';' after do-while statement",
+        )?;
+        self.finish_node();
+        Ok(StatementKind::DoWhile { body, condition })
+    }
 
-        Ok(())
+    /// forstatement ::= <KW_FOR> "(" ( statassignment )? ";" ( assignment )? ";"
+    ///                  ( statassignment )? ")" block
+    fn for_statement(&mut self) -> Parsed<StatementKind> {
+        self.start_node(Nonterminal::ForStatement);
+        self.check_and_eat_token(
+            &C1Token::KwFor,
+            Nonterminal::ForStatement,
+            "Expected 'for' keyword",
+        )?;
+        let opening = self.current_location();
+        self.check_and_eat_token(
+            &C1Token::LeftParenthesis,
+            Nonterminal::ForStatement,
+            "Expected '('",
+        )?;
+        let init = if self.current_matches(&C1Token::Semicolon) {
+            None
+        } else {
+            Some(self.for_clause()?)
+        };
+        self.check_and_eat_token(
+            &C1Token::Semicolon,
+            Nonterminal::ForStatement,
+            "Expected ';' after the initialization of the for loop",
+        )?;
+        let condition = if self.current_matches(&C1Token::Semicolon) {
+            None
+        } else {
+            Some(self.assignment()?)
+        };
+        self.check_and_eat_token(
+            &C1Token::Semicolon,
+            Nonterminal::ForStatement,
+            "Expected ';' after the condition of the for loop",
+        )?;
+        let update = if self.current_matches(&C1Token::RightParenthesis) {
+            None
+        } else {
+            Some(self.for_clause()?)
+        };
+        self.check_and_eat_closing(
+            &C1Token::RightParenthesis,
+            opening,
+            Nonterminal::ForStatement,
+            "Expected ')'",
+        )?;
+        let body = Box::new(self.block()?);
+        self.finish_node();
+        Ok(StatementKind::For {
+            init,
+            condition,
+            update,
+            body,
+        })
     }
 
-    /// return_type ::= <KW_BOOLEAN> | <KW_FLOAT> | <KW_INT> | <KW_VOID>
-    fn return_type(&mut self) -> ParseResult {
-        let valid_types = [
-            C1Token::KwBoolean,
-            C1Token::KwFloat,
-            C1Token::KwInt,
-            C1Token::KwVoid,
-        ];
-        if self.any_match_current(&valid_types) {
+    /// The statassignment of the initialization or update of a for loop
+    fn for_clause(&mut self) -> Parsed<Box<Statement>> {
+        let location = self.current_location();
+        let kind = self.stat_assignment()?;
+        Ok(Box::new(Statement { kind, location }))
+    }
+
+    /// declaration ::= type <ID> ( "=" assignment )? ";"
+    fn declaration(&mut self) -> Parsed<StatementKind> {
+        self.start_node(Nonterminal::Declaration);
+        let ty = self.return_type()?;
+        let name = self.identifier(Nonterminal::Declaration, "Expected variable name")?;
+        let value = if self.current_matches(&C1Token::Assign) {
             self.eat();
-            return Ok(());
+            Some(self.assignment()?)
         } else {
-            return Err(self.error_message_current("Expected type keyword"));
-        }
+            None
+        };
+        self.check_and_eat_token(
+            &C1Token::Semicolon,
+            Nonterminal::Declaration,
+            "Expected ';' after declaration",
+        )?;
+        self.finish_node();
+        Ok(StatementKind::Declaration { ty, name, value })
     }
 
-    /// printf ::= <KW_PRINTF> "(" assignment ")"
-    fn printf(&mut self) -> ParseResult {
-        self.check_and_eat_token(&C1Token::KwPrintf, "Expected 'printf' keyword")?;
-        self.check_and_eat_token(&C1Token::LeftParenthesis, "Expected '('")?;
-        self.assignment()?;
-        self.check_and_eat_token(&C1Token::RightParenthesis, "Expected ')'")?;
-        Ok(())
+    /// returnstatement ::= <KW_RETURN> ( assignment )?
+    fn return_statement(&mut self) -> Parsed<StatementKind> {
+        self.start_node(Nonterminal::ReturnStatement);
+        self.check_and_eat_token(
+            &C1Token::KwReturn,
+            Nonterminal::ReturnStatement,
+            "Expected 'return' keyword",
+        )?;
+
+        // At EOF there is nothing to return. This case can only happen without failing in test
+        // enviroment, in other enviroments there would be an error expecting ";"
+        let value = match self.current_token() {
+            Some(_) if !self.current_matches(&C1Token::Semicolon) => Some(self.assignment()?),
+            _ => None,
+        };
+
+        self.finish_node();
+        Ok(StatementKind::Return(value))
+    }
+
+    /// return_type ::= <KW_BOOLEAN> | <KW_FLOAT> | <KW_INT> | <KW_VOID>
+    fn return_type(&mut self) -> Parsed<Type> {
+        self.start_node(Nonterminal::Type);
+        let return_type = match self.current_token() {
+            Some(C1Token::KwBoolean) => Type::Bool,
+            Some(C1Token::KwFloat) => Type::Float,
+            Some(C1Token::KwInt) => Type::Int,
+            Some(C1Token::KwVoid) => Type::Void,
+            _ => {
+                return Err(self.error_current(
+                    Nonterminal::Type,
+                    TYPE_KEYWORDS,
+                    "Expected type keyword",
+                ))
+            }
+        };
+        self.eat();
+        self.finish_node();
+        Ok(return_type)
+    }
+
+    /// printf ::= <KW_PRINTF> "(" ( <CONST_STRING> | assignment ) ")"
+    ///
+    /// Strings are only allowed in C1.
+    fn printf(&mut self) -> Parsed<StatementKind> {
+        self.start_node(Nonterminal::Printf);
+        self.check_and_eat_token(
+            &C1Token::KwPrintf,
+            Nonterminal::Printf,
+            "Expected 'printf' keyword",
+        )?;
+        let opening = self.current_location();
+        self.check_and_eat_token(
+            &C1Token::LeftParenthesis,
+            Nonterminal::Printf,
+            "Expected '('",
+        )?;
+        let argument = if self.dialect == Dialect::C1 && self.current_matches(&C1Token::ConstString)
+        {
+            let value = self.current_text().and_then(unescape).ok_or_else(|| {
+                self.invalid_literal(
+                    Nonterminal::Printf,
+                    "Invalid escape sequence in string constant",
+                )
+            })?;
+            self.eat();
+            PrintfArgument::String(value)
+        } else {
+            PrintfArgument::Value(self.assignment()?)
+        };
+        self.check_and_eat_closing(
+            &C1Token::RightParenthesis,
+            opening,
+            Nonterminal::Printf,
+            "Expected ')'",
+        )?;
+        self.finish_node();
+        Ok(StatementKind::Printf(argument))
     }
 
     /// statassignment ::= <ID> "=" assignment
-    fn stat_assignment(&mut self) -> ParseResult {
-        self.check_and_eat_token(&C1Token::Identifier, "Expected identifier")?;
-        self.check_and_eat_token(&C1Token::Assign, "Expected '='")?;
-        self.assignment()?;
-        Ok(())
+    fn stat_assignment(&mut self) -> Parsed<StatementKind> {
+        self.start_node(Nonterminal::StatAssignment);
+        let target = self.identifier(Nonterminal::StatAssignment, "Expected identifier")?;
+        self.check_and_eat_token(
+            &C1Token::Assign,
+            Nonterminal::StatAssignment,
+            "Expected '='",
+        )?;
+        let value = self.assignment()?;
+        self.finish_node();
+        Ok(StatementKind::Assignment { target, value })
     }
 
     /// assignment ::= ( ( <ID> "=" assignment ) | expr )
-    fn assignment(&mut self) -> ParseResult {
-        if self.current_matches(&C1Token::Identifier) && self.next_matches(&C1Token::Assign) {
-            self.check_and_eat_token(&C1Token::Identifier, "Expected identifier")?;
-            self.check_and_eat_token(&C1Token::Assign, "Expected '='")?;
-            self.assignment()?;
+    fn assignment(&mut self) -> Parsed<Expr> {
+        self.start_node(Nonterminal::Assignment);
+        let expr = if self.current_matches(&C1Token::Identifier)
+            && self.next_matches(&C1Token::Assign)
+        {
+            let location = self.current_location();
+            let target = self.identifier(Nonterminal::Assignment, "Expected identifier")?;
+            self.check_and_eat_token(&C1Token::Assign, Nonterminal::Assignment, "Expected '='")?;
+            let value = Box::new(self.assignment()?);
+            Expr {
+                kind: ExprKind::Assignment { target, value },
+                location,
+            }
         } else {
-            self.expr()?;
-        }
-        Ok(())
+            self.expr()?
+        };
+        self.finish_node();
+        Ok(expr)
     }
 
     /// expr ::= simpexpr ( ( "==" | "!=" | "<=" | ">=" | "<" | ">" ) simpexpr )?
-    fn expr(&mut self) -> ParseResult {
-        self.simpexpr()?;
-        if self.any_match_current(&[
-            C1Token::Equal,
-            C1Token::NotEqual,
-            C1Token::LessEqual,
-            C1Token::GreaterEqual,
-            C1Token::Less,
-            C1Token::Greater,
-        ]) {
-            self.eat();
-            self.simpexpr()?;
-        }
-        Ok(())
+    fn expr(&mut self) -> Parsed<Expr> {
+        self.start_node(Nonterminal::Expr);
+        let lhs = self.simpexpr()?;
+        let op = match self.current_token() {
+            Some(C1Token::Equal) => BinaryOperator::Equal,
+            Some(C1Token::NotEqual) => BinaryOperator::NotEqual,
+            Some(C1Token::LessEqual) => BinaryOperator::LessEqual,
+            Some(C1Token::GreaterEqual) => BinaryOperator::GreaterEqual,
+            Some(C1Token::Less) => BinaryOperator::Less,
+            Some(C1Token::Greater) => BinaryOperator::Greater,
+            _ => {
+                self.finish_node();
+                return Ok(lhs);
+            }
+        };
+        let location = self.current_location();
+        self.eat();
+        let rhs = self.simpexpr()?;
+        self.finish_node();
+        Ok(Self::binary(op, lhs, rhs, location))
     }
 
-    /// simpexpr ::= ( "-" )? term ( ( "+" | "-" | "||" ) term )*
-    fn simpexpr(&mut self) -> ParseResult {
-        if self.current_matches(&C1Token::Minus) {
-            self.eat();
-        }
-        self.term()?;
-        while self.any_match_current(&[C1Token::Plus, C1Token::Minus, C1Token::Or]) {
+    /// simpexpr ::= term ( ( "+" | "-" | "||" ) term )*
+    fn simpexpr(&mut self) -> Parsed<Expr> {
+        self.start_node(Nonterminal::SimpExpr);
+        let mut lhs = self.term()?;
+        loop {
+            let op = match self.current_token() {
+                Some(C1Token::Plus) => BinaryOperator::Add,
+                Some(C1Token::Minus) => BinaryOperator::Subtract,
+                Some(C1Token::Or) => BinaryOperator::Or,
+                _ => break,
+            };
+            let location = self.current_location();
             self.eat();
-            self.term()?;
+            let rhs = self.term()?;
+            lhs = Self::binary(op, lhs, rhs, location);
         }
-        Ok(())
+        self.finish_node();
+        Ok(lhs)
     }
 
-    /// term ::= factor ( ( "*" | "/" | "&&" ) factor )*
-    fn term(&mut self) -> ParseResult {
-        self.factor()?;
-        while self.any_match_current(&[C1Token::Asterisk, C1Token::Slash, C1Token::And]) {
+    /// term ::= unary ( ( "*" | "/" | "%" | "&&" ) unary )*
+    fn term(&mut self) -> Parsed<Expr> {
+        self.start_node(Nonterminal::Term);
+        let mut lhs = self.unary()?;
+        loop {
+            let op = match self.current_token() {
+                Some(C1Token::Asterisk) => BinaryOperator::Multiply,
+                Some(C1Token::Slash) => BinaryOperator::Divide,
+                Some(C1Token::Percent) => BinaryOperator::Modulo,
+                Some(C1Token::And) => BinaryOperator::And,
+                _ => break,
+            };
+            let location = self.current_location();
             self.eat();
-            self.factor()?;
+            let rhs = self.unary()?;
+            lhs = Self::binary(op, lhs, rhs, location);
         }
-        Ok(())
+        self.finish_node();
+        Ok(lhs)
+    }
+
+    /// unary ::= ( "!" | "-" | "+" ) unary | factor
+    fn unary(&mut self) -> Parsed<Expr> {
+        self.start_node(Nonterminal::Unary);
+        let op = match self.current_token() {
+            Some(C1Token::Not) => UnaryOperator::Not,
+            Some(C1Token::Minus) => UnaryOperator::Minus,
+            Some(C1Token::Plus) => UnaryOperator::Plus,
+            _ => {
+                let factor = self.factor()?;
+                self.finish_node();
+                return Ok(factor);
+            }
+        };
+        let location = self.current_location();
+        self.eat();
+        let operand = Box::new(self.unary()?);
+        self.finish_node();
+        Ok(Expr {
+            kind: ExprKind::Unary { op, operand },
+            location,
+        })
     }
 
     /// factor ::= <CONST_INT> | <CONST_FLOAT> | <CONST_BOOLEAN> | functioncall | <ID> | "(" assignment ")"
-    fn factor(&mut self) -> ParseResult {
-        if self.current_matches(&C1Token::ConstInt)
-            || self.current_matches(&C1Token::ConstFloat)
-            || self.current_matches(&C1Token::ConstBoolean)
+    fn factor(&mut self) -> Parsed<Expr> {
+        self.start_node(Nonterminal::Factor);
+        let location = self.current_location();
+        let kind = if self
+            .current_token()
+            .is_some_and(|token| CONSTANTS.contains(&token))
         {
-            self.eat();
+            ExprKind::Literal(self.constant(Nonterminal::Factor)?)
         } else if self.next_can_be_function_call() {
-            self.function_call()?;
+            ExprKind::Call(self.function_call()?)
         } else if self.current_matches(&C1Token::Identifier) {
-            self.eat();
+            ExprKind::Variable(self.identifier(Nonterminal::Factor, "Expected identifier")?)
         } else if self.current_matches(&C1Token::LeftParenthesis) {
             self.eat();
-            self.assignment()?;
-            self.check_and_eat_token(&C1Token::RightParenthesis, "Expected ')'")?;
+            let inner = self.assignment()?;
+            self.check_and_eat_closing(
+                &C1Token::RightParenthesis,
+                location,
+                Nonterminal::Factor,
+                "Expected ')'",
+            )?;
+            self.finish_node();
+            return Ok(inner);
         } else {
-            return Err(self.error_message_current("Expected factor"));
+            return Err(self.error_current(Nonterminal::Factor, FACTOR_START, "Expected factor"));
+        };
+        self.finish_node();
+        Ok(Expr { kind, location })
+    }
+
+    /// constant ::= <CONST_INT> | <CONST_FLOAT> | <CONST_BOOLEAN>
+    ///
+    /// Errors are reported for the given nonterminal.
+    fn constant(&mut self, nonterminal: Nonterminal) -> Parsed<Literal> {
+        let literal = match self.current_token() {
+            Some(C1Token::ConstInt) => self
+                .current_text()
+                .and_then(|text| text.parse().ok())
+                .map(Literal::Int)
+                .ok_or_else(|| {
+                    self.invalid_literal(nonterminal, "Integer constant out of range")
+                })?,
+            Some(C1Token::ConstFloat) => self
+                .current_text()
+                .and_then(|text| text.parse().ok())
+                .map(Literal::Float)
+                .ok_or_else(|| self.invalid_literal(nonterminal, "Invalid float constant"))?,
+            Some(C1Token::ConstBoolean) => Literal::Bool(self.current_text() == Some("true")),
+            _ => return Err(self.error_current(nonterminal, CONSTANTS, "Expected constant")),
+        };
+        self.eat();
+        Ok(literal)
+    }
+
+    fn binary(op: BinaryOperator, lhs: Expr, rhs: Expr, location: Location) -> Expr {
+        Expr {
+            kind: ExprKind::Binary {
+                op,
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+            },
+            location,
+        }
+    }
+
+    /// Check whether the current token is an identifier. If yes, consume it and return it as an AST
+    /// node, otherwise return an error with the given error message
+    fn identifier(
+        &mut self,
+        nonterminal: Nonterminal,
+        error_message: &'static str,
+    ) -> Parsed<Identifier> {
+        let location = self.current_location();
+        match self.current_token() {
+            Some(C1Token::Identifier) => {
+                let name = self.current_text().unwrap_or_default().to_string();
+                self.eat();
+                Ok(Identifier { name, location })
+            }
+            _ => Err(self.error_current(nonterminal, &[C1Token::Identifier], error_message)),
         }
-        Ok(())
     }
 
     /// Check whether the current token is equal to the given token. If yes, consume it, otherwise
     /// return an error with the given error message
-    fn check_and_eat_token(&mut self, token: &C1Token, error_message: &'static str) -> ParseResult {
+    fn check_and_eat_token(
+        &mut self,
+        token: &C1Token,
+        nonterminal: Nonterminal,
+        error_message: &'static str,
+    ) -> Parsed<()> {
         if self.current_matches(token) {
             self.eat();
             Ok(())
         } else {
-            Err(self.error_message_current(error_message))
+            Err(self.error_current(nonterminal, &[*token], error_message))
+        }
+    }
+
+    /// Consume the current token. When building a concrete syntax tree, add the token and the trivia
+    /// in front of it to the current node.
+    fn eat(&mut self) {
+        if let Some(builder) = &mut self.cst {
+            if !self.trivia_recorded {
+                for trivia in self.lexer.current_trivia() {
+                    builder.token(trivia.kind, trivia.text);
+                }
+            }
+            if let (Some(token), Some(text)) =
+                (self.lexer.current_token(), self.lexer.current_text())
+            {
+                builder.token(token, text);
+            }
+        }
+        self.trivia_recorded = false;
+        self.lexer.eat();
+    }
+
+    /// Open a node of the concrete syntax tree, if one is built
+    fn start_node(&mut self, kind: Nonterminal) {
+        self.record_leading_trivia();
+        if let Some(builder) = &mut self.cst {
+            builder.start_node(kind);
+        }
+    }
+
+    /// Add the trivia in front of the current token to the innermost open node, as it belongs to
+    /// the enclosing node of the node that starts at the current token
+    fn record_leading_trivia(&mut self) {
+        if let Some(builder) = &mut self.cst {
+            if builder.depth() > 0 && !self.trivia_recorded {
+                for trivia in self.lexer.current_trivia() {
+                    builder.token(trivia.kind, trivia.text);
+                }
+                self.trivia_recorded = true;
+            }
+        }
+    }
+
+    /// Return a checkpoint in front of the current token, to start a node there once its kind is
+    /// known
+    fn checkpoint(&mut self) -> usize {
+        self.record_leading_trivia();
+        self.cst.as_ref().map_or(0, GreenNodeBuilder::checkpoint)
+    }
+
+    /// Start a node of the concrete syntax tree at the given checkpoint, if one is built
+    fn start_node_at(&mut self, checkpoint: usize, kind: Nonterminal) {
+        if let Some(builder) = &mut self.cst {
+            builder.start_node_at(checkpoint, kind);
+        }
+    }
+
+    /// Close the most recently opened node of the concrete syntax tree, if one is built
+    fn finish_node(&mut self) {
+        if let Some(builder) = &mut self.cst {
+            builder.finish_node();
+        }
+    }
+
+    /// Close the program node of the concrete syntax tree, including the trivia at the end of the
+    /// text
+    fn finish_program(&mut self) {
+        if let Some(builder) = &mut self.cst {
+            for trivia in self.lexer.trailing_trivia() {
+                builder.token(trivia.kind, trivia.text);
+            }
         }
+        self.finish_node();
     }
 
-    /// For each token in the given slice, check whether the token is equal to the current token,
-    /// consume the current token, and check the next token in the slice against the next token
-    /// provided by the lexer.
-    /* fn check_and_eat_tokens(
+    /// Like `check_and_eat_token`, for a closing delimiter that belongs to the opening delimiter at
+    /// the given location
+    fn check_and_eat_closing(
         &mut self,
-        token: &[C1Token],
+        token: &C1Token,
+        opening: Location,
+        nonterminal: Nonterminal,
         error_message: &'static str,
-    ) -> ParseResult {
-        match token
-            .iter()
-            .map(|t| self.check_and_eat_token(t, error_message))
-            .filter(ParseResult::is_err)
-            .last()
-        {
-            None => Ok(()),
-            Some(err) => err,
+    ) -> Parsed<()> {
+        self.check_and_eat_token(token, nonterminal, error_message)
+            .map_err(|error| error.with_unclosed_delimiter(opening))
+    }
+
+    /// In recovery mode, record the error so that the caller can synchronize and continue. Otherwise
+    /// return the error to abort parsing.
+    fn recover(&mut self, error: ParseError) -> Parsed<()> {
+        if self.recovering {
+            self.errors.push(error);
+            Ok(())
+        } else {
+            Err(error)
         }
-    } */
+    }
+
+    /// Skip tokens after an error inside a statement list. Stops after the next ";" or after a
+    /// skipped block, or before the next unmatched "}" or type keyword, so that the enclosing block
+    /// or function definition can be closed.
+    fn synchronize_statement(&mut self) {
+        let mut depth = 0;
+        while let Some(token) = self.current_token() {
+            if TYPE_KEYWORDS.contains(&token) || (token == C1Token::RightBrace && depth == 0) {
+                return;
+            }
+            self.eat();
+            match token {
+                C1Token::LeftBrace => depth += 1,
+                C1Token::RightBrace => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return;
+                    }
+                }
+                C1Token::Semicolon if depth == 0 => return,
+                _ => {}
+            }
+        }
+    }
+
+    /// Skip tokens after an error in a function definition that started at the given span, up to
+    /// the type keyword of the next function definition. At least one token is skipped if the
+    /// definition did not consume any, so that parsing always makes progress.
+    fn synchronize_function(&mut self, start: Option<Range<usize>>) {
+        if self.current_span() == start {
+            self.eat();
+        }
+        while let Some(token) = self.current_token() {
+            if TYPE_KEYWORDS.contains(&token) {
+                return;
+            }
+            self.eat();
+        }
+    }
 
     /// Check whether the given token matches the current token
     fn current_matches(&self, token: &C1Token) -> bool {
@@ -303,6 +1157,12 @@ impl<'a> C1Parser<'a> {
         }
     }
 
+    /// Check whether the given token matches the current token in C1. In C(-1) the token cannot
+    /// start the construct it starts in C1.
+    fn current_matches_c1(&self, token: &C1Token) -> bool {
+        self.dialect == Dialect::C1 && self.current_matches(token)
+    }
+
     /// Check whether the given token matches the next token
     fn next_matches(&self, token: &C1Token) -> bool {
         match &self.peek_token() {
@@ -311,33 +1171,52 @@ impl<'a> C1Parser<'a> {
         }
     }
 
-    /// Check whether any of the tokens matches the current token.
-    fn any_match_current(&self, token: &[C1Token]) -> bool {
-        token.iter().any(|t| self.current_matches(t))
+    /// Return the location of the current token, or a default location at EOF
+    fn current_location(&self) -> Location {
+        let span = self.current_span().unwrap_or_default();
+        Location {
+            line: self.current_line_number().unwrap_or_default(),
+            column: self.current_column().unwrap_or_default(),
+            start: span.start,
+            end: span.end,
+        }
     }
 
-    /// Check whether any of the tokens matches the current token, then consume it
-    /* fn any_match_and_eat(&mut self, token: &[C1Token], error_message: &String) -> ParseResult {
-        if token
-            .iter()
-            .any(|t| self.check_and_eat_token(t, "").is_ok())
-        {
-            Ok(())
-        } else {
-            Err(String::from(error_message))
-        }
-    }*/
+    /// Build an error for the current token, which is none of the expected tokens
+    fn error_current(
+        &self,
+        nonterminal: Nonterminal,
+        expected: &[C1Token],
+        reason: &'static str,
+    ) -> ParseError {
+        let kind = match self.current_token() {
+            None => ParseErrorKind::UnexpectedEof,
+            Some(C1Token::Error) => ParseErrorKind::InvalidToken,
+            Some(_) => ParseErrorKind::UnexpectedToken,
+        };
+        self.error_current_with_kind(kind, nonterminal, expected, reason)
+    }
 
-    fn error_message_current(&self, reason: &'static str) -> String {
-        match self.current_token() {
-            None => format!("{}. Reached EOF", reason),
-            Some(_) => format!(
-                "{} at line {:?}, got '{}' instead.",
-                reason,
-                self.current_line_number().unwrap(),
-                self.current_text().unwrap()
-            ),
-        }
+    /// Build an error for a constant token whose value cannot be represented
+    fn invalid_literal(&self, nonterminal: Nonterminal, reason: &'static str) -> ParseError {
+        self.error_current_with_kind(ParseErrorKind::InvalidLiteral, nonterminal, &[], reason)
+    }
+
+    fn error_current_with_kind(
+        &self,
+        kind: ParseErrorKind,
+        nonterminal: Nonterminal,
+        expected: &[C1Token],
+        reason: &'static str,
+    ) -> ParseError {
+        let found = self.current_token().map(|token| {
+            (
+                token,
+                self.current_text().unwrap_or_default(),
+                self.current_location(),
+            )
+        });
+        ParseError::new(kind, nonterminal, found, expected, reason)
     }
 
     /*fn error_message_peek(&mut self, reason: &'static str) -> String {
@@ -353,21 +1232,62 @@ impl<'a> C1Parser<'a> {
     }*/
 }
 
+/// Decode the text of a <CONST_STRING> token: remove the quotes and replace the escape sequences
+/// `\n`, `\t`, `\r`, `\0`, `\"`, `\'` and `\\`. Returns `None` for any other escape sequence.
+fn unescape(text: &str) -> Option<String> {
+    let text = text.strip_prefix('"')?.strip_suffix('"')?;
+    let mut value = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+        value.push(match chars.next()? {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            '0' => '\0',
+            c @ ('"' | '\'' | '\\') => c,
+            _ => return None,
+        });
+    }
+    Some(value)
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::parser::{C1Parser, ParseResult};
+    use crate::ast::{
+        BinaryOperator, ExprKind, Literal, PrintfArgument, Statement, StatementKind, Type,
+        UnaryOperator,
+    };
+    use crate::parser::{C1Parser, Dialect, Parsed};
+    use crate::{C1Token, Nonterminal, ParseErrorKind};
+
+    fn call_method<'a, F, T>(parse_method: F, text: &'static str) -> Parsed<T>
+    where
+        F: Fn(&mut C1Parser<'a>) -> Parsed<T>,
+    {
+        call_method_in(Dialect::CMinus1, parse_method, text)
+    }
 
-    fn call_method<'a, F>(parse_method: F, text: &'static str) -> ParseResult
+    fn call_method_c1<'a, F, T>(parse_method: F, text: &'static str) -> Parsed<T>
     where
-        F: Fn(&mut C1Parser<'a>) -> ParseResult,
+        F: Fn(&mut C1Parser<'a>) -> Parsed<T>,
     {
-        let mut parser = C1Parser::initialize_parser(text);
-        if let Err(message) = parse_method(&mut parser) {
+        call_method_in(Dialect::C1, parse_method, text)
+    }
+
+    fn call_method_in<'a, F, T>(dialect: Dialect, parse_method: F, text: &'static str) -> Parsed<T>
+    where
+        F: Fn(&mut C1Parser<'a>) -> Parsed<T>,
+    {
+        let mut parser = C1Parser::initialize_parser(text, dialect);
+        let result = parse_method(&mut parser);
+        if let Err(message) = &result {
             eprintln!("Parse Error: {}", message);
-            Err(message)
-        } else {
-            Ok(())
         }
+        result
     }
 
     #[test]
@@ -459,6 +1379,50 @@ mod tests {
         assert!(call_method(C1Parser::function_call, "bar23( )").is_ok());
     }
 
+    #[test]
+    fn parameters_and_arguments() {
+        let program = C1Parser::parse_program_in(
+            "float calc(int a, float b) { return a * b; }\nvoid main() { calc(1, x = 2.5); }",
+            Dialect::C1,
+        )
+        .unwrap();
+        let parameters = &program.functions[0].parameters;
+        assert_eq!(parameters.len(), 2);
+        assert_eq!(parameters[0].ty, Type::Int);
+        assert_eq!(parameters[0].name.name, "a");
+        assert_eq!(parameters[1].ty, Type::Float);
+        assert_eq!(parameters[1].name.name, "b");
+
+        let StatementKind::Call(call) = &program.functions[1].body[0].kind else {
+            panic!("expected function call");
+        };
+        assert_eq!(call.arguments.len(), 2);
+        assert_eq!(call.arguments[0].kind, ExprKind::Literal(Literal::Int(1)));
+        assert!(matches!(
+            call.arguments[1].kind,
+            ExprKind::Assignment { .. }
+        ));
+
+        let c1 = |text| call_method_in(Dialect::C1, C1Parser::function_call, text);
+        assert!(c1("foo()").is_ok());
+        assert!(c1("foo(a)").is_ok());
+        assert!(c1("foo(a, b + 1, bar(c, d))").is_ok());
+        assert!(c1("foo(a,)").is_err());
+        assert!(c1("foo(,a)").is_err());
+        assert!(c1("foo(a b)").is_err());
+
+        let c1 = |text| C1Parser::parse_program_in(text, Dialect::C1);
+        assert!(c1("void f(bool b) {}").is_ok());
+        assert!(c1("void f(int a,) {}").is_err());
+        assert!(c1("void f(int) {}").is_err());
+        assert!(c1("void f(a) {}").is_err());
+        assert!(c1("void f(int a float b) {}").is_err());
+
+        // C(-1) has neither parameters nor arguments
+        assert!(call_method(C1Parser::function_call, "foo(a)").is_err());
+        assert!(C1Parser::parse_program("void f(int a) {}").is_err());
+    }
+
     #[test]
     fn fail_invalid_function_call() {
         assert!(call_method(C1Parser::function_call, "foo)").is_err());
@@ -468,13 +1432,14 @@ mod tests {
 
     #[test]
     fn valid_statement_list() {
-        assert!(call_method(C1Parser::statement_list, "int x = 4;").is_ok());
-        assert!(call_method(
-            C1Parser::statement_list,
-            "int x = 4;\n\
-        int y = 2.1;"
-        )
-        .is_ok());
+        let c1 = |text| call_method_in(Dialect::C1, C1Parser::statement_list, text);
+        assert_eq!(c1("int x = 4;").map(|statements| statements.len()), Ok(1));
+        assert_eq!(
+            c1("int x = 4;\n\
+        int y = 2.1;")
+            .map(|statements| statements.len()),
+            Ok(2)
+        );
         assert!(call_method(
             C1Parser::statement_list,
             "x = 4;\n\
@@ -486,6 +1451,88 @@ mod tests {
         assert!(call_method(C1Parser::statement_list, "{x = 4;}\nint y = 1;\nfoo;\n{}").is_ok());
     }
 
+    #[test]
+    fn declarations() {
+        let program = C1Parser::parse_program_in(
+            "void main() { int x; float y = x = 2; if (x) bool b; }",
+            Dialect::C1,
+        )
+        .unwrap();
+        let body = &program.functions[0].body;
+        let StatementKind::Declaration { ty, name, value } = &body[0].kind else {
+            panic!("expected declaration");
+        };
+        assert_eq!((*ty, name.name.as_str(), value), (Type::Int, "x", &None));
+        let StatementKind::Declaration { ty, name, value } = &body[1].kind else {
+            panic!("expected declaration");
+        };
+        assert_eq!((*ty, name.name.as_str()), (Type::Float, "y"));
+        assert!(matches!(
+            value.as_ref().map(|value| &value.kind),
+            Some(ExprKind::Assignment { .. })
+        ));
+        assert!(matches!(
+            &body[2].kind,
+            StatementKind::If { then_branch, .. }
+                if matches!(then_branch.kind, StatementKind::Declaration { ty: Type::Bool, .. })
+        ));
+
+        let c1 = |text| call_method_in(Dialect::C1, C1Parser::statement, text);
+        assert!(c1("void v;").is_ok());
+        assert!(c1("int x").is_err());
+        assert!(c1("int = 1;").is_err());
+        assert!(c1("int x = ;").is_err());
+        assert!(c1("int x, y;").is_err());
+        assert!(c1("x int;").is_err());
+
+        // C(-1) has no declarations, the statement list ends in front of the type keyword
+        assert!(call_method(C1Parser::statement, "int x;").is_err());
+        assert!(C1Parser::parse_program("void main() { int x; }").is_err());
+        assert!(C1Parser::parse_program_in("int f() { int x; } int g() {}", Dialect::C1).is_ok());
+        assert!(C1Parser::parse_program_in("int f() { int g() {}", Dialect::C1).is_err());
+    }
+
+    #[test]
+    fn globals() {
+        let program = C1Parser::parse_program_in(
+            "int count = 0; float scale; void main() {} bool flag = true;",
+            Dialect::C1,
+        )
+        .unwrap();
+        assert_eq!(program.functions.len(), 1);
+        let globals: Vec<_> = program
+            .globals
+            .iter()
+            .map(|global| (global.ty, global.name.name.as_str(), global.value))
+            .collect();
+        assert_eq!(
+            globals,
+            [
+                (Type::Int, "count", Some(Literal::Int(0))),
+                (Type::Float, "scale", None),
+                (Type::Bool, "flag", Some(Literal::Bool(true))),
+            ]
+        );
+        assert_eq!(program.globals[1].location.column, 16);
+
+        let c1 = |text| C1Parser::parse_program_in(text, Dialect::C1);
+        assert!(c1("float f = 1.5; int i = 1;").is_ok());
+        assert!(c1("int x = y;").is_err());
+        assert!(c1("int x = 1 + 2;").is_err());
+        assert!(c1("int x = -1;").is_err());
+        assert!(c1("int x").is_err());
+        assert!(c1("int x = 4294967296;").is_err());
+        assert!(c1("int x; y = 1;").is_err());
+
+        let error = c1("int x = ;").unwrap_err();
+        assert_eq!(error.nonterminal, Nonterminal::GlobalDeclaration);
+        assert_eq!(error.reason(), "Expected constant");
+
+        // C(-1) has no global declarations
+        let error = C1Parser::parse_program("int x;").unwrap_err();
+        assert_eq!(error.nonterminal, Nonterminal::FunctionDefinition);
+    }
+
     #[test]
     fn fail_invalid_statement_list() {
         assert!(call_method(
@@ -511,6 +1558,9 @@ mod tests {
         assert!(call_method(C1Parser::if_statement, "if(z) {}").is_ok());
         assert!(call_method(C1Parser::if_statement, "if(true) {}").is_ok());
         assert!(call_method(C1Parser::if_statement, "if(false) {}").is_ok());
+        assert!(call_method_c1(C1Parser::if_statement, "if(x) {} else {}").is_ok());
+        assert!(call_method_c1(C1Parser::if_statement, "if(x) y = 1; else y = 2;").is_ok());
+        assert!(call_method_c1(C1Parser::if_statement, "if(x) {} else if(y) {} else {}").is_ok());
     }
 
     #[test]
@@ -520,6 +1570,186 @@ mod tests {
         assert!(call_method(C1Parser::if_statement, "if(> z) {}").is_err());
         assert!(call_method(C1Parser::if_statement, "if( {}").is_err());
         assert!(call_method(C1Parser::if_statement, "if(false) }").is_err());
+        assert!(call_method_c1(C1Parser::if_statement, "if(x) {} else").is_err());
+        assert!(call_method_c1(C1Parser::if_statement, "if(x) {} else }").is_err());
+        assert!(call_method_c1(C1Parser::if_statement, "if(x) else {}").is_err());
+        assert!(call_method(C1Parser::statement, "else {}").is_err());
+    }
+
+    #[test]
+    fn dangling_else() {
+        // The else belongs to the inner if
+        let statement =
+            call_method_c1(C1Parser::statement, "if (a) if (b) x = 1; else x = 2;").unwrap();
+        let StatementKind::If {
+            then_branch,
+            else_branch: None,
+            ..
+        } = statement.kind
+        else {
+            panic!("expected if statement without else");
+        };
+        let StatementKind::If {
+            else_branch: Some(else_branch),
+            ..
+        } = then_branch.kind
+        else {
+            panic!("expected if statement with else");
+        };
+        assert!(matches!(else_branch.kind, StatementKind::Assignment { .. }));
+
+        // Braces make the else belong to the outer if
+        let statement =
+            call_method_c1(C1Parser::statement, "if (a) { if (b) x = 1; } else x = 2;").unwrap();
+        let StatementKind::If {
+            else_branch: Some(_),
+            ..
+        } = statement.kind
+        else {
+            panic!("expected if statement with else");
+        };
+
+        // In a chain, each else belongs to the if right in front of it
+        let statement = call_method_c1(
+            C1Parser::statement,
+            "if (a) x = 1; else if (b) if (c) x = 2; else x = 3; else x = 4;",
+        )
+        .unwrap();
+        let StatementKind::If {
+            else_branch: Some(else_branch),
+            ..
+        } = statement.kind
+        else {
+            panic!("expected if statement with else");
+        };
+        let StatementKind::If {
+            then_branch,
+            else_branch: Some(last),
+            ..
+        } = else_branch.kind
+        else {
+            panic!("expected else if");
+        };
+        assert!(matches!(
+            then_branch.kind,
+            StatementKind::If {
+                else_branch: Some(_),
+                ..
+            }
+        ));
+        assert!(matches!(last.kind, StatementKind::Assignment { .. }));
+    }
+
+    #[test]
+    fn valid_while_statement() {
+        assert!(call_method_c1(C1Parser::while_statement, "while(x == 1) {}").is_ok());
+        assert!(call_method_c1(C1Parser::while_statement, "while(x < 10) x = x + 1;").is_ok());
+        assert!(call_method_c1(C1Parser::while_statement, "while(true) { while(b) {} }").is_ok());
+        assert!(call_method_c1(C1Parser::while_statement, "while(x = f()) {}").is_ok());
+    }
+
+    #[test]
+    fn fail_invalid_while_statement() {
+        assert!(call_method_c1(C1Parser::while_statement, "while() {}").is_err());
+        assert!(call_method_c1(C1Parser::while_statement, "while x {}").is_err());
+        assert!(call_method_c1(C1Parser::while_statement, "while(x {}").is_err());
+        assert!(call_method_c1(C1Parser::while_statement, "while(true)").is_err());
+        assert!(call_method_c1(C1Parser::while_statement, "while(true) }").is_err());
+        assert!(call_method_c1(C1Parser::while_statement, "if(true) {}").is_err());
+    }
+
+    #[test]
+    fn valid_do_while_statement() {
+        assert!(call_method_c1(C1Parser::do_while_statement, "do {} while(x == 1);").is_ok());
+        assert!(
+            call_method_c1(C1Parser::do_while_statement, "do x = x + 1; while(x < 10);").is_ok()
+        );
+        assert!(call_method_c1(
+            C1Parser::do_while_statement,
+            "do { do {} while(b); } while(a);"
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn fail_invalid_do_while_statement() {
+        assert!(call_method_c1(C1Parser::do_while_statement, "do {} while();").is_err());
+        assert!(call_method_c1(C1Parser::do_while_statement, "do while(true);").is_err());
+        assert!(call_method_c1(C1Parser::do_while_statement, "do {} (true);").is_err());
+
+        let error =
+            call_method_c1(C1Parser::do_while_statement, "do { x = 1; } x = 2;").unwrap_err();
+        assert_eq!(error.nonterminal, Nonterminal::DoWhileStatement);
+        assert_eq!(error.expected, [C1Token::KwWhile]);
+        assert_eq!(
+            error.to_string(),
+            "Expected 'while' after the body of the do-while loop at line 1, got 'x' instead."
+        );
+
+        let error = call_method_c1(C1Parser::do_while_statement, "do {} while(true)").unwrap_err();
+        assert_eq!(error.kind, ParseErrorKind::UnexpectedEof);
+        assert_eq!(
+            error.to_string(),
+            "Expected ';' after do-while statement. Reached EOF"
+        );
+        let error =
+            call_method_c1(C1Parser::do_while_statement, "do {} while(true) }").unwrap_err();
+        assert_eq!(error.expected, [C1Token::Semicolon]);
+    }
+
+    #[test]
+    fn valid_for_statement() {
+        // Every combination of the optional clauses
+        for init in ["", "i = 0"] {
+            for condition in ["", "i < 10"] {
+                for update in ["", "i = i + 1"] {
+                    let text = format!("for({}; {}; {}) {{}}", init, condition, update);
+                    let statement = C1Parser::initialize_parser(&text, Dialect::C1).statement();
+                    let Ok(Statement {
+                        kind:
+                            StatementKind::For {
+                                init: parsed_init,
+                                condition: parsed_condition,
+                                update: parsed_update,
+                                ..
+                            },
+                        ..
+                    }) = statement
+                    else {
+                        panic!("expected for statement for {:?}", text);
+                    };
+                    assert_eq!(parsed_init.is_some(), !init.is_empty());
+                    assert_eq!(parsed_condition.is_some(), !condition.is_empty());
+                    assert_eq!(parsed_update.is_some(), !update.is_empty());
+                }
+            }
+        }
+        assert!(call_method_c1(C1Parser::for_statement, "for(;;) x = x + 1;").is_ok());
+        assert!(
+            call_method_c1(C1Parser::for_statement, "for(i = j = 0; x = f(); i = 1) {}").is_ok()
+        );
+        assert!(call_method_c1(C1Parser::for_statement, "for(;;) for(;;) {}").is_ok());
+    }
+
+    #[test]
+    fn fail_invalid_for_statement() {
+        assert!(call_method_c1(C1Parser::for_statement, "for() {}").is_err());
+        assert!(call_method_c1(C1Parser::for_statement, "for(;) {}").is_err());
+        assert!(call_method_c1(C1Parser::for_statement, "for(;;;) {}").is_err());
+        assert!(call_method_c1(C1Parser::for_statement, "for(1; true;) {}").is_err());
+        assert!(call_method_c1(C1Parser::for_statement, "for(; true; 1) {}").is_err());
+        assert!(call_method_c1(C1Parser::for_statement, "for(f(); true;) {}").is_err());
+        assert!(call_method_c1(C1Parser::for_statement, "for(;;)").is_err());
+
+        let error = call_method_c1(C1Parser::for_statement, "for(i = 0 i < 3;) {}").unwrap_err();
+        assert_eq!(error.expected, [C1Token::Semicolon]);
+        assert_eq!(
+            error.reason(),
+            "Expected ';' after the initialization of the for loop"
+        );
+        let error = call_method_c1(C1Parser::for_statement, "for(;; i = i + 1 {}").unwrap_err();
+        assert_eq!(error.expected, [C1Token::RightParenthesis]);
+        assert!(error.unclosed_delimiter.is_some());
     }
 
     #[test]
@@ -548,6 +1778,42 @@ mod tests {
         assert!(call_method(C1Parser::printf, "Printf()").is_err());
     }
 
+    #[test]
+    fn printf_strings() {
+        let c1 = |text| call_method_in(Dialect::C1, C1Parser::printf, text);
+        assert_eq!(
+            c1(r#"printf("Hello, world!")"#),
+            Ok(StatementKind::Printf(PrintfArgument::String(
+                "Hello, world!".to_string()
+            )))
+        );
+        assert_eq!(
+            c1(r#"printf("tab\t\"quote\" \\ 'single\' \0 end\r\n")"#),
+            Ok(StatementKind::Printf(PrintfArgument::String(
+                "tab\t\"quote\" \\ 'single' \0 end\r\n".to_string()
+            )))
+        );
+        assert_eq!(
+            c1(r#"printf("")"#),
+            Ok(StatementKind::Printf(PrintfArgument::String(String::new())))
+        );
+        assert!(matches!(
+            c1("printf(1)"),
+            Ok(StatementKind::Printf(PrintfArgument::Value(_)))
+        ));
+        assert!(c1(r#"printf("a" + 1)"#).is_err());
+        assert!(c1(r#"printf("a", 1)"#).is_err());
+        assert!(c1(r#"x = "a""#).is_err());
+
+        let error = c1(r#"printf("\q")"#).unwrap_err();
+        assert_eq!(error.kind, ParseErrorKind::InvalidLiteral);
+        assert_eq!(error.nonterminal, Nonterminal::Printf);
+        assert_eq!(error.column(), Some(8));
+
+        // Strings are not part of C(-1)
+        assert!(call_method(C1Parser::printf, r#"printf("a")"#).is_err());
+    }
+
     #[test]
     fn valid_return_type() {
         assert!(call_method(C1Parser::return_type, "void").is_ok());
@@ -595,4 +1861,336 @@ mod tests {
         )
         .is_ok());
     }
+
+    #[test]
+    fn program_ast() {
+        let program =
+            C1Parser::parse_program("int foo() { x = 1; return x; }\nvoid main() {}").unwrap();
+        assert_eq!(program.functions.len(), 2);
+
+        let foo = &program.functions[0];
+        assert_eq!(foo.return_type, Type::Int);
+        assert_eq!(foo.name.name, "foo");
+        assert_eq!(foo.body.len(), 2);
+        assert!(matches!(
+            &foo.body[0].kind,
+            StatementKind::Assignment { target, .. } if target.name == "x"
+        ));
+        assert!(matches!(&foo.body[1].kind, StatementKind::Return(Some(_))));
+
+        let main = &program.functions[1];
+        assert_eq!(main.return_type, Type::Void);
+        assert_eq!(main.name.location.line, 2);
+        assert_eq!(main.name.location.column, 6);
+        assert_eq!(main.name.location.span(), 36..40);
+        assert!(main.body.is_empty());
+    }
+
+    #[test]
+    fn statement_ast() {
+        let statement = call_method(C1Parser::statement, "if (a) { printf(1); foo(); }").unwrap();
+        let StatementKind::If { then_branch, .. } = statement.kind else {
+            panic!("expected if statement");
+        };
+        let StatementKind::Block(statements) = then_branch.kind else {
+            panic!("expected block");
+        };
+        assert!(matches!(statements[0].kind, StatementKind::Printf(_)));
+        assert!(matches!(statements[1].kind, StatementKind::Call(_)));
+
+        let statement = call_method_c1(C1Parser::statement, "while (i < 3) i = i + 1;").unwrap();
+        let StatementKind::While { condition, body } = statement.kind else {
+            panic!("expected while statement");
+        };
+        assert!(matches!(
+            condition.kind,
+            ExprKind::Binary {
+                op: BinaryOperator::Less,
+                ..
+            }
+        ));
+        assert!(matches!(body.kind, StatementKind::Assignment { .. }));
+
+        let statement = call_method(C1Parser::statement, "return;").unwrap();
+        assert_eq!(statement.kind, StatementKind::Return(None));
+    }
+
+    #[test]
+    fn expression_precedence() {
+        // 1 + 2 * 3 == 7 parses as (1 + (2 * 3)) == 7
+        let expr = call_method(C1Parser::assignment, "1 + 2 * 3 == 7").unwrap();
+        let ExprKind::Binary { op, lhs, rhs } = expr.kind else {
+            panic!("expected binary expression");
+        };
+        assert_eq!(op, BinaryOperator::Equal);
+        assert_eq!(rhs.kind, ExprKind::Literal(Literal::Int(7)));
+        let ExprKind::Binary { op, rhs, .. } = lhs.kind else {
+            panic!("expected binary expression");
+        };
+        assert_eq!(op, BinaryOperator::Add);
+        assert!(matches!(
+            rhs.kind,
+            ExprKind::Binary {
+                op: BinaryOperator::Multiply,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn expression_associativity() {
+        // a - b - c parses as (a - b) - c
+        let expr = call_method(C1Parser::assignment, "a - b - c").unwrap();
+        let ExprKind::Binary { lhs, rhs, .. } = expr.kind else {
+            panic!("expected binary expression");
+        };
+        assert!(matches!(rhs.kind, ExprKind::Variable(ref id) if id.name == "c"));
+        assert!(matches!(
+            lhs.kind,
+            ExprKind::Binary {
+                op: BinaryOperator::Subtract,
+                ..
+            }
+        ));
+
+        // -a + b negates only the first term
+        let expr = call_method(C1Parser::simpexpr, "-a + b").unwrap();
+        let ExprKind::Binary { lhs, .. } = expr.kind else {
+            panic!("expected binary expression");
+        };
+        assert!(matches!(
+            lhs.kind,
+            ExprKind::Unary {
+                op: UnaryOperator::Minus,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn unary_precedence() {
+        // -x * -y parses as (-x) * (-y)
+        let expr = call_method(C1Parser::assignment, "-x * -y").unwrap();
+        let ExprKind::Binary { op, lhs, rhs } = expr.kind else {
+            panic!("expected binary expression");
+        };
+        assert_eq!(op, BinaryOperator::Multiply);
+        for operand in [lhs, rhs] {
+            assert!(matches!(
+                operand.kind,
+                ExprKind::Unary {
+                    op: UnaryOperator::Minus,
+                    ..
+                }
+            ));
+        }
+
+        // !a && b parses as (!a) && b
+        let expr = call_method(C1Parser::assignment, "!a && b").unwrap();
+        let ExprKind::Binary { op, lhs, .. } = expr.kind else {
+            panic!("expected binary expression");
+        };
+        assert_eq!(op, BinaryOperator::And);
+        assert!(matches!(
+            lhs.kind,
+            ExprKind::Unary {
+                op: UnaryOperator::Not,
+                ..
+            }
+        ));
+
+        // Unary operators nest: - +!x parses as -(+(!x))
+        let mut expr = call_method(C1Parser::assignment, "- +!x").unwrap();
+        for expected in [
+            UnaryOperator::Minus,
+            UnaryOperator::Plus,
+            UnaryOperator::Not,
+        ] {
+            let ExprKind::Unary { op, operand } = expr.kind else {
+                panic!("expected unary expression");
+            };
+            assert_eq!(op, expected);
+            expr = *operand;
+        }
+        assert!(matches!(expr.kind, ExprKind::Variable(ref id) if id.name == "x"));
+
+        assert!(call_method(C1Parser::assignment, "!").is_err());
+        assert!(call_method(C1Parser::assignment, "x * !").is_err());
+    }
+
+    #[test]
+    fn modulo_precedence() {
+        // a + b % c parses as a + (b % c)
+        let expr = call_method(C1Parser::assignment, "a + b % c").unwrap();
+        let ExprKind::Binary { op, rhs, .. } = expr.kind else {
+            panic!("expected binary expression");
+        };
+        assert_eq!(op, BinaryOperator::Add);
+        assert!(matches!(
+            rhs.kind,
+            ExprKind::Binary {
+                op: BinaryOperator::Modulo,
+                ..
+            }
+        ));
+
+        // a % b * c parses as (a % b) * c
+        let expr = call_method(C1Parser::assignment, "a % b * c").unwrap();
+        let ExprKind::Binary { op, lhs, .. } = expr.kind else {
+            panic!("expected binary expression");
+        };
+        assert_eq!(op, BinaryOperator::Multiply);
+        assert!(matches!(
+            lhs.kind,
+            ExprKind::Binary {
+                op: BinaryOperator::Modulo,
+                ..
+            }
+        ));
+
+        assert!(call_method(C1Parser::assignment, "a %").is_err());
+        assert!(call_method(C1Parser::assignment, "% a").is_err());
+    }
+
+    #[test]
+    fn literal_values() {
+        let expr = call_method(C1Parser::factor, "42").unwrap();
+        assert_eq!(expr.kind, ExprKind::Literal(Literal::Int(42)));
+        let expr = call_method(C1Parser::factor, "2.5").unwrap();
+        assert_eq!(expr.kind, ExprKind::Literal(Literal::Float(2.5)));
+        let expr = call_method(C1Parser::factor, "false").unwrap();
+        assert_eq!(expr.kind, ExprKind::Literal(Literal::Bool(false)));
+        assert!(call_method(C1Parser::factor, "99999999999").is_err());
+    }
+
+    #[test]
+    fn nested_assignment() {
+        let expr = call_method(C1Parser::assignment, "x = y = 3").unwrap();
+        let ExprKind::Assignment { target, value } = expr.kind else {
+            panic!("expected assignment");
+        };
+        assert_eq!(target.name, "x");
+        assert!(matches!(value.kind, ExprKind::Assignment { .. }));
+    }
+
+    #[test]
+    fn error_kinds() {
+        let error = C1Parser::parse_program("void main() { x = (1; }").unwrap_err();
+        assert_eq!(error.kind, ParseErrorKind::UnexpectedToken);
+        assert_eq!(error.nonterminal, Nonterminal::Factor);
+        assert_eq!(error.found, Some(C1Token::Semicolon));
+        assert_eq!(error.expected, [C1Token::RightParenthesis]);
+        assert_eq!(error.line(), Some(1));
+        assert_eq!(error.column(), Some(21));
+        assert_eq!(error.location.unwrap().span(), 20..21);
+
+        let error = C1Parser::parse_program("void main() { return 1").unwrap_err();
+        assert_eq!(error.kind, ParseErrorKind::UnexpectedEof);
+        assert_eq!(error.nonterminal, Nonterminal::Statement);
+        assert_eq!(error.found, None);
+        assert_eq!(error.location, None);
+
+        let error = C1Parser::parse_program("void main() { x = 1 $ 2; }").unwrap_err();
+        assert_eq!(error.kind, ParseErrorKind::InvalidToken);
+        assert_eq!(error.found, Some(C1Token::Error));
+
+        let error = C1Parser::parse_program("void main() { x = 4294967296; }").unwrap_err();
+        assert_eq!(error.kind, ParseErrorKind::InvalidLiteral);
+
+        let error = call_method(C1Parser::factor, "if").unwrap_err();
+        assert_eq!(error.nonterminal, Nonterminal::Factor);
+        assert!(error.expected.contains(&C1Token::ConstInt));
+        assert!(error.expected.contains(&C1Token::LeftParenthesis));
+
+        let error = C1Parser::parse_program("const bar() {}").unwrap_err();
+        assert_eq!(error.nonterminal, Nonterminal::Type);
+        assert_eq!(error.expected.len(), 4);
+    }
+
+    #[test]
+    fn error_display() {
+        let error = C1Parser::parse_program("void main() {\n x = (1;\n}").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Expected ')' at line 2, got ';' instead."
+        );
+        assert_eq!(error.reason(), "Expected ')'");
+
+        let error = C1Parser::parse_program("void main() {").unwrap_err();
+        assert_eq!(error.to_string(), "Expected '}'. Reached EOF");
+
+        assert_eq!(
+            C1Parser::parse("int foo() { printf(); }"),
+            Err(String::from("Expected factor at line 1, got ')' instead."))
+        );
+    }
+
+    #[test]
+    fn recovery_reports_all_errors() {
+        let (program, errors) = C1Parser::parse_with_recovery(
+            "void main() {\n\
+                x = ;\n\
+                y = 1\n\
+                printf(y);\n\
+                if (x { z = 2; }\n\
+                foo();\n\
+            }\n\
+            int bar() { return 1; }",
+        );
+        let lines: Vec<_> = errors.iter().map(|error| error.line()).collect();
+        assert_eq!(lines, [Some(2), Some(4), Some(5)]);
+        assert_eq!(errors[1].expected, [C1Token::Semicolon]);
+
+        // The statements around the errors and the following function are kept
+        assert_eq!(program.functions.len(), 2);
+        let main = &program.functions[0];
+        assert!(matches!(
+            main.body.last().unwrap().kind,
+            StatementKind::Call(_)
+        ));
+        assert_eq!(program.functions[1].name.name, "bar");
+    }
+
+    #[test]
+    fn recovery_at_function_boundaries() {
+        // A missing "}" is reported once the next function definition starts
+        let (program, errors) =
+            C1Parser::parse_with_recovery("void foo() {\n x = 1;\nint bar() { return 1; }");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].nonterminal, Nonterminal::FunctionDefinition);
+        assert_eq!(errors[0].found, Some(C1Token::KwInt));
+        assert_eq!(program.functions.len(), 2);
+
+        // A broken function header skips to the next type keyword
+        let (program, errors) =
+            C1Parser::parse_with_recovery("void foo( {}\nint bar() {}\nx = 1;\n}");
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].found, Some(C1Token::LeftBrace));
+        assert_eq!(errors[1].found_text.as_deref(), Some("x"));
+        assert_eq!(program.functions.len(), 1);
+        assert_eq!(program.functions[0].name.name, "bar");
+
+        // Stray tokens inside a block are skipped up to the next ";"
+        let (program, errors) = C1Parser::parse_with_recovery("void foo() { { 1 + 2; x = 3; } }");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].nonterminal, Nonterminal::StatementList);
+        let StatementKind::Block(statements) = &program.functions[0].body[0].kind else {
+            panic!("expected block");
+        };
+        assert_eq!(statements.len(), 1);
+    }
+
+    #[test]
+    fn recovery_without_errors() {
+        let (program, errors) = C1Parser::parse_with_recovery("void main() { printf(1); }");
+        assert!(errors.is_empty());
+        assert_eq!(
+            Ok(program),
+            C1Parser::parse_program("void main() { printf(1); }")
+        );
+
+        let (program, errors) = C1Parser::parse_with_recovery("void main() { if");
+        assert_eq!(errors.len(), 2);
+        assert_eq!(program.functions.len(), 1);
+    }
 }
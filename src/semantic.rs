@@ -0,0 +1,574 @@
+//! Name resolution for C(-1) programs.
+//!
+//! [`analyze`] builds the table of all function definitions of a program and checks that
+//! - no two functions have the same name and no two parameters of a function have the same name,
+//! - no global variable is declared twice or with the name of a function, and no local variable is
+//!   declared twice in a function or with the name of a parameter. Local variables may have the
+//!   name of a global variable.
+//! - every called function is defined and called with one argument per parameter,
+//! - there is a `main` function without parameters returning `int` or `void`.
+//!
+//! ```
+//! use cb_3::semantic::{analyze, SemanticErrorKind};
+//! use cb_3::C1Parser;
+//!
+//! let program = C1Parser::parse_program("void main() { foo(); }").unwrap();
+//! let errors = analyze(&program).unwrap_err();
+//! assert_eq!(errors[0].kind, SemanticErrorKind::UndefinedFunction("foo".to_string()));
+//! ```
+
+use crate::ast::{
+    Expr, ExprKind, FunctionCall, FunctionDefinition, Location, PrintfArgument, Program, Statement,
+    StatementKind, Type,
+};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Classification of a semantic error
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SemanticErrorKind {
+    /// A called function is not defined
+    UndefinedFunction(String),
+    /// A function is defined more than once
+    DuplicateFunction {
+        name: String,
+        /// Location of the name of the first definition
+        first_definition: Location,
+    },
+    /// A function has two parameters with the same name
+    DuplicateParameter {
+        name: String,
+        /// Location of the first parameter with the name
+        first_definition: Location,
+    },
+    /// A global variable is declared twice, or a local variable is declared twice in a function or
+    /// has the name of a parameter
+    DuplicateVariable {
+        name: String,
+        /// Location of the parameter or the first declaration with the name
+        first_definition: Location,
+    },
+    /// A global variable has the name of a function
+    DuplicateName {
+        name: String,
+        /// Location of the first of the function and the global variable
+        first_definition: Location,
+    },
+    /// A function is called with the wrong number of arguments
+    ArgumentCount {
+        function: String,
+        expected: usize,
+        found: usize,
+    },
+    /// The program has no `main` function
+    MissingMain,
+    /// `main` returns something else than `int` or `void`
+    InvalidMainType(Type),
+    /// `main` has parameters
+    MainParameters,
+}
+
+/// An error found by [`analyze`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SemanticError {
+    pub kind: SemanticErrorKind,
+    /// Location of the offending name or type, `None` if the program has no `main`
+    pub location: Option<Location>,
+}
+
+impl SemanticError {
+    fn new(kind: SemanticErrorKind, location: Location) -> SemanticError {
+        SemanticError {
+            kind,
+            location: Some(location),
+        }
+    }
+
+    /// Short description of what went wrong, without location information
+    pub fn message(&self) -> String {
+        match &self.kind {
+            SemanticErrorKind::UndefinedFunction(name) => {
+                format!("Call of undefined function '{}'", name)
+            }
+            SemanticErrorKind::DuplicateFunction { name, .. } => {
+                format!("Function '{}' is defined multiple times", name)
+            }
+            SemanticErrorKind::DuplicateParameter { name, .. } => {
+                format!("Parameter '{}' is defined multiple times", name)
+            }
+            SemanticErrorKind::DuplicateVariable { name, .. } => {
+                format!("Variable '{}' is defined multiple times", name)
+            }
+            SemanticErrorKind::DuplicateName { name, .. } => {
+                format!("'{}' is defined as a function and a global variable", name)
+            }
+            SemanticErrorKind::ArgumentCount {
+                function,
+                expected,
+                found,
+            } => format!(
+                "Function '{}' takes {} arguments, but {} were given",
+                function, expected, found
+            ),
+            SemanticErrorKind::MissingMain => "No main function".to_string(),
+            SemanticErrorKind::InvalidMainType(found) => {
+                format!("main has to return int or void, not {}", found)
+            }
+            SemanticErrorKind::MainParameters => "main cannot have parameters".to_string(),
+        }
+    }
+}
+
+impl fmt::Display for SemanticError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.location {
+            Some(location) => write!(f, "{} at line {}", self.message(), location.line),
+            None => f.write_str(&self.message()),
+        }
+    }
+}
+
+impl std::error::Error for SemanticError {}
+
+/// The functions of a program by name
+#[derive(Debug, Clone)]
+pub struct FunctionTable<'a> {
+    functions: HashMap<&'a str, &'a FunctionDefinition>,
+}
+
+impl<'a> FunctionTable<'a> {
+    pub fn get(&self, name: &str) -> Option<&'a FunctionDefinition> {
+        self.functions.get(name).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.functions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.functions.is_empty()
+    }
+}
+
+/// Build the function table of the program, or return all semantic errors ordered by location
+pub fn analyze(program: &Program) -> Result<FunctionTable<'_>, Vec<SemanticError>> {
+    let mut errors = Vec::new();
+    let mut globals: HashMap<&str, Location> = HashMap::new();
+    for global in &program.globals {
+        let name = &global.name;
+        match globals.get(name.name.as_str()) {
+            Some(first) => errors.push(SemanticError::new(
+                SemanticErrorKind::DuplicateVariable {
+                    name: name.name.clone(),
+                    first_definition: *first,
+                },
+                name.location,
+            )),
+            None => {
+                globals.insert(&name.name, name.location);
+            }
+        }
+    }
+
+    let mut functions: HashMap<&str, &FunctionDefinition> = HashMap::new();
+    for function in &program.functions {
+        let name = &function.name;
+        match functions.get(name.name.as_str()) {
+            Some(first) => errors.push(SemanticError::new(
+                SemanticErrorKind::DuplicateFunction {
+                    name: name.name.clone(),
+                    first_definition: first.name.location,
+                },
+                name.location,
+            )),
+            None => {
+                functions.insert(&name.name, function);
+            }
+        }
+
+        for (index, parameter) in function.parameters.iter().enumerate() {
+            let name = &parameter.name;
+            let first = function.parameters[..index]
+                .iter()
+                .find(|other| other.name.name == name.name);
+            if let Some(first) = first {
+                errors.push(SemanticError::new(
+                    SemanticErrorKind::DuplicateParameter {
+                        name: name.name.clone(),
+                        first_definition: first.name.location,
+                    },
+                    name.location,
+                ));
+            }
+        }
+
+        let mut variables: HashMap<&str, Location> = HashMap::new();
+        for parameter in &function.parameters {
+            variables
+                .entry(&parameter.name.name)
+                .or_insert(parameter.name.location);
+        }
+        for name in function.declarations() {
+            match variables.get(name.name.as_str()) {
+                Some(first) => errors.push(SemanticError::new(
+                    SemanticErrorKind::DuplicateVariable {
+                        name: name.name.clone(),
+                        first_definition: *first,
+                    },
+                    name.location,
+                )),
+                None => {
+                    variables.insert(&name.name, name.location);
+                }
+            }
+        }
+    }
+
+    // Report the later of a global variable and a function with the same name
+    for global in &program.globals {
+        let name = &global.name;
+        if let Some(function) = functions.get(name.name.as_str()) {
+            let (first, second) = if function.name.location.start < name.location.start {
+                (function.name.location, name.location)
+            } else {
+                (name.location, function.name.location)
+            };
+            errors.push(SemanticError::new(
+                SemanticErrorKind::DuplicateName {
+                    name: name.name.clone(),
+                    first_definition: first,
+                },
+                second,
+            ));
+        }
+    }
+
+    let mut calls = Vec::new();
+    for function in &program.functions {
+        statements_calls(&function.body, &mut calls);
+    }
+    for call in calls {
+        match functions.get(call.name.name.as_str()) {
+            None => errors.push(SemanticError::new(
+                SemanticErrorKind::UndefinedFunction(call.name.name.clone()),
+                call.name.location,
+            )),
+            Some(function) if function.parameters.len() != call.arguments.len() => {
+                errors.push(SemanticError::new(
+                    SemanticErrorKind::ArgumentCount {
+                        function: call.name.name.clone(),
+                        expected: function.parameters.len(),
+                        found: call.arguments.len(),
+                    },
+                    call.name.location,
+                ))
+            }
+            Some(_) => {}
+        }
+    }
+
+    match functions.get("main") {
+        Some(main) if !matches!(main.return_type, Type::Int | Type::Void) => {
+            errors.push(SemanticError::new(
+                SemanticErrorKind::InvalidMainType(main.return_type),
+                main.location,
+            ))
+        }
+        Some(main) if !main.parameters.is_empty() => errors.push(SemanticError::new(
+            SemanticErrorKind::MainParameters,
+            main.parameters[0].name.location,
+        )),
+        Some(_) => {}
+        None => errors.push(SemanticError {
+            kind: SemanticErrorKind::MissingMain,
+            location: None,
+        }),
+    }
+
+    if errors.is_empty() {
+        Ok(FunctionTable { functions })
+    } else {
+        // Errors without location come last
+        errors.sort_by_key(|error| error.location.map_or(usize::MAX, |location| location.start));
+        Err(errors)
+    }
+}
+
+/// Collect the function calls in the statements in source order
+fn statements_calls<'a>(statements: &'a [Statement], calls: &mut Vec<&'a FunctionCall>) {
+    for statement in statements {
+        statement_calls(statement, calls);
+    }
+}
+
+fn statement_calls<'a>(statement: &'a Statement, calls: &mut Vec<&'a FunctionCall>) {
+    match &statement.kind {
+        StatementKind::Block(statements) => statements_calls(statements, calls),
+        StatementKind::If {
+            condition,
+            then_branch,
+            else_branch,
+        } => {
+            expr_calls(condition, calls);
+            statement_calls(then_branch, calls);
+            if let Some(else_branch) = else_branch {
+                statement_calls(else_branch, calls);
+            }
+        }
+        StatementKind::While { condition, body } => {
+            expr_calls(condition, calls);
+            statement_calls(body, calls);
+        }
+        StatementKind::DoWhile { body, condition } => {
+            statement_calls(body, calls);
+            expr_calls(condition, calls);
+        }
+        StatementKind::For {
+            init,
+            condition,
+            update,
+            body,
+        } => {
+            if let Some(init) = init {
+                statement_calls(init, calls);
+            }
+            if let Some(condition) = condition {
+                expr_calls(condition, calls);
+            }
+            if let Some(update) = update {
+                statement_calls(update, calls);
+            }
+            statement_calls(body, calls);
+        }
+        StatementKind::Declaration { value, .. } | StatementKind::Return(value) => {
+            if let Some(value) = value {
+                expr_calls(value, calls);
+            }
+        }
+        StatementKind::Printf(PrintfArgument::String(_)) => {}
+        StatementKind::Printf(PrintfArgument::Value(value)) => expr_calls(value, calls),
+        StatementKind::Assignment { value, .. } => expr_calls(value, calls),
+        StatementKind::Call(call) => call_calls(call, calls),
+    }
+}
+
+fn expr_calls<'a>(expr: &'a Expr, calls: &mut Vec<&'a FunctionCall>) {
+    match &expr.kind {
+        ExprKind::Literal(_) | ExprKind::Variable(_) => {}
+        ExprKind::Call(call) => call_calls(call, calls),
+        ExprKind::Assignment { value, .. } => expr_calls(value, calls),
+        ExprKind::Unary { operand, .. } => expr_calls(operand, calls),
+        ExprKind::Binary { lhs, rhs, .. } => {
+            expr_calls(lhs, calls);
+            expr_calls(rhs, calls);
+        }
+    }
+}
+
+/// Collect the call and the calls in its arguments
+fn call_calls<'a>(call: &'a FunctionCall, calls: &mut Vec<&'a FunctionCall>) {
+    calls.push(call);
+    for argument in &call.arguments {
+        expr_calls(argument, calls);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ast::Type;
+    use crate::semantic::{analyze, SemanticError, SemanticErrorKind};
+    use crate::{C1Parser, Dialect};
+
+    fn analyze_text(text: &str) -> Result<usize, Vec<SemanticError>> {
+        let program = C1Parser::parse_program_in(text, Dialect::C1).unwrap();
+        analyze(&program).map(|table| table.len())
+    }
+
+    fn error_kinds(text: &str) -> Vec<SemanticErrorKind> {
+        analyze_text(text)
+            .unwrap_err()
+            .into_iter()
+            .map(|error| error.kind)
+            .collect()
+    }
+
+    #[test]
+    fn valid_programs() {
+        assert_eq!(
+            analyze_text(include_str!("../tests/data/beispiel.c-1")),
+            Ok(3)
+        );
+        assert_eq!(analyze_text("int main() { return main(); }"), Ok(1));
+
+        let program = C1Parser::parse_program("void f() {} void main() { f(); }").unwrap();
+        let table = analyze(&program).unwrap();
+        assert_eq!(table.get("f").unwrap().name.location.column, 6);
+        assert!(table.get("g").is_none());
+    }
+
+    #[test]
+    fn undefined_functions() {
+        let errors =
+            analyze_text("void main() {\n  if (f()) { x = 1 + g(); }\n  h();\n}").unwrap_err();
+        let names: Vec<_> = errors
+            .iter()
+            .map(|error| (error.kind.clone(), error.location.unwrap().line))
+            .collect();
+        assert_eq!(
+            names,
+            [
+                (SemanticErrorKind::UndefinedFunction("f".to_string()), 2),
+                (SemanticErrorKind::UndefinedFunction("g".to_string()), 2),
+                (SemanticErrorKind::UndefinedFunction("h".to_string()), 3),
+            ]
+        );
+        assert_eq!(
+            errors[0].to_string(),
+            "Call of undefined function 'f' at line 2"
+        );
+    }
+
+    #[test]
+    fn duplicate_functions() {
+        let errors = analyze_text("void main() {}\nint f() {}\nvoid f() {}").unwrap_err();
+        assert_eq!(errors.len(), 1);
+        match &errors[0].kind {
+            SemanticErrorKind::DuplicateFunction {
+                name,
+                first_definition,
+            } => {
+                assert_eq!(name, "f");
+                assert_eq!(first_definition.line, 2);
+            }
+            kind => panic!("unexpected error {:?}", kind),
+        }
+        assert_eq!(errors[0].location.unwrap().line, 3);
+    }
+
+    #[test]
+    fn parameters_and_arguments() {
+        assert_eq!(
+            analyze_text("int f(int a, int b) { return a + b; } void main() { f(1, f(2, 3)); }"),
+            Ok(2)
+        );
+        assert_eq!(
+            error_kinds("int f(int a) { return a; } void main() { f(); f(1, g(f(1, 2))); }"),
+            [
+                SemanticErrorKind::ArgumentCount {
+                    function: "f".to_string(),
+                    expected: 1,
+                    found: 0
+                },
+                SemanticErrorKind::ArgumentCount {
+                    function: "f".to_string(),
+                    expected: 1,
+                    found: 2
+                },
+                SemanticErrorKind::UndefinedFunction("g".to_string()),
+                SemanticErrorKind::ArgumentCount {
+                    function: "f".to_string(),
+                    expected: 1,
+                    found: 2
+                },
+            ]
+        );
+
+        let errors = analyze_text("void f(int a, float b, bool a) {} void main() {}").unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].location.unwrap().column, 29);
+        assert!(matches!(
+            &errors[0].kind,
+            SemanticErrorKind::DuplicateParameter { name, first_definition }
+                if name == "a" && first_definition.column == 12
+        ));
+    }
+
+    #[test]
+    fn declarations() {
+        assert_eq!(
+            analyze_text("void main() { int x = f(); if (true) { float y; } } int f() {}"),
+            Ok(2)
+        );
+
+        let errors = analyze_text(
+            "void f(int a) {
+                 int b;
+                 while (true) { bool a = g(); }
+                 float b = 1.0;
+             }
+             void main() {}",
+        )
+        .unwrap_err();
+        assert_eq!(errors.len(), 3);
+        assert!(matches!(
+            &errors[0].kind,
+            SemanticErrorKind::DuplicateVariable { name, first_definition }
+                if name == "a" && first_definition.line == 1
+        ));
+        assert_eq!(
+            errors[1].kind,
+            SemanticErrorKind::UndefinedFunction("g".to_string())
+        );
+        assert!(matches!(
+            &errors[2].kind,
+            SemanticErrorKind::DuplicateVariable { name, first_definition }
+                if name == "b" && first_definition.line == 2
+        ));
+    }
+
+    #[test]
+    fn globals() {
+        assert_eq!(
+            analyze_text("int x; float y = 1.0; void main() { int x = 1; y = x; }"),
+            Ok(1)
+        );
+
+        let errors = analyze_text("int x;\nvoid main() {}\nbool x = true;").unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            &errors[0].kind,
+            SemanticErrorKind::DuplicateVariable { name, first_definition }
+                if name == "x" && first_definition.line == 1
+        ));
+        assert_eq!(errors[0].location.unwrap().line, 3);
+
+        let errors = analyze_text("int f;\nvoid main() {}\nvoid f() {}\nfloat main;").unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert!(matches!(
+            &errors[0].kind,
+            SemanticErrorKind::DuplicateName { name, first_definition }
+                if name == "f" && first_definition.line == 1
+        ));
+        assert_eq!(errors[0].location.unwrap().line, 3);
+        assert!(matches!(
+            &errors[1].kind,
+            SemanticErrorKind::DuplicateName { name, first_definition }
+                if name == "main" && first_definition.line == 2
+        ));
+        assert_eq!(
+            errors[1].to_string(),
+            "'main' is defined as a function and a global variable at line 4"
+        );
+    }
+
+    #[test]
+    fn main_function() {
+        assert_eq!(error_kinds("void f() {}"), [SemanticErrorKind::MissingMain]);
+        assert_eq!(error_kinds(""), [SemanticErrorKind::MissingMain]);
+        assert_eq!(
+            error_kinds("float main() { foo(); }"),
+            [
+                SemanticErrorKind::InvalidMainType(Type::Float),
+                SemanticErrorKind::UndefinedFunction("foo".to_string()),
+            ]
+        );
+        assert_eq!(
+            error_kinds("bool main() {}"),
+            [SemanticErrorKind::InvalidMainType(Type::Bool)]
+        );
+        assert_eq!(
+            error_kinds("int main(int argc) { return argc; }"),
+            [SemanticErrorKind::MainParameters]
+        );
+    }
+}
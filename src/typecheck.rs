@@ -0,0 +1,916 @@
+//! Static type checking for C(-1) programs.
+//!
+//! The type of a parameter or a declared variable is its declared type. The type of an undeclared
+//! variable is the type of the value of its first assignment in source order, reading it before
+//! that assignment is an error. In a function, a name refers to a global variable unless the
+//! function has a parameter or declares a local variable with that name. The rules are:
+//! - `+`, `-`, `*` and `/` take `int` or `float` operands. The result is `int` if both operands are
+//!   `int`, otherwise `float`.
+//! - `<`, `<=`, `>` and `>=` take `int` or `float` operands, `==` and `!=` take two numbers or two
+//!   `bool`s. The result is `bool`.
+//! - `&&` and `||` take `bool` operands.
+//! - An `int` can be assigned, passed as an argument or returned where a `float` is expected, no
+//!   other conversions exist.
+//! - Parameters and variables cannot be declared `void`. The initial value of a global variable
+//!   has to be assignable to its type.
+//! - Conditions of `if` statements and loops are `bool`. `printf` prints any value except the
+//!   result of a `void` function.
+//! - Functions with a return type other than `void` return a value on every path. A loop with the
+//!   condition `true` never ends, as there is no `break`.
+//!
+//! ```
+//! use cb_3::ast::Type;
+//! use cb_3::typecheck::{check, TypeErrorKind};
+//! use cb_3::C1Parser;
+//!
+//! let program = C1Parser::parse_program("int main() { return 3.14159; }").unwrap();
+//! let errors = check(&program).unwrap_err();
+//! assert_eq!(
+//!     errors[0].kind,
+//!     TypeErrorKind::Mismatch { expected: Type::Int, found: Type::Float }
+//! );
+//! ```
+
+use crate::ast::{
+    BinaryOperator, Expr, ExprKind, FunctionCall, FunctionDefinition, GlobalDeclaration,
+    Identifier, Literal, Location, PrintfArgument, Program, Statement, StatementKind, Type,
+    UnaryOperator,
+};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// Classification of a type error
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypeErrorKind {
+    /// A value of the wrong type was assigned, returned or used as a condition
+    Mismatch { expected: Type, found: Type },
+    /// An operator or `printf` was applied to a value of a type it does not support
+    InvalidOperand { operator: String, found: Type },
+    /// The operands of a binary operator are valid on their own, but not together, e.g. `1 == true`
+    MismatchedOperands {
+        operator: String,
+        lhs: Type,
+        rhs: Type,
+    },
+    /// A variable is read before its first assignment
+    UndefinedVariable(String),
+    /// A parameter or variable is declared as `void`
+    VoidVariable(String),
+    /// The end of a function with a return value can be reached
+    MissingReturn { function: String, expected: Type },
+}
+
+/// An error found by [`check`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeError {
+    pub kind: TypeErrorKind,
+    /// Location of the offending expression or statement
+    pub location: Location,
+}
+
+impl TypeError {
+    fn new(kind: TypeErrorKind, location: Location) -> TypeError {
+        TypeError { kind, location }
+    }
+
+    /// Short description of what went wrong, without location information
+    pub fn message(&self) -> String {
+        match &self.kind {
+            TypeErrorKind::Mismatch { expected, found } => {
+                format!(
+                    "Mismatched types, expected {} but found {}",
+                    expected, found
+                )
+            }
+            TypeErrorKind::InvalidOperand { operator, found } => {
+                format!("Invalid operand of type {} for '{}'", found, operator)
+            }
+            TypeErrorKind::MismatchedOperands { operator, lhs, rhs } => {
+                format!("Cannot apply '{}' to {} and {}", operator, lhs, rhs)
+            }
+            TypeErrorKind::UndefinedVariable(name) => {
+                format!("Variable '{}' is used before it is assigned", name)
+            }
+            TypeErrorKind::VoidVariable(name) => {
+                format!("Variable '{}' cannot have type void", name)
+            }
+            TypeErrorKind::MissingReturn { function, expected } => {
+                format!(
+                    "Function '{}' may end without returning {}",
+                    function, expected
+                )
+            }
+        }
+    }
+}
+
+impl fmt::Display for TypeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at line {}", self.message(), self.location.line)
+    }
+}
+
+impl std::error::Error for TypeError {}
+
+/// The types of a function and its variables
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionTypes {
+    pub return_type: Type,
+    pub parameters: Vec<(String, Type)>,
+    /// The variables of the function other than the parameters, in the order of their first
+    /// assignment
+    pub variables: Vec<(String, Type)>,
+}
+
+impl FunctionTypes {
+    /// Return the type of a parameter or variable
+    pub fn variable(&self, name: &str) -> Option<Type> {
+        self.parameters
+            .iter()
+            .chain(&self.variables)
+            .find(|(variable, _)| variable == name)
+            .map(|(_, ty)| *ty)
+    }
+}
+
+/// The types of a checked program, as needed by code generators
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeInfo {
+    globals: HashMap<String, Type>,
+    functions: HashMap<String, FunctionTypes>,
+}
+
+impl TypeInfo {
+    pub fn global(&self, name: &str) -> Option<Type> {
+        self.globals.get(name).copied()
+    }
+
+    pub fn function(&self, name: &str) -> Option<&FunctionTypes> {
+        self.functions.get(name)
+    }
+
+    /// Return the type of the local or global variable the name refers to in the given function
+    pub fn variable(&self, function: &str, name: &str) -> Option<Type> {
+        self.function(function)
+            .and_then(|types| types.variable(name))
+            .or_else(|| self.global(name))
+    }
+
+    /// Return the type of an expression in the given function of the checked program
+    pub fn expr_type(&self, function: &str, expr: &Expr) -> Type {
+        match &expr.kind {
+            ExprKind::Literal(literal) => literal_type(literal),
+            ExprKind::Variable(name) | ExprKind::Assignment { target: name, .. } => self
+                .variable(function, &name.name)
+                .expect("variable of a checked program"),
+            ExprKind::Call(call) => {
+                self.function(&call.name.name)
+                    .expect("function of a checked program")
+                    .return_type
+            }
+            ExprKind::Unary { operand, .. } => self.expr_type(function, operand),
+            ExprKind::Binary { op, lhs, rhs } => binary_type(
+                *op,
+                self.expr_type(function, lhs),
+                self.expr_type(function, rhs),
+            )
+            .expect("operands of a checked program"),
+        }
+    }
+}
+
+/// Check the program and return the types of its functions and variables, or all type errors in
+/// source order.
+///
+/// Calls of undefined functions are not reported, see
+/// [`semantic::analyze`](crate::semantic::analyze).
+pub fn check(program: &Program) -> Result<TypeInfo, Vec<TypeError>> {
+    let mut signatures = HashMap::new();
+    for function in &program.functions {
+        signatures
+            .entry(function.name.name.as_str())
+            .or_insert(function);
+    }
+
+    let mut checker = Checker {
+        signatures,
+        globals: HashMap::new(),
+        locals: HashSet::new(),
+        variables: Vec::new(),
+        errors: Vec::new(),
+    };
+    for global in &program.globals {
+        checker.global(global);
+    }
+    let mut functions = HashMap::new();
+    for function in &program.functions {
+        let types = checker.function(function);
+        functions.entry(function.name.name.clone()).or_insert(types);
+    }
+
+    if checker.errors.is_empty() {
+        let globals = checker
+            .globals
+            .iter()
+            .map(|(name, ty)| (name.to_string(), *ty))
+            .collect();
+        Ok(TypeInfo { globals, functions })
+    } else {
+        checker.errors.sort_by_key(|error| error.location.start);
+        Err(checker.errors)
+    }
+}
+
+/// Types are `None` for expressions whose type cannot be determined because of an error that was
+/// already reported, so that no follow-up errors are reported.
+struct Checker<'a> {
+    signatures: HashMap<&'a str, &'a FunctionDefinition>,
+    globals: HashMap<&'a str, Type>,
+    /// The names that refer to local variables in the current function instead of globals
+    locals: HashSet<&'a str>,
+    /// The parameters and local variables of the current function
+    variables: Vec<(String, Type)>,
+    errors: Vec<TypeError>,
+}
+
+impl<'a> Checker<'a> {
+    fn global(&mut self, global: &'a GlobalDeclaration) {
+        let name = &global.name;
+        if global.ty == Type::Void {
+            self.error(
+                TypeErrorKind::VoidVariable(name.name.clone()),
+                name.location,
+            );
+        }
+        if let Some(value) = &global.value {
+            let found = literal_type(value);
+            if !assignable(global.ty, found) {
+                self.mismatch(global.ty, found, name.location);
+            }
+        }
+        // Declaring a global twice is reported by the semantic analysis
+        self.globals.entry(&name.name).or_insert(global.ty);
+    }
+
+    fn function(&mut self, function: &'a FunctionDefinition) -> FunctionTypes {
+        self.variables.clear();
+        self.locals = function
+            .locals()
+            .into_iter()
+            .map(|name| name.name.as_str())
+            .collect();
+        for parameter in &function.parameters {
+            if parameter.ty == Type::Void {
+                self.error(
+                    TypeErrorKind::VoidVariable(parameter.name.name.clone()),
+                    parameter.name.location,
+                );
+            }
+            self.variables
+                .push((parameter.name.name.clone(), parameter.ty));
+        }
+        let return_type = function.return_type;
+        for statement in &function.body {
+            self.statement(statement, return_type);
+        }
+        if return_type != Type::Void && !function.body.iter().any(always_returns) {
+            self.error(
+                TypeErrorKind::MissingReturn {
+                    function: function.name.name.clone(),
+                    expected: return_type,
+                },
+                function.name.location,
+            );
+        }
+        let mut variables = std::mem::take(&mut self.variables);
+        let locals = variables.split_off(function.parameters.len());
+        FunctionTypes {
+            return_type,
+            parameters: variables,
+            variables: locals,
+        }
+    }
+
+    fn statement(&mut self, statement: &Statement, return_type: Type) {
+        match &statement.kind {
+            StatementKind::Block(statements) => {
+                for statement in statements {
+                    self.statement(statement, return_type);
+                }
+            }
+            StatementKind::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                self.condition(condition);
+                self.statement(then_branch, return_type);
+                if let Some(else_branch) = else_branch {
+                    self.statement(else_branch, return_type);
+                }
+            }
+            StatementKind::While { condition, body } => {
+                self.condition(condition);
+                self.statement(body, return_type);
+            }
+            StatementKind::DoWhile { body, condition } => {
+                self.statement(body, return_type);
+                self.condition(condition);
+            }
+            StatementKind::For {
+                init,
+                condition,
+                update,
+                body,
+            } => {
+                // In source order, so the update can declare a variable that the body uses
+                if let Some(init) = init {
+                    self.statement(init, return_type);
+                }
+                if let Some(condition) = condition {
+                    self.condition(condition);
+                }
+                if let Some(update) = update {
+                    self.statement(update, return_type);
+                }
+                self.statement(body, return_type);
+            }
+            StatementKind::Declaration { ty, name, value } => {
+                if *ty == Type::Void {
+                    self.error(
+                        TypeErrorKind::VoidVariable(name.name.clone()),
+                        name.location,
+                    );
+                }
+                if let Some(value) = value {
+                    match self.expr(value) {
+                        Some(found) if !assignable(*ty, found) => {
+                            self.mismatch(*ty, found, value.location)
+                        }
+                        _ => {}
+                    }
+                }
+                // Declaring a variable twice is reported by the semantic analysis, an earlier
+                // assignment has to agree with the declared type
+                match self.variable(&name.name) {
+                    Some(found) if found != *ty => self.mismatch(*ty, found, name.location),
+                    Some(_) => {}
+                    None => self.variables.push((name.name.clone(), *ty)),
+                }
+            }
+            StatementKind::Return(None) => {
+                if return_type != Type::Void {
+                    self.mismatch(return_type, Type::Void, statement.location);
+                }
+            }
+            StatementKind::Return(Some(value)) => {
+                if let Some(found) = self.expr(value) {
+                    if !assignable(return_type, found) {
+                        self.mismatch(return_type, found, value.location);
+                    }
+                }
+            }
+            StatementKind::Printf(PrintfArgument::String(_)) => {}
+            StatementKind::Printf(PrintfArgument::Value(value)) => {
+                if self.expr(value) == Some(Type::Void) {
+                    self.invalid_operand("printf", Type::Void, value.location);
+                }
+            }
+            StatementKind::Assignment { target, value } => {
+                self.assignment(target, value);
+            }
+            StatementKind::Call(call) => {
+                self.call(call);
+            }
+        }
+    }
+
+    fn condition(&mut self, condition: &Expr) {
+        match self.expr(condition) {
+            Some(Type::Bool) | None => {}
+            Some(found) => self.mismatch(Type::Bool, found, condition.location),
+        }
+    }
+
+    fn assignment(&mut self, target: &Identifier, value: &Expr) -> Option<Type> {
+        let found = self.expr(value)?;
+        if found == Type::Void {
+            self.invalid_operand("=", found, value.location);
+            return None;
+        }
+        match self.variable(&target.name) {
+            Some(expected) if assignable(expected, found) => Some(expected),
+            Some(expected) => {
+                self.mismatch(expected, found, value.location);
+                None
+            }
+            None => {
+                // The first assignment declares the variable
+                self.variables.push((target.name.clone(), found));
+                Some(found)
+            }
+        }
+    }
+
+    fn call(&mut self, call: &FunctionCall) -> Option<Type> {
+        let arguments: Vec<_> = call
+            .arguments
+            .iter()
+            .map(|argument| self.expr(argument))
+            .collect();
+        let function = *self.signatures.get(call.name.name.as_str())?;
+        for ((parameter, argument), found) in function
+            .parameters
+            .iter()
+            .zip(&call.arguments)
+            .zip(arguments)
+        {
+            match found {
+                Some(found) if !assignable(parameter.ty, found) => {
+                    self.mismatch(parameter.ty, found, argument.location)
+                }
+                _ => {}
+            }
+        }
+        Some(function.return_type)
+    }
+
+    fn expr(&mut self, expr: &Expr) -> Option<Type> {
+        match &expr.kind {
+            ExprKind::Literal(literal) => Some(literal_type(literal)),
+            ExprKind::Variable(name) => {
+                let ty = self.variable(&name.name);
+                if ty.is_none() {
+                    self.error(
+                        TypeErrorKind::UndefinedVariable(name.name.clone()),
+                        name.location,
+                    );
+                }
+                ty
+            }
+            ExprKind::Call(call) => self.call(call),
+            ExprKind::Assignment { target, value } => self.assignment(target, value),
+            ExprKind::Unary { op, operand } => {
+                let found = self.expr(operand)?;
+                match (op, found) {
+                    (UnaryOperator::Minus | UnaryOperator::Plus, Type::Int | Type::Float) => {
+                        Some(found)
+                    }
+                    (UnaryOperator::Not, Type::Bool) => Some(found),
+                    (op, found) => {
+                        self.invalid_operand(op, found, operand.location);
+                        None
+                    }
+                }
+            }
+            ExprKind::Binary { op, lhs, rhs } => {
+                let lhs_type = self.expr(lhs);
+                let rhs_type = self.expr(rhs);
+                let (lhs_type, rhs_type) = (lhs_type?, rhs_type?);
+                match binary_type(*op, lhs_type, rhs_type) {
+                    Ok(ty) => Some(ty),
+                    Err(OperandError::Lhs) => {
+                        self.invalid_operand(op, lhs_type, lhs.location);
+                        None
+                    }
+                    Err(OperandError::Rhs) => {
+                        self.invalid_operand(op, rhs_type, rhs.location);
+                        None
+                    }
+                    Err(OperandError::Mismatch) => {
+                        self.error(
+                            TypeErrorKind::MismatchedOperands {
+                                operator: op.to_string(),
+                                lhs: lhs_type,
+                                rhs: rhs_type,
+                            },
+                            expr.location,
+                        );
+                        None
+                    }
+                }
+            }
+        }
+    }
+
+    fn variable(&self, name: &str) -> Option<Type> {
+        if !self.locals.contains(name) {
+            if let Some(ty) = self.globals.get(name) {
+                return Some(*ty);
+            }
+        }
+        self.variables
+            .iter()
+            .find(|(variable, _)| variable == name)
+            .map(|(_, ty)| *ty)
+    }
+
+    fn error(&mut self, kind: TypeErrorKind, location: Location) {
+        self.errors.push(TypeError::new(kind, location));
+    }
+
+    fn mismatch(&mut self, expected: Type, found: Type, location: Location) {
+        self.error(TypeErrorKind::Mismatch { expected, found }, location);
+    }
+
+    fn invalid_operand(&mut self, operator: impl ToString, found: Type, location: Location) {
+        self.error(
+            TypeErrorKind::InvalidOperand {
+                operator: operator.to_string(),
+                found,
+            },
+            location,
+        );
+    }
+}
+
+/// Which operands of a binary operator are invalid
+#[derive(Debug)]
+enum OperandError {
+    Lhs,
+    Rhs,
+    /// Both operands are valid on their own, but not together
+    Mismatch,
+}
+
+/// Return the result type of a binary operator
+fn binary_type(op: BinaryOperator, lhs: Type, rhs: Type) -> Result<Type, OperandError> {
+    use BinaryOperator::*;
+
+    let accepts = |ty: Type| match op {
+        Add | Subtract | Multiply | Divide | Less | LessEqual | Greater | GreaterEqual => {
+            is_number(ty)
+        }
+        Modulo => ty == Type::Int,
+        Equal | NotEqual => ty != Type::Void,
+        And | Or => ty == Type::Bool,
+    };
+    if !accepts(lhs) {
+        return Err(OperandError::Lhs);
+    }
+    if !accepts(rhs) {
+        return Err(OperandError::Rhs);
+    }
+
+    match op {
+        Add | Subtract | Multiply | Divide if lhs == Type::Int && rhs == Type::Int => Ok(Type::Int),
+        Add | Subtract | Multiply | Divide => Ok(Type::Float),
+        Modulo => Ok(Type::Int),
+        Equal | NotEqual if is_number(lhs) != is_number(rhs) => Err(OperandError::Mismatch),
+        _ => Ok(Type::Bool),
+    }
+}
+
+fn is_number(ty: Type) -> bool {
+    matches!(ty, Type::Int | Type::Float)
+}
+
+/// Whether a value of type `found` can be stored where a value of type `expected` is needed
+fn assignable(expected: Type, found: Type) -> bool {
+    expected == found || (expected == Type::Float && found == Type::Int)
+}
+
+fn literal_type(literal: &Literal) -> Type {
+    match literal {
+        Literal::Int(_) => Type::Int,
+        Literal::Float(_) => Type::Float,
+        Literal::Bool(_) => Type::Bool,
+    }
+}
+
+/// Whether executing the statement always ends with a `return`
+fn always_returns(statement: &Statement) -> bool {
+    match &statement.kind {
+        StatementKind::Return(_) => true,
+        StatementKind::Block(statements) => statements.iter().any(always_returns),
+        StatementKind::If {
+            then_branch,
+            else_branch: Some(else_branch),
+            ..
+        } => always_returns(then_branch) && always_returns(else_branch),
+        StatementKind::While { condition, .. } => is_true(condition),
+        // A for loop without condition runs forever
+        StatementKind::For { condition, .. } => condition.as_ref().is_none_or(is_true),
+        // The body of a do-while loop is executed at least once
+        StatementKind::DoWhile { body, condition } => is_true(condition) || always_returns(body),
+        _ => false,
+    }
+}
+
+/// Whether the condition is the literal `true`, so that a loop with it can only be left by a
+/// `return`
+fn is_true(condition: &Expr) -> bool {
+    matches!(condition.kind, ExprKind::Literal(Literal::Bool(true)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ast::{StatementKind, Type};
+    use crate::typecheck::{check, TypeError, TypeErrorKind};
+    use crate::{C1Parser, Dialect};
+
+    fn check_text(text: &str) -> Result<(), Vec<TypeError>> {
+        check(&C1Parser::parse_program_in(text, Dialect::C1).unwrap()).map(|_| ())
+    }
+
+    fn error_kinds(text: &str) -> Vec<TypeErrorKind> {
+        check_text(text)
+            .unwrap_err()
+            .into_iter()
+            .map(|error| error.kind)
+            .collect()
+    }
+
+    fn mismatch(expected: Type, found: Type) -> TypeErrorKind {
+        TypeErrorKind::Mismatch { expected, found }
+    }
+
+    fn invalid_operand(operator: &str, found: Type) -> TypeErrorKind {
+        TypeErrorKind::InvalidOperand {
+            operator: operator.to_string(),
+            found,
+        }
+    }
+
+    #[test]
+    fn valid_programs() {
+        assert_eq!(
+            check_text(include_str!("../tests/data/beispiel.c-1")),
+            Ok(())
+        );
+        assert_eq!(
+            check_text(
+                "float f() { x = 1.5; x = 2; return x * 2; }
+                 bool g() { b = (1 < 2.5) && (true == (1 != 2)); return b || false; }
+                 void main() { if (g()) { printf(f()); } y = -f(); return; }"
+            ),
+            Ok(())
+        );
+        assert_eq!(check_text(r#"void main() { printf("done\n"); }"#), Ok(()));
+        assert_eq!(
+            check_text("void main() { i = -1 % 3; f = +-1.5; done = !(i == 2) && !false; }"),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn variable_types() {
+        let program = C1Parser::parse_program(
+            "void main() { i = 1; f = i / 2.0; b = f > 1; f = i; c = i = 3 % 2; }",
+        )
+        .unwrap();
+        let types = check(&program).unwrap();
+        let main = types.function("main").unwrap();
+        assert_eq!(main.return_type, Type::Void);
+        assert_eq!(
+            main.variables,
+            [
+                ("i".to_string(), Type::Int),
+                ("f".to_string(), Type::Float),
+                ("b".to_string(), Type::Bool),
+                ("c".to_string(), Type::Int),
+            ]
+        );
+        assert_eq!(main.variable("f"), Some(Type::Float));
+        assert_eq!(main.variable("x"), None);
+
+        let StatementKind::Assignment { value, .. } = &program.functions[0].body[1].kind else {
+            panic!("expected an assignment");
+        };
+        assert_eq!(types.expr_type("main", value), Type::Float);
+    }
+
+    #[test]
+    fn parameters_and_arguments() {
+        let program = C1Parser::parse_program_in(
+            "float f(int a, float b) { c = a + b; return c; }
+             void main() { printf(f(1, 2)); x = f(1, 2.5) * 2; }",
+            Dialect::C1,
+        )
+        .unwrap();
+        let types = check(&program).unwrap();
+        let f = types.function("f").unwrap();
+        assert_eq!(
+            f.parameters,
+            [("a".to_string(), Type::Int), ("b".to_string(), Type::Float)]
+        );
+        assert_eq!(f.variables, [("c".to_string(), Type::Float)]);
+        assert_eq!(f.variable("b"), Some(Type::Float));
+        assert_eq!(
+            types.function("main").unwrap().variable("x"),
+            Some(Type::Float)
+        );
+
+        assert_eq!(
+            error_kinds(
+                "int f(int a, bool b) { a = 1.5; return a; }
+                 void main() { f(1.5, true); f(1, 2); f(1, x); f(1); }"
+            ),
+            [
+                mismatch(Type::Int, Type::Float),
+                mismatch(Type::Int, Type::Float),
+                mismatch(Type::Bool, Type::Int),
+                TypeErrorKind::UndefinedVariable("x".to_string()),
+            ]
+        );
+        assert_eq!(
+            error_kinds("void f(void v) { printf(v); } void main() {}"),
+            [
+                TypeErrorKind::VoidVariable("v".to_string()),
+                invalid_operand("printf", Type::Void),
+            ]
+        );
+    }
+
+    #[test]
+    fn declarations() {
+        let program = C1Parser::parse_program_in(
+            "void main() { float f; int i = 2; f = i; bool b = f > i; float g = i; }",
+            Dialect::C1,
+        )
+        .unwrap();
+        let types = check(&program).unwrap();
+        assert_eq!(
+            types.function("main").unwrap().variables,
+            [
+                ("f".to_string(), Type::Float),
+                ("i".to_string(), Type::Int),
+                ("b".to_string(), Type::Bool),
+                ("g".to_string(), Type::Float),
+            ]
+        );
+
+        assert_eq!(
+            error_kinds(
+                "void main() { int i = 1.5; bool b; b = 1; x = 1; float x; void v; int w = v; }"
+            ),
+            [
+                mismatch(Type::Int, Type::Float),
+                mismatch(Type::Bool, Type::Int),
+                mismatch(Type::Float, Type::Int),
+                TypeErrorKind::VoidVariable("v".to_string()),
+                mismatch(Type::Int, Type::Void),
+            ]
+        );
+    }
+
+    #[test]
+    fn globals() {
+        let program = C1Parser::parse_program_in(
+            "int count = 0; float scale = 2; bool flag;
+             void f(float count) { x = count; }
+             void main() { count = count + 1; scale = count; float flag = 1; y = flag; }",
+            Dialect::C1,
+        )
+        .unwrap();
+        let types = check(&program).unwrap();
+        assert_eq!(types.global("count"), Some(Type::Int));
+        assert_eq!(types.global("scale"), Some(Type::Float));
+        assert_eq!(types.variable("f", "count"), Some(Type::Float));
+        assert_eq!(types.variable("f", "x"), Some(Type::Float));
+        assert_eq!(types.variable("main", "count"), Some(Type::Int));
+        assert_eq!(types.variable("main", "flag"), Some(Type::Float));
+        // Assignments to globals do not create local variables
+        assert_eq!(
+            types.function("main").unwrap().variables,
+            [
+                ("flag".to_string(), Type::Float),
+                ("y".to_string(), Type::Float)
+            ]
+        );
+
+        assert_eq!(
+            error_kinds(
+                "int i = 1.5; float f = 1; bool b = 0; void v;
+                 void main() { i = true; b = f; }"
+            ),
+            [
+                mismatch(Type::Int, Type::Float),
+                mismatch(Type::Bool, Type::Int),
+                TypeErrorKind::VoidVariable("v".to_string()),
+                mismatch(Type::Int, Type::Bool),
+                mismatch(Type::Bool, Type::Float),
+            ]
+        );
+    }
+
+    #[test]
+    fn operand_errors() {
+        assert_eq!(
+            error_kinds("void main() { x = 1 + true; y = false * 2.0; }"),
+            [
+                invalid_operand("+", Type::Bool),
+                invalid_operand("*", Type::Bool)
+            ]
+        );
+        assert_eq!(
+            error_kinds("void main() { x = 1 && true; y = -false; z = true < false; }"),
+            [
+                invalid_operand("&&", Type::Int),
+                invalid_operand("-", Type::Bool),
+                invalid_operand("<", Type::Bool),
+            ]
+        );
+        assert_eq!(
+            error_kinds("void main() { x = !1; y = +true; z = 1.5 % 2; w = 5 % 2.0; }"),
+            [
+                invalid_operand("!", Type::Int),
+                invalid_operand("+", Type::Bool),
+                invalid_operand("%", Type::Float),
+                invalid_operand("%", Type::Float),
+            ]
+        );
+        assert_eq!(
+            error_kinds("void f() {} void main() { x = 1 == true; printf(f()); y = f(); }"),
+            [
+                TypeErrorKind::MismatchedOperands {
+                    operator: "==".to_string(),
+                    lhs: Type::Int,
+                    rhs: Type::Bool
+                },
+                invalid_operand("printf", Type::Void),
+                invalid_operand("=", Type::Void),
+            ]
+        );
+    }
+
+    #[test]
+    fn statement_errors() {
+        assert_eq!(
+            error_kinds(
+                "void main() { if (1) printf(1); while (2.0) {} for (; 3;) {}
+                 x = 1; x = 1.5; b = true; b = 1; }"
+            ),
+            [
+                mismatch(Type::Bool, Type::Int),
+                mismatch(Type::Bool, Type::Float),
+                mismatch(Type::Bool, Type::Int),
+                mismatch(Type::Int, Type::Float),
+                mismatch(Type::Bool, Type::Int),
+            ]
+        );
+        assert_eq!(
+            error_kinds("void main() { printf(x); x = 1; }"),
+            [TypeErrorKind::UndefinedVariable("x".to_string())]
+        );
+        // Undefined variables cause no follow-up errors
+        assert_eq!(
+            error_kinds("void main() { if (x + 1 > 2) { y = x * 2; } }"),
+            [
+                TypeErrorKind::UndefinedVariable("x".to_string()),
+                TypeErrorKind::UndefinedVariable("x".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn return_errors() {
+        let errors = check_text("int main() {\n  return 3.14159;\n}").unwrap_err();
+        assert_eq!(errors[0].kind, mismatch(Type::Int, Type::Float));
+        assert_eq!(
+            (errors[0].location.line, errors[0].location.column),
+            (2, 10)
+        );
+        assert_eq!(
+            errors[0].to_string(),
+            "Mismatched types, expected int but found float at line 2"
+        );
+
+        assert_eq!(
+            error_kinds("void f() { return 1; } int g() { return; } float h() { return 1; }"),
+            [
+                mismatch(Type::Void, Type::Int),
+                mismatch(Type::Int, Type::Void)
+            ]
+        );
+        assert_eq!(
+            error_kinds(
+                "int f() { if (true) return 1; } bool g() { { return true; } }
+                 int h() { do { return 1; } while (true); }
+                 int i() { if (true) return 1; else if (false) return 2; else { return 3; } }
+                 int j() { while (true) { if (false) return 1; } }
+                 int k() { do {} while (true); }
+                 int l() { while (!false) { printf(1); } }
+                 int m() { for (i = 0; ; i = i + 1) { if (i > 3) return i; } }
+                 int n() { for (;;) {} }
+                 int o() { for (; true;) { printf(1); } }
+                 int p() { for (i = 0; i < 3; i = i + 1) { return i; } }"
+            ),
+            [
+                TypeErrorKind::MissingReturn {
+                    function: "f".to_string(),
+                    expected: Type::Int
+                },
+                // Only the literal true is known to be true
+                TypeErrorKind::MissingReturn {
+                    function: "l".to_string(),
+                    expected: Type::Int
+                },
+                TypeErrorKind::MissingReturn {
+                    function: "p".to_string(),
+                    expected: Type::Int
+                }
+            ]
+        );
+    }
+}
@@ -0,0 +1,467 @@
+//! Stack-based virtual machine for [bytecode modules](crate::bytecode).
+//!
+//! The virtual machine runs the `main` function of a [`Module`] with the semantics of the
+//! [interpreter](crate::interpreter): values, operators, conversions, the output of `printf` and
+//! the runtime errors are the same, so a program prints the same output and returns the same exit
+//! value with both. Calls do not recurse on the native stack, so the call depth is only limited to
+//! [`MAX_FRAMES`] instead of the interpreter's much lower
+//! [`MAX_CALL_DEPTH`](crate::interpreter::MAX_CALL_DEPTH).
+//!
+//! Programs that [`compile`](crate::bytecode::compile) rejects never get here, even if the
+//! interpreter would not reach the offending code: `printf(false && undefined());` prints `false`
+//! in the interpreter, but calling a function that does not exist is a compile error wherever the
+//! call is.
+//!
+//! ```
+//! use cb_3::bytecode::compile;
+//! use cb_3::interpreter::Value;
+//! use cb_3::vm::Vm;
+//! use cb_3::C1Parser;
+//!
+//! let text = "int main() { x = 6; printf(x * 7); return 1; }";
+//! let module = compile(&C1Parser::parse_program(text).unwrap()).unwrap();
+//! let mut output = Vec::new();
+//! let exit_value = Vm::new(&module, &mut output).run().unwrap();
+//! assert_eq!(exit_value, Value::Int(1));
+//! assert_eq!(output, b"42\n");
+//! ```
+
+use crate::ast::{BinaryOperator, Location, Type};
+use crate::bytecode::{Instruction, Module};
+use crate::interpreter::{
+    assigned_value, binary, convert, logical, unary, Evaluated, RuntimeError, RuntimeErrorKind,
+    Value,
+};
+use std::io::Write;
+
+/// Maximum number of nested function calls before a [`RuntimeErrorKind::StackOverflow`]
+pub const MAX_FRAMES: usize = 1_000_000;
+
+/// An invocation of a function
+struct Frame {
+    function: usize,
+    /// Index of the next instruction
+    ip: usize,
+    /// Index of the first local variable of the invocation in the local variables of the machine
+    base: usize,
+}
+
+/// Executes a module, writing the output of `printf` to `output`
+pub struct Vm<'a, W: Write> {
+    module: &'a Module,
+    stack: Vec<Value>,
+    /// The local variables of all invocations, `None` until they are assigned
+    locals: Vec<Option<Value>>,
+    frames: Vec<Frame>,
+    globals: Vec<Value>,
+    output: W,
+}
+
+impl<'a, W: Write> Vm<'a, W> {
+    pub fn new(module: &'a Module, output: W) -> Vm<'a, W> {
+        Vm {
+            module,
+            stack: Vec::new(),
+            locals: Vec::new(),
+            frames: Vec::new(),
+            globals: Vec::new(),
+            output,
+        }
+    }
+
+    /// Run the `main` function and return its return value, which is the exit value of the program
+    pub fn run(&mut self) -> Evaluated<Value> {
+        self.stack.clear();
+        self.locals.clear();
+        self.frames.clear();
+        self.globals = self.module.globals.iter().map(|g| g.value).collect();
+        let main = self.module.main.ok_or(RuntimeError {
+            kind: RuntimeErrorKind::MissingMain,
+            location: None,
+        })?;
+        // Parameters of main are never assigned
+        self.push_frame(main, 0);
+        self.execute()
+    }
+
+    /// Return the writer the output is written to
+    pub fn into_output(self) -> W {
+        self.output
+    }
+
+    /// Start an invocation of the function, whose first `arguments` parameters are on top of the
+    /// stack
+    fn push_frame(&mut self, function: usize, arguments: usize) {
+        let base = self.locals.len();
+        let first_argument = self.stack.len() - arguments;
+        self.locals
+            .extend(self.stack.drain(first_argument..).map(Some));
+        self.locals
+            .resize(base + self.module.functions[function].locals.len(), None);
+        self.frames.push(Frame {
+            function,
+            ip: 0,
+            base,
+        });
+    }
+
+    fn execute(&mut self) -> Evaluated<Value> {
+        let module = self.module;
+        loop {
+            let frame = self.frames.last_mut().expect("no function is running");
+            let function = &module.functions[frame.function];
+            let instruction = function.code[frame.ip];
+            let location = function.locations[frame.ip];
+            let base = frame.base;
+            frame.ip += 1;
+
+            match instruction {
+                Instruction::Constant(index) => self.stack.push(module.constants[index]),
+                Instruction::LoadLocal(slot) => {
+                    let value = self.locals[base + slot].ok_or_else(|| {
+                        RuntimeError::new(
+                            RuntimeErrorKind::UndefinedVariable(function.locals[slot].clone()),
+                            location,
+                        )
+                    })?;
+                    self.stack.push(value);
+                }
+                Instruction::StoreLocal(slot) => {
+                    let value = self.assigned(location)?;
+                    let variable = &mut self.locals[base + slot];
+                    *variable = Some(assigned_value(*variable, value));
+                }
+                Instruction::LoadGlobal(index) => self.stack.push(self.globals[index]),
+                Instruction::StoreGlobal(index) => {
+                    let value = self.assigned(location)?;
+                    let variable = &mut self.globals[index];
+                    *variable = assigned_value(Some(*variable), value);
+                }
+                Instruction::Declare { slot, ty } => {
+                    let value = self.pop();
+                    let value = convert(ty, value).ok_or_else(|| {
+                        RuntimeError::new(
+                            RuntimeErrorKind::InvalidInitializer {
+                                expected: ty,
+                                found: value.ty(),
+                            },
+                            location,
+                        )
+                    })?;
+                    self.locals[base + slot] = Some(value);
+                }
+                Instruction::Argument(ty) => {
+                    let value = self.pop();
+                    let value = convert(ty, value).ok_or_else(|| {
+                        RuntimeError::new(
+                            RuntimeErrorKind::InvalidArgument {
+                                expected: ty,
+                                found: value.ty(),
+                            },
+                            location,
+                        )
+                    })?;
+                    self.stack.push(value);
+                }
+                Instruction::Unary(op) => {
+                    let value = self.pop();
+                    self.stack.push(unary(op, value, location)?);
+                }
+                Instruction::Binary(op) => {
+                    let rhs = self.pop();
+                    let lhs = self.pop();
+                    self.stack.push(binary(op, lhs, rhs, location)?);
+                }
+                Instruction::ShortCircuit { op, target } => {
+                    let lhs = logical(op, self.pop(), location)?;
+                    if lhs == (op == BinaryOperator::Or) {
+                        self.stack.push(Value::Bool(lhs));
+                        self.jump(target);
+                    }
+                }
+                Instruction::Logical(op) => {
+                    let rhs = logical(op, self.pop(), location)?;
+                    self.stack.push(Value::Bool(rhs));
+                }
+                Instruction::Jump(target) => self.jump(target),
+                Instruction::JumpIfFalse(target) => {
+                    if !self.condition(location)? {
+                        self.jump(target);
+                    }
+                }
+                Instruction::JumpIfTrue(target) => {
+                    if self.condition(location)? {
+                        self.jump(target);
+                    }
+                }
+                Instruction::Call(index) => {
+                    if self.frames.len() == MAX_FRAMES {
+                        return Err(RuntimeError::new(
+                            RuntimeErrorKind::StackOverflow(MAX_FRAMES),
+                            location,
+                        ));
+                    }
+                    self.push_frame(index, module.functions[index].parameters.len());
+                }
+                Instruction::Return | Instruction::ReturnVoid => {
+                    let value = match instruction {
+                        Instruction::Return => self.pop(),
+                        _ => Value::Void,
+                    };
+                    let value = convert(function.return_type, value).ok_or_else(|| {
+                        RuntimeError::new(
+                            RuntimeErrorKind::InvalidReturnValue {
+                                expected: function.return_type,
+                                found: value.ty(),
+                            },
+                            location,
+                        )
+                    })?;
+                    self.locals.truncate(base);
+                    self.frames.pop();
+                    if self.frames.is_empty() {
+                        return Ok(value);
+                    }
+                    self.stack.push(value);
+                }
+                Instruction::Print => {
+                    let value = self.pop();
+                    if value == Value::Void {
+                        return Err(RuntimeError::invalid_operand(
+                            "printf",
+                            value.ty(),
+                            location,
+                        ));
+                    }
+                    writeln!(self.output, "{}", value).map_err(|error| {
+                        RuntimeError::new(RuntimeErrorKind::Output(error.to_string()), location)
+                    })?;
+                }
+                Instruction::PrintString(index) => {
+                    write!(self.output, "{}", module.strings[index]).map_err(|error| {
+                        RuntimeError::new(RuntimeErrorKind::Output(error.to_string()), location)
+                    })?;
+                }
+                Instruction::Pop => {
+                    self.pop();
+                }
+            }
+        }
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().expect("the stack is empty")
+    }
+
+    /// Pop a value that is assigned to a variable
+    fn assigned(&mut self, location: Location) -> Evaluated<Value> {
+        match self.pop() {
+            Value::Void => Err(RuntimeError::invalid_operand("=", Type::Void, location)),
+            value => Ok(value),
+        }
+    }
+
+    /// Pop the condition of an if statement or a loop
+    fn condition(&mut self, location: Location) -> Evaluated<bool> {
+        match self.pop() {
+            Value::Bool(value) => Ok(value),
+            value => Err(RuntimeError::new(
+                RuntimeErrorKind::InvalidCondition(value.ty()),
+                location,
+            )),
+        }
+    }
+
+    fn jump(&mut self, target: usize) {
+        self.frames.last_mut().expect("no function is running").ip = target;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bytecode::compile;
+    use crate::interpreter::{Interpreter, RuntimeErrorKind, Value};
+    use crate::vm::{Vm, MAX_FRAMES};
+    use crate::{C1Parser, Dialect};
+
+    /// Run the program with the virtual machine and check that the interpreter agrees on the exit
+    /// value or error and the output
+    fn run(text: &str) -> (Result<Value, RuntimeErrorKind>, String) {
+        let program = C1Parser::parse_program_in(text, Dialect::C1).unwrap();
+        let module = compile(&program).unwrap();
+        let mut output = Vec::new();
+        let result = Vm::new(&module, &mut output).run();
+        let result = (
+            result.map_err(|error| error.kind),
+            String::from_utf8(output).unwrap(),
+        );
+
+        let mut output = Vec::new();
+        let expected = Interpreter::new(&program, &mut output).run();
+        let expected = (
+            expected.map_err(|error| error.kind),
+            String::from_utf8(output).unwrap(),
+        );
+        assert_eq!(result, expected, "{}", text);
+        result
+    }
+
+    fn run_main(body: &str) -> Result<String, RuntimeErrorKind> {
+        match run(&format!("void main() {{ {} }}", body)) {
+            (Ok(_), output) => Ok(output),
+            (Err(error), _) => Err(error),
+        }
+    }
+
+    #[test]
+    fn run_example() {
+        let (result, output) = run(include_str!("../tests/data/beispiel.c-1"));
+        assert_eq!(result, Ok(Value::Void));
+        assert_eq!(output, "3\n17\n3.141590\n");
+    }
+
+    #[test]
+    fn exit_value() {
+        assert_eq!(run("int main() { return 3; }").0, Ok(Value::Int(3)));
+        assert_eq!(run("float main() { return 1; }").0, Ok(Value::Float(1.0)));
+        assert_eq!(run("void main() { return; }").0, Ok(Value::Void));
+    }
+
+    #[test]
+    fn expressions() {
+        assert_eq!(
+            run_main("printf(1 + 2 * 3); printf(-7 / 2); printf(-7 % 3); printf(7 / 2.0);"),
+            Ok("7\n-3\n-1\n3.500000\n".to_string())
+        );
+        assert_eq!(
+            run_main("x = 2147483647; printf(x + 1); printf(!(1 < 2) || (2 >= 1.5));"),
+            Ok("-2147483648\ntrue\n".to_string())
+        );
+        // The right operand is not evaluated, so the unassigned variable is never read
+        assert_eq!(
+            run_main("printf(false && y); printf(true || y); printf(true && !false);"),
+            Ok("false\ntrue\ntrue\n".to_string())
+        );
+        assert_eq!(
+            run_main("x = y = 2; f = 1.5; f = x; printf(f); printf(x = 3);"),
+            Ok("2.000000\n3\n".to_string())
+        );
+    }
+
+    #[test]
+    fn statements() {
+        assert_eq!(
+            run_main(
+                "for (i = 0; i < 3; i = i + 1) { if (i == 1) printf(10); else printf(i); }
+                 do i = i - 1; while (i > 0); printf(i);
+                 while (true) { int j; printf(j); return; }"
+            ),
+            Ok("0\n10\n2\n0\n0\n".to_string())
+        );
+        assert_eq!(
+            run_main(r#"printf("a\tb\n"); float f = 2; printf(f);"#),
+            Ok("a\tb\n2.000000\n".to_string())
+        );
+    }
+
+    #[test]
+    fn calls_and_globals() {
+        let text = "int count;
+            int fac(int n) { count = count + 1; if (n < 2) return 1; return n * fac(n - 1); }
+            float half(float x) { return x / 2; }
+            void main() { int count = 10; printf(fac(5)); printf(count); printf(half(3)); show(); }
+            void show() { printf(count); }";
+        assert_eq!(
+            run(text),
+            (Ok(Value::Void), "120\n10\n1.500000\n5\n".to_string())
+        );
+
+        // Globals are reset on every run
+        let program = C1Parser::parse_program_in(text, Dialect::C1).unwrap();
+        let module = compile(&program).unwrap();
+        let mut vm = Vm::new(&module, Vec::new());
+        vm.run().unwrap();
+        vm.run().unwrap();
+        assert_eq!(
+            vm.into_output().rsplit(|&byte| byte == b'\n').nth(1),
+            Some(&b"5"[..])
+        );
+    }
+
+    #[test]
+    fn deep_recursion() {
+        // Much deeper than the interpreter can go
+        let text = "int sum(int n) { if (n == 0) return 0; return n + sum(n - 1); }
+                    int main() { printf(sum(500000)); return sum(100); }";
+        let program = C1Parser::parse_program_in(text, Dialect::C1).unwrap();
+        let module = compile(&program).unwrap();
+        let mut output = Vec::new();
+        assert_eq!(Vm::new(&module, &mut output).run(), Ok(Value::Int(5050)));
+        assert_eq!(output, b"446198416\n");
+
+        let program = C1Parser::parse_program("void f() { f(); } void main() { f(); }").unwrap();
+        let error = Vm::new(&compile(&program).unwrap(), Vec::new())
+            .run()
+            .unwrap_err();
+        assert_eq!(error.kind, RuntimeErrorKind::StackOverflow(MAX_FRAMES));
+        assert_eq!(
+            error.to_string(),
+            "Exceeded the maximum call depth of 1000000 at line 1"
+        );
+    }
+
+    #[test]
+    fn runtime_errors() {
+        assert_eq!(
+            run_main("printf(x);"),
+            Err(RuntimeErrorKind::UndefinedVariable("x".to_string()))
+        );
+        assert_eq!(
+            run("int main(int a) { return a; }").0,
+            Err(RuntimeErrorKind::UndefinedVariable("a".to_string()))
+        );
+        assert_eq!(run("int f() {}").0, Err(RuntimeErrorKind::MissingMain));
+        assert_eq!(
+            run_main("printf(1 % 0);"),
+            Err(RuntimeErrorKind::DivisionByZero)
+        );
+        assert_eq!(
+            run_main("while (1) {}"),
+            Err(RuntimeErrorKind::InvalidCondition(crate::ast::Type::Int))
+        );
+        assert!(matches!(
+            run_main("printf(1 && true);"),
+            Err(RuntimeErrorKind::InvalidOperand { .. })
+        ));
+        assert_eq!(run_main("printf(true || 1);"), Ok("true\n".to_string()));
+        assert!(matches!(
+            run_main("printf(false || 1);"),
+            Err(RuntimeErrorKind::InvalidOperand { .. })
+        ));
+        assert!(matches!(
+            run("void f() {} void main() { x = f(); }").0,
+            Err(RuntimeErrorKind::InvalidOperand { .. })
+        ));
+        assert!(matches!(
+            run("void f() {} void main() { printf(f()); }").0,
+            Err(RuntimeErrorKind::InvalidOperand { .. })
+        ));
+        assert!(matches!(
+            run("void f(int a) {} void main() { f(true); }").0,
+            Err(RuntimeErrorKind::InvalidArgument { .. })
+        ));
+        assert!(matches!(
+            run_main("int x = 1.5;"),
+            Err(RuntimeErrorKind::InvalidInitializer { .. })
+        ));
+        assert!(matches!(
+            run("int f() {} void main() { f(); }").0,
+            Err(RuntimeErrorKind::InvalidReturnValue { .. })
+        ));
+
+        let program = C1Parser::parse_program("void main() {\n  printf(1 / 0);\n}").unwrap();
+        let module = compile(&program).unwrap();
+        let error = Vm::new(&module, Vec::new()).run().unwrap_err();
+        assert_eq!(error.to_string(), "Division by zero at line 2");
+    }
+}
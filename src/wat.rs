@@ -0,0 +1,741 @@
+//! WebAssembly code generator for C(-1) programs.
+//!
+//! [`emit`] translates a checked [`Program`] into a module in the WebAssembly text format, which
+//! behaves like the [interpreter](crate::interpreter) when the host provides `printf`:
+//! - `int` and `bool` values are `i32`, `float` values are `f32`. `int` arithmetic wraps around,
+//!   dividing by zero traps.
+//! - Every function definition becomes a wasm function of the same name, with a local for each
+//!   variable it declares or assigns. Local variables start as zero. `main` is exported as `main`.
+//! - Global variables become mutable wasm globals of the same name.
+//! - Statements become structured control flow: `if` statements are `if` blocks, loops are `loop`
+//!   blocks inside `block`s that they leave with `br_if`.
+//! - `printf` calls one of the functions `print_int`, `print_float`, `print_bool` and
+//!   `print_string` that the module imports from `env`. Each prints its argument the way the
+//!   interpreter does, followed by a newline except for strings. Strings are stored in the
+//!   exported memory `memory`, `print_string` takes their offset and length in bytes. Only the
+//!   functions the program uses are imported.
+//!
+//! Reading a variable before its first assignment reads zero instead of being an error, and the
+//! depth of nested calls is only limited by the host.
+//!
+//! ```
+//! use cb_3::typecheck::check;
+//! use cb_3::wat::emit;
+//! use cb_3::C1Parser;
+//!
+//! let program = C1Parser::parse_program("void main() { x = 6; printf(x * 7); }").unwrap();
+//! let types = check(&program).unwrap();
+//! assert_eq!(
+//!     emit(&program, &types),
+//!     "\
+//! (module
+//!   (import \"env\" \"print_int\" (func $print_int (param i32)))
+//!   (func $main
+//!     (local $x i32)
+//!     i32.const 6
+//!     local.set $x
+//!     local.get $x
+//!     i32.const 7
+//!     i32.mul
+//!     call $print_int
+//!   )
+//!   (export \"main\" (func $main))
+//! )
+//! "
+//! );
+//! ```
+
+use crate::ast::{
+    BinaryOperator, Expr, ExprKind, FunctionCall, FunctionDefinition, Literal, PrintfArgument,
+    Program, Statement, StatementKind, Type, UnaryOperator,
+};
+use crate::typecheck::TypeInfo;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+
+/// The size of a page of wasm memory
+const PAGE_SIZE: usize = 65536;
+
+/// Host functions that implement `printf` for each type of argument, in the order they are imported
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Print {
+    Int,
+    Float,
+    Bool,
+    String,
+}
+
+impl Print {
+    fn declaration(self) -> &'static str {
+        match self {
+            Print::Int => "(import \"env\" \"print_int\" (func $print_int (param i32)))",
+            Print::Float => "(import \"env\" \"print_float\" (func $print_float (param f32)))",
+            Print::Bool => "(import \"env\" \"print_bool\" (func $print_bool (param i32)))",
+            Print::String => {
+                "(import \"env\" \"print_string\" (func $print_string (param i32 i32)))"
+            }
+        }
+    }
+}
+
+/// Divide two `int`s. `i32.div_s` traps when dividing the smallest `int` by -1, so dividing by -1
+/// is a negation.
+const DIVIDE: &str = "  (func $c1_div (param $lhs i32) (param $rhs i32) (result i32)
+    local.get $rhs
+    i32.const -1
+    i32.eq
+    if (result i32)
+      i32.const 0
+      local.get $lhs
+      i32.sub
+    else
+      local.get $lhs
+      local.get $rhs
+      i32.div_s
+    end
+  )
+";
+
+/// Translate the program into a WebAssembly text module.
+///
+/// The program has to pass [`semantic::analyze`](crate::semantic::analyze) and
+/// [`typecheck::check`](crate::typecheck::check), which returned the types.
+pub fn emit(program: &Program, types: &TypeInfo) -> String {
+    let mut emitter = Emitter {
+        types,
+        functions: program
+            .functions
+            .iter()
+            .map(|function| (function.name.name.as_str(), function))
+            .collect(),
+        prints: BTreeSet::new(),
+        divides: false,
+        data: Vec::new(),
+        labels: 0,
+        function: "",
+        return_type: Type::Void,
+        indent: 0,
+        code: String::new(),
+    };
+    let functions: Vec<_> = program
+        .functions
+        .iter()
+        .map(|function| emitter.function(function))
+        .collect();
+
+    let mut module = String::from("(module\n");
+    for print in &emitter.prints {
+        writeln!(module, "  {}", print.declaration()).unwrap();
+    }
+    // print_string reads the memory even if all strings are empty
+    if emitter.prints.contains(&Print::String) {
+        let pages = emitter.data.len().div_ceil(PAGE_SIZE);
+        writeln!(module, "  (memory (export \"memory\") {})", pages).unwrap();
+    }
+    if !emitter.data.is_empty() {
+        writeln!(
+            module,
+            "  (data (i32.const 0) {})",
+            string_literal(&emitter.data)
+        )
+        .unwrap();
+    }
+    for global in &program.globals {
+        let value = match global.value {
+            Some(value) => constant(global.ty, value),
+            None => constant(global.ty, Literal::Int(0)),
+        };
+        writeln!(
+            module,
+            "  (global ${} (mut {}) ({}))",
+            global.name.name,
+            value_type(global.ty),
+            value
+        )
+        .unwrap();
+    }
+    if emitter.divides {
+        module.push_str(DIVIDE);
+    }
+    for function in functions {
+        module.push_str(&function);
+    }
+    if program
+        .functions
+        .iter()
+        .any(|function| function.name.name == "main")
+    {
+        module.push_str("  (export \"main\" (func $main))\n");
+    }
+    module.push_str(")\n");
+    module
+}
+
+fn value_type(ty: Type) -> &'static str {
+    match ty {
+        Type::Float => "f32",
+        _ => "i32",
+    }
+}
+
+/// Return the instruction that pushes the literal converted to the type of a variable
+fn constant(ty: Type, value: Literal) -> String {
+    let value = match (ty, value) {
+        (Type::Float, Literal::Int(value)) => value as f32,
+        (_, Literal::Float(value)) => value,
+        (_, Literal::Int(value)) => return format!("i32.const {}", value),
+        (_, Literal::Bool(value)) => return format!("i32.const {}", value as i32),
+    };
+    if value.is_finite() {
+        // The shortest representation that reads back as the same float
+        format!("f32.const {:?}", value)
+    } else {
+        "f32.const inf".to_string()
+    }
+}
+
+/// Return the bytes as a string literal of the text format
+fn string_literal(bytes: &[u8]) -> String {
+    let mut literal = String::from("\"");
+    for &byte in bytes {
+        match byte {
+            b'"' | b'\\' => write!(literal, "\\{}", byte as char).unwrap(),
+            b' '..=b'~' => literal.push(byte as char),
+            _ => write!(literal, "\\{:02x}", byte).unwrap(),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+struct Emitter<'a> {
+    types: &'a TypeInfo,
+    functions: HashMap<&'a str, &'a FunctionDefinition>,
+    prints: BTreeSet<Print>,
+    /// Whether the program divides `int`s
+    divides: bool,
+    /// The content of the memory: the strings printed by the program
+    data: Vec<u8>,
+    /// The number of loops emitted so far, which number their labels
+    labels: usize,
+    /// Name of the current function
+    function: &'a str,
+    return_type: Type,
+    indent: usize,
+    code: String,
+}
+
+impl<'a> Emitter<'a> {
+    fn function(&mut self, function: &'a FunctionDefinition) -> String {
+        let types = self
+            .types
+            .function(&function.name.name)
+            .expect("function of a checked program");
+        self.function = &function.name.name;
+        self.return_type = function.return_type;
+
+        self.code = format!("  (func ${}", function.name.name);
+        for (name, ty) in &types.parameters {
+            write!(self.code, " (param ${} {})", name, value_type(*ty)).unwrap();
+        }
+        if function.return_type != Type::Void {
+            write!(self.code, " (result {})", value_type(function.return_type)).unwrap();
+        }
+        self.code.push('\n');
+        for (name, ty) in &types.variables {
+            writeln!(self.code, "    (local ${} {})", name, value_type(*ty)).unwrap();
+        }
+
+        self.indent = 2;
+        for statement in &function.body {
+            self.statement(statement);
+        }
+        // The end of a function with a return value is never reached, but it has to be valid
+        let returns = matches!(
+            function.body.last(),
+            Some(Statement {
+                kind: StatementKind::Return(_),
+                ..
+            })
+        );
+        if function.return_type != Type::Void && !returns {
+            self.instruction("unreachable");
+        }
+        self.code.push_str("  )\n");
+        std::mem::take(&mut self.code)
+    }
+
+    fn instruction(&mut self, instruction: &str) {
+        for _ in 0..self.indent {
+            self.code.push_str("  ");
+        }
+        self.code.push_str(instruction);
+        self.code.push('\n');
+    }
+
+    /// Return whether a name refers to a local variable of the current function
+    fn is_local(&self, name: &str) -> bool {
+        self.types
+            .function(self.function)
+            .and_then(|types| types.variable(name))
+            .is_some()
+    }
+
+    fn statement(&mut self, statement: &'a Statement) {
+        match &statement.kind {
+            StatementKind::Block(statements) => {
+                for statement in statements {
+                    self.statement(statement);
+                }
+            }
+            StatementKind::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                self.expr(condition);
+                self.instruction("if");
+                self.indented(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.instruction("else");
+                    self.indented(else_branch);
+                }
+                self.instruction("end");
+            }
+            StatementKind::While { condition, body } => {
+                let (exit, start) = self.begin_loop();
+                self.exit_unless(condition, &exit);
+                self.statement(body);
+                self.instruction(&format!("br {}", start));
+                self.end_loop();
+            }
+            StatementKind::DoWhile { body, condition } => {
+                self.labels += 1;
+                let start = format!("$loop{}", self.labels);
+                self.instruction(&format!("loop {}", start));
+                self.indented(body);
+                self.indent += 1;
+                self.expr(condition);
+                self.instruction(&format!("br_if {}", start));
+                self.indent -= 1;
+                self.instruction("end");
+            }
+            StatementKind::For {
+                init,
+                condition,
+                update,
+                body,
+            } => {
+                if let Some(init) = init {
+                    self.statement(init);
+                }
+                let (exit, start) = self.begin_loop();
+                if let Some(condition) = condition {
+                    self.exit_unless(condition, &exit);
+                }
+                self.statement(body);
+                if let Some(update) = update {
+                    self.statement(update);
+                }
+                self.instruction(&format!("br {}", start));
+                self.end_loop();
+            }
+            StatementKind::Declaration { ty, name, value } => {
+                match value {
+                    Some(value) => {
+                        self.expr(value);
+                        self.convert(self.expr_type(value), *ty);
+                    }
+                    None => self.instruction(&constant(*ty, Literal::Int(0))),
+                }
+                self.instruction(&format!("local.set ${}", name.name));
+            }
+            StatementKind::Return(value) => {
+                if let Some(value) = value {
+                    self.expr(value);
+                    self.convert(self.expr_type(value), self.return_type);
+                }
+                self.instruction("return");
+            }
+            StatementKind::Printf(PrintfArgument::String(text)) => {
+                self.prints.insert(Print::String);
+                self.instruction(&format!("i32.const {}", self.data.len()));
+                self.instruction(&format!("i32.const {}", text.len()));
+                self.instruction("call $print_string");
+                self.data.extend(text.bytes());
+            }
+            StatementKind::Printf(PrintfArgument::Value(value)) => {
+                self.expr(value);
+                let (print, name) = match self.expr_type(value) {
+                    Type::Int => (Print::Int, "$print_int"),
+                    Type::Float => (Print::Float, "$print_float"),
+                    _ => (Print::Bool, "$print_bool"),
+                };
+                self.prints.insert(print);
+                self.instruction(&format!("call {}", name));
+            }
+            StatementKind::Assignment { target, value } => {
+                self.expr(value);
+                self.store(&target.name, self.expr_type(value), false);
+            }
+            StatementKind::Call(call) => {
+                self.call(call);
+                if self.functions[call.name.name.as_str()].return_type != Type::Void {
+                    self.instruction("drop");
+                }
+            }
+        }
+    }
+
+    /// Emit a statement nested in a block
+    fn indented(&mut self, statement: &'a Statement) {
+        self.indent += 1;
+        self.statement(statement);
+        self.indent -= 1;
+    }
+
+    /// Open a loop inside a block and return the labels of the block and the loop. Branching to
+    /// the block leaves the loop, branching to the loop starts the next iteration.
+    fn begin_loop(&mut self) -> (String, String) {
+        self.labels += 1;
+        let exit = format!("$exit{}", self.labels);
+        let start = format!("$loop{}", self.labels);
+        self.instruction(&format!("block {}", exit));
+        self.indent += 1;
+        self.instruction(&format!("loop {}", start));
+        self.indent += 1;
+        (exit, start)
+    }
+
+    fn end_loop(&mut self) {
+        self.indent -= 1;
+        self.instruction("end");
+        self.indent -= 1;
+        self.instruction("end");
+    }
+
+    /// Leave the block with the given label if the condition is false
+    fn exit_unless(&mut self, condition: &Expr, exit: &str) {
+        self.expr(condition);
+        self.instruction("i32.eqz");
+        self.instruction(&format!("br_if {}", exit));
+    }
+
+    /// Assign the value of the given type on the stack to a variable, converting it to the type of
+    /// the variable. If `keep` is true, the value remains on the stack.
+    fn store(&mut self, name: &str, ty: Type, keep: bool) {
+        let variable_type = self
+            .types
+            .variable(self.function, name)
+            .expect("variable of a checked program");
+        self.convert(ty, variable_type);
+        match (self.is_local(name), keep) {
+            (true, false) => self.instruction(&format!("local.set ${}", name)),
+            (true, true) => self.instruction(&format!("local.tee ${}", name)),
+            (false, keep) => {
+                self.instruction(&format!("global.set ${}", name));
+                if keep {
+                    self.instruction(&format!("global.get ${}", name));
+                }
+            }
+        }
+    }
+
+    /// Convert the value on the stack to the type it is assigned to
+    fn convert(&mut self, from: Type, to: Type) {
+        if from == Type::Int && to == Type::Float {
+            self.instruction("f32.convert_i32_s");
+        }
+    }
+
+    fn expr_type(&self, expr: &Expr) -> Type {
+        self.types.expr_type(self.function, expr)
+    }
+
+    /// Push the value of the expression
+    fn expr(&mut self, expr: &Expr) {
+        match &expr.kind {
+            ExprKind::Literal(value) => {
+                let ty = self.expr_type(expr);
+                self.instruction(&constant(ty, *value));
+            }
+            ExprKind::Variable(name) if self.is_local(&name.name) => {
+                self.instruction(&format!("local.get ${}", name.name))
+            }
+            ExprKind::Variable(name) => self.instruction(&format!("global.get ${}", name.name)),
+            ExprKind::Call(call) => self.call(call),
+            ExprKind::Assignment { target, value } => {
+                self.expr(value);
+                self.store(&target.name, self.expr_type(value), true);
+            }
+            ExprKind::Unary { op, operand } => match (op, self.expr_type(operand)) {
+                (UnaryOperator::Plus, _) => self.expr(operand),
+                (UnaryOperator::Minus, Type::Float) => {
+                    self.expr(operand);
+                    self.instruction("f32.neg");
+                }
+                (UnaryOperator::Minus, _) => {
+                    self.instruction("i32.const 0");
+                    self.expr(operand);
+                    self.instruction("i32.sub");
+                }
+                (UnaryOperator::Not, _) => {
+                    self.expr(operand);
+                    self.instruction("i32.eqz");
+                }
+            },
+            ExprKind::Binary {
+                op: op @ (BinaryOperator::And | BinaryOperator::Or),
+                lhs,
+                rhs,
+            } => {
+                // The left operand decides the result if it is false for && and true for ||
+                self.expr(lhs);
+                self.instruction("if (result i32)");
+                self.indent += 1;
+                if *op == BinaryOperator::And {
+                    self.expr(rhs);
+                } else {
+                    self.instruction("i32.const 1");
+                }
+                self.indent -= 1;
+                self.instruction("else");
+                self.indent += 1;
+                if *op == BinaryOperator::And {
+                    self.instruction("i32.const 0");
+                } else {
+                    self.expr(rhs);
+                }
+                self.indent -= 1;
+                self.instruction("end");
+            }
+            ExprKind::Binary { op, lhs, rhs } => {
+                let (lhs_type, rhs_type) = (self.expr_type(lhs), self.expr_type(rhs));
+                let float = lhs_type == Type::Float || rhs_type == Type::Float;
+                let operand_type = if float { Type::Float } else { Type::Int };
+                self.expr(lhs);
+                self.convert(lhs_type, operand_type);
+                self.expr(rhs);
+                self.convert(rhs_type, operand_type);
+                let instruction = match (op, float) {
+                    (BinaryOperator::Divide, false) => {
+                        self.divides = true;
+                        "call $c1_div"
+                    }
+                    (BinaryOperator::Add, false) => "i32.add",
+                    (BinaryOperator::Subtract, false) => "i32.sub",
+                    (BinaryOperator::Multiply, false) => "i32.mul",
+                    (BinaryOperator::Modulo, _) => "i32.rem_s",
+                    (BinaryOperator::Equal, false) => "i32.eq",
+                    (BinaryOperator::NotEqual, false) => "i32.ne",
+                    (BinaryOperator::Less, false) => "i32.lt_s",
+                    (BinaryOperator::LessEqual, false) => "i32.le_s",
+                    (BinaryOperator::Greater, false) => "i32.gt_s",
+                    (BinaryOperator::GreaterEqual, false) => "i32.ge_s",
+                    (BinaryOperator::Add, true) => "f32.add",
+                    (BinaryOperator::Subtract, true) => "f32.sub",
+                    (BinaryOperator::Multiply, true) => "f32.mul",
+                    (BinaryOperator::Divide, true) => "f32.div",
+                    (BinaryOperator::Equal, true) => "f32.eq",
+                    (BinaryOperator::NotEqual, true) => "f32.ne",
+                    (BinaryOperator::Less, true) => "f32.lt",
+                    (BinaryOperator::LessEqual, true) => "f32.le",
+                    (BinaryOperator::Greater, true) => "f32.gt",
+                    (BinaryOperator::GreaterEqual, true) => "f32.ge",
+                    (BinaryOperator::And | BinaryOperator::Or, _) => {
+                        unreachable!("logical operator")
+                    }
+                };
+                self.instruction(instruction);
+            }
+        }
+    }
+
+    fn call(&mut self, call: &FunctionCall) {
+        let function = self.functions[call.name.name.as_str()];
+        for (argument, parameter) in call.arguments.iter().zip(&function.parameters) {
+            self.expr(argument);
+            self.convert(self.expr_type(argument), parameter.ty);
+        }
+        self.instruction(&format!("call ${}", call.name.name));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::emit;
+    use crate::semantic::analyze;
+    use crate::typecheck::check;
+    use crate::{C1Parser, Dialect};
+
+    fn emit_text(text: &str) -> String {
+        let program = C1Parser::parse_program_in(text, Dialect::C1).unwrap();
+        analyze(&program).unwrap();
+        let types = check(&program).unwrap();
+        emit(&program, &types)
+    }
+
+    #[test]
+    fn operators() {
+        let code = emit_text(
+            "bool f(int a, float b) { return (-a / 2 < b) && !(a % 3 == 0); }
+             void main() { f(1, 2); }",
+        );
+        assert!(code.contains("  (func $c1_div (param $lhs i32) (param $rhs i32) (result i32)\n"));
+        assert!(code.contains(
+            "  (func $f (param $a i32) (param $b f32) (result i32)
+    i32.const 0
+    local.get $a
+    i32.sub
+    i32.const 2
+    call $c1_div
+    f32.convert_i32_s
+    local.get $b
+    f32.lt
+    if (result i32)
+      local.get $a
+      i32.const 3
+      i32.rem_s
+      i32.const 0
+      i32.eq
+      i32.eqz
+    else
+      i32.const 0
+    end
+    return
+  )
+"
+        ));
+        // The result of a call statement is dropped, the int argument converted
+        assert!(code.contains(
+            "    i32.const 1
+    i32.const 2
+    f32.convert_i32_s
+    call $f
+    drop
+"
+        ));
+    }
+
+    #[test]
+    fn control_flow() {
+        let code = emit_text(
+            "int f(int n) {
+                 for (i = 0; i < n; i = i + 1) { if (i == 3) { return i; } }
+                 do { n = n - 1; } while (n > 0);
+                 if (n < 0) { return 1; } else { return (x = 2) + x; }
+             }
+             void main() { f(5); }",
+        );
+        assert_eq!(
+            code,
+            "(module
+  (func $f (param $n i32) (result i32)
+    (local $i i32)
+    (local $x i32)
+    i32.const 0
+    local.set $i
+    block $exit1
+      loop $loop1
+        local.get $i
+        local.get $n
+        i32.lt_s
+        i32.eqz
+        br_if $exit1
+        local.get $i
+        i32.const 3
+        i32.eq
+        if
+          local.get $i
+          return
+        end
+        local.get $i
+        i32.const 1
+        i32.add
+        local.set $i
+        br $loop1
+      end
+    end
+    loop $loop2
+      local.get $n
+      i32.const 1
+      i32.sub
+      local.set $n
+      local.get $n
+      i32.const 0
+      i32.gt_s
+      br_if $loop2
+    end
+    local.get $n
+    i32.const 0
+    i32.lt_s
+    if
+      i32.const 1
+      return
+    else
+      i32.const 2
+      local.tee $x
+      local.get $x
+      i32.add
+      return
+    end
+    unreachable
+  )
+"
+            .to_string()
+                + &code[code.find("  (func $main").unwrap()..]
+        );
+    }
+
+    #[test]
+    fn module_layout() {
+        let code = emit_text(
+            "float scale = 2; bool verbose = true; int count;
+             void show(float x) { printf(\"x: \\\"\\0\\n\"); printf(x * scale); }
+             void main() { count = count + 1; show(count); printf(verbose); printf(\"end\"); }",
+        );
+        assert_eq!(
+            code,
+            "(module
+  (import \"env\" \"print_float\" (func $print_float (param f32)))
+  (import \"env\" \"print_bool\" (func $print_bool (param i32)))
+  (import \"env\" \"print_string\" (func $print_string (param i32 i32)))
+  (memory (export \"memory\") 1)
+  (data (i32.const 0) \"x: \\\"\\00\\0aend\")
+  (global $scale (mut f32) (f32.const 2.0))
+  (global $verbose (mut i32) (i32.const 1))
+  (global $count (mut i32) (i32.const 0))
+  (func $show (param $x f32)
+    i32.const 0
+    i32.const 6
+    call $print_string
+    local.get $x
+    global.get $scale
+    f32.mul
+    call $print_float
+  )
+  (func $main
+    global.get $count
+    i32.const 1
+    i32.add
+    global.set $count
+    global.get $count
+    f32.convert_i32_s
+    call $show
+    global.get $verbose
+    call $print_bool
+    i32.const 6
+    i32.const 3
+    call $print_string
+  )
+  (export \"main\" (func $main))
+)
+"
+        );
+        // Printing only empty strings needs the memory but no data
+        let code = emit_text("void main() { printf(\"\"); }");
+        assert!(
+            code.contains("(param i32 i32)))\n  (memory (export \"memory\") 0)\n  (func $main\n")
+        );
+    }
+}
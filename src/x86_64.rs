@@ -0,0 +1,976 @@
+//! x86-64 code generator for C(-1) programs.
+//!
+//! [`emit`] translates a checked [`Program`] into assembly in AT&T syntax for the GNU assembler.
+//! The result behaves like the [interpreter](crate::interpreter) when it is assembled and linked
+//! against the C library on Linux, e.g. with `cc -o program program.s`:
+//! - Functions follow the System V ABI. `int` and `bool` values are passed and returned in the
+//!   general purpose registers, `float` values in the SSE registers, further arguments on the
+//!   stack. Every function is called `fn_` followed by its name, the C1 `main` function is called
+//!   by a C `main` function, which returns its value if it is an `int` and 0 otherwise.
+//! - Global variables are `var_` followed by their name. The parameters and variables of a
+//!   function live in its stack frame, variables start as zero.
+//! - Expressions are evaluated from left to right into `%eax` for `int` and `bool` values and
+//!   `%xmm0` for `float` values. Left operands and arguments are pushed onto the stack while the
+//!   next operand is evaluated.
+//! - `int` arithmetic wraps around. Dividing by zero prints the runtime error to `stderr` and
+//!   exits with status 1, the remainder of dividing by -1 is 0.
+//! - `printf` is implemented by small runtime functions, which call `printf`, `puts` and `fwrite`
+//!   of the C library and are only emitted if the program needs them.
+//!
+//! Reading a variable before its first assignment reads zero instead of being an error, and the
+//! depth of nested calls is not limited.
+//!
+//! ```
+//! use cb_3::typecheck::check;
+//! use cb_3::x86_64::emit;
+//! use cb_3::C1Parser;
+//!
+//! let program = C1Parser::parse_program("int main() { x = 6; return x * 7; }").unwrap();
+//! let types = check(&program).unwrap();
+//! assert_eq!(
+//!     emit(&program, &types),
+//!     "    .text
+//!
+//! fn_main:
+//!     pushq %rbp
+//!     movq %rsp, %rbp
+//!     subq $16, %rsp
+//!     movl $0, -8(%rbp)
+//!     movl $6, %eax
+//!     movl %eax, -8(%rbp)
+//!     movl -8(%rbp), %eax
+//!     pushq %rax
+//!     movl $7, %eax
+//!     movl %eax, %ecx
+//!     popq %rax
+//!     imull %ecx, %eax
+//!     leave
+//!     ret
+//!
+//!     .globl main
+//! main:
+//!     subq $8, %rsp
+//!     call fn_main
+//!     addq $8, %rsp
+//!     ret
+//!
+//!     .section .note.GNU-stack,\"\",@progbits
+//! "
+//! );
+//! ```
+
+use crate::ast::{
+    BinaryOperator, Expr, ExprKind, FunctionCall, FunctionDefinition, Literal, PrintfArgument,
+    Program, Statement, StatementKind, Type, UnaryOperator,
+};
+use crate::typecheck::TypeInfo;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+
+/// The registers for the first `int` and `bool` arguments
+const INT_REGISTERS: [&str; 6] = ["%edi", "%esi", "%edx", "%ecx", "%r8d", "%r9d"];
+
+/// The number of `float` arguments passed in `%xmm0` to `%xmm7`
+const FLOAT_REGISTERS: usize = 8;
+
+/// Runtime functions of the generated code. They align the stack before calling into the C
+/// library and are defined in the order of the variants.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Helper {
+    DivisionByZero,
+    PrintInt,
+    PrintFloat,
+    PrintBool,
+    PrintString,
+}
+
+impl Helper {
+    /// The code of the function, followed by the read-only data it uses
+    fn definition(self) -> (&'static str, &'static str) {
+        match self {
+            // Called with the line number in %edi, does not return
+            Helper::DivisionByZero => (
+                "c1_division_by_zero:
+    andq $-16, %rsp
+    movl %edi, %edx
+    movq stderr@GOTPCREL(%rip), %rax
+    movq (%rax), %rdi
+    leaq .Ldivision_by_zero(%rip), %rsi
+    xorl %eax, %eax
+    call fprintf@PLT
+    movl $1, %edi
+    call exit@PLT
+",
+                ".Ldivision_by_zero:
+    .asciz \"Division by zero at line %d\\n\"
+",
+            ),
+            Helper::PrintInt => (
+                "c1_print_int:
+    subq $8, %rsp
+    movl %edi, %esi
+    leaq .Lint_format(%rip), %rdi
+    xorl %eax, %eax
+    call printf@PLT
+    addq $8, %rsp
+    ret
+",
+                ".Lint_format:
+    .asciz \"%d\\n\"
+",
+            ),
+            // Print NaN the way the interpreter does, the C library may add a sign
+            Helper::PrintFloat => (
+                "c1_print_float:
+    subq $8, %rsp
+    ucomiss %xmm0, %xmm0
+    jp 1f
+    cvtss2sd %xmm0, %xmm0
+    leaq .Lfloat_format(%rip), %rdi
+    movl $1, %eax
+    call printf@PLT
+    addq $8, %rsp
+    ret
+1:
+    leaq .Lnan(%rip), %rdi
+    call puts@PLT
+    addq $8, %rsp
+    ret
+",
+                ".Lfloat_format:
+    .asciz \"%f\\n\"
+.Lnan:
+    .asciz \"NaN\"
+",
+            ),
+            Helper::PrintBool => (
+                "c1_print_bool:
+    subq $8, %rsp
+    leaq .Lfalse(%rip), %rax
+    testl %edi, %edi
+    leaq .Ltrue(%rip), %rdi
+    cmove %rax, %rdi
+    call puts@PLT
+    addq $8, %rsp
+    ret
+",
+                ".Ltrue:
+    .asciz \"true\"
+.Lfalse:
+    .asciz \"false\"
+",
+            ),
+            // Called with the address in %rdi and the length in %rsi
+            Helper::PrintString => (
+                "c1_print_string:
+    subq $8, %rsp
+    movq %rsi, %rdx
+    movl $1, %esi
+    movq stdout@GOTPCREL(%rip), %rcx
+    movq (%rcx), %rcx
+    call fwrite@PLT
+    addq $8, %rsp
+    ret
+",
+                "",
+            ),
+        }
+    }
+}
+
+/// Translate the program into assembly.
+///
+/// The program has to pass [`semantic::analyze`](crate::semantic::analyze) and
+/// [`typecheck::check`](crate::typecheck::check), which returned the types.
+pub fn emit(program: &Program, types: &TypeInfo) -> String {
+    let mut emitter = Emitter {
+        types,
+        functions: program
+            .functions
+            .iter()
+            .map(|function| (function.name.name.as_str(), function))
+            .collect(),
+        helpers: BTreeSet::new(),
+        strings: Vec::new(),
+        labels: 0,
+        function: "",
+        return_type: Type::Void,
+        slots: HashMap::new(),
+        depth: 0,
+        code: String::new(),
+    };
+
+    let mut sections = vec!["    .text\n".to_string()];
+    for function in &program.functions {
+        sections.push(emitter.function(function));
+    }
+    if let Some(main) = program
+        .functions
+        .iter()
+        .find(|function| function.name.name == "main")
+    {
+        // The stack is aligned to 16 bytes before the call of main, which pushed the return
+        // address
+        let result = match main.return_type {
+            Type::Int => "",
+            _ => "    xorl %eax, %eax\n",
+        };
+        sections.push(format!(
+            "    .globl main\nmain:\n    subq $8, %rsp\n    call fn_main\n{}    \
+             addq $8, %rsp\n    ret\n",
+            result
+        ));
+    }
+    for helper in &emitter.helpers {
+        sections.push(helper.definition().0.to_string());
+    }
+
+    if !program.globals.is_empty() {
+        let mut data = String::from("    .data\n    .p2align 2\n");
+        for global in &program.globals {
+            let value = match global.value {
+                Some(value) => constant(global.ty, value),
+                None => 0,
+            };
+            writeln!(data, "var_{}:\n    .long {}", global.name.name, value).unwrap();
+        }
+        sections.push(data);
+    }
+    let mut read_only = String::new();
+    for (i, text) in emitter.strings.iter().enumerate() {
+        writeln!(
+            read_only,
+            ".Lstring{}:\n    .ascii {}",
+            i,
+            string_literal(text)
+        )
+        .unwrap();
+    }
+    for helper in &emitter.helpers {
+        read_only.push_str(helper.definition().1);
+    }
+    if !read_only.is_empty() {
+        sections.push(format!("    .section .rodata\n{}", read_only));
+    }
+
+    // The stack does not need to be executable
+    sections.push("    .section .note.GNU-stack,\"\",@progbits\n".to_string());
+    sections.join("\n")
+}
+
+/// Return the bits of a literal converted to the type of a variable
+fn constant(ty: Type, value: Literal) -> u32 {
+    match (ty, value) {
+        (Type::Float, Literal::Int(value)) => (value as f32).to_bits(),
+        (_, Literal::Int(value)) => value as u32,
+        (_, Literal::Float(value)) => value.to_bits(),
+        (_, Literal::Bool(value)) => value as u32,
+    }
+}
+
+/// Return the string as a string literal of the assembler
+fn string_literal(text: &str) -> String {
+    let mut literal = String::from("\"");
+    for byte in text.bytes() {
+        match byte {
+            b'"' => literal.push_str("\\\""),
+            b'\\' => literal.push_str("\\\\"),
+            b' '..=b'~' => literal.push(byte as char),
+            _ => write!(literal, "\\{:03o}", byte).unwrap(),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+/// Where the System V ABI passes an argument
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArgumentLocation {
+    Register(&'static str),
+    /// The SSE register with the given number
+    Float(usize),
+    /// The stack slot with the given number, counted from the return address
+    Stack(usize),
+}
+
+/// Return where the arguments of the given types are passed
+fn argument_locations(types: impl Iterator<Item = Type>) -> Vec<ArgumentLocation> {
+    let (mut ints, mut floats, mut stack) = (0, 0, 0);
+    types
+        .map(|ty| {
+            if ty == Type::Float && floats < FLOAT_REGISTERS {
+                floats += 1;
+                ArgumentLocation::Float(floats - 1)
+            } else if ty != Type::Float && ints < INT_REGISTERS.len() {
+                ints += 1;
+                ArgumentLocation::Register(INT_REGISTERS[ints - 1])
+            } else {
+                stack += 1;
+                ArgumentLocation::Stack(stack - 1)
+            }
+        })
+        .collect()
+}
+
+struct Emitter<'a> {
+    types: &'a TypeInfo,
+    functions: HashMap<&'a str, &'a FunctionDefinition>,
+    helpers: BTreeSet<Helper>,
+    /// The strings printed by the program, labeled by their index
+    strings: Vec<String>,
+    /// The number of labels created so far
+    labels: usize,
+    /// Name of the current function
+    function: &'a str,
+    return_type: Type,
+    /// The offsets of the parameters and variables of the current function from `%rbp`
+    slots: HashMap<&'a str, i64>,
+    /// The number of bytes pushed onto the stack by the expression being evaluated, which is
+    /// needed to align the stack for calls
+    depth: usize,
+    code: String,
+}
+
+impl<'a> Emitter<'a> {
+    fn function(&mut self, function: &'a FunctionDefinition) -> String {
+        let types = self
+            .types
+            .function(&function.name.name)
+            .expect("function of a checked program");
+        self.function = &function.name.name;
+        self.return_type = function.return_type;
+        self.slots = types
+            .parameters
+            .iter()
+            .chain(&types.variables)
+            .enumerate()
+            .map(|(i, (name, _))| (name.as_str(), -8 * (i as i64 + 1)))
+            .collect();
+        // The stack is aligned to 16 bytes after pushing %rbp
+        let frame = (8 * self.slots.len()).next_multiple_of(16);
+
+        self.code = format!("fn_{}:\n", function.name.name);
+        self.instruction("pushq %rbp");
+        self.instruction("movq %rsp, %rbp");
+        if frame > 0 {
+            self.instruction(&format!("subq ${}, %rsp", frame));
+        }
+        let locations = argument_locations(types.parameters.iter().map(|(_, ty)| *ty));
+        for ((name, ty), location) in types.parameters.iter().zip(locations) {
+            let slot = self.slots[name.as_str()];
+            match location {
+                ArgumentLocation::Register(register) => {
+                    self.instruction(&format!("movl {}, {}(%rbp)", register, slot))
+                }
+                ArgumentLocation::Float(register) => {
+                    self.instruction(&format!("movss %xmm{}, {}(%rbp)", register, slot))
+                }
+                ArgumentLocation::Stack(index) => {
+                    // Above the saved %rbp and the return address
+                    let mnemonic = if *ty == Type::Float { "movss" } else { "movl" };
+                    let register = if *ty == Type::Float { "%xmm0" } else { "%eax" };
+                    self.instruction(&format!(
+                        "{} {}(%rbp), {}",
+                        mnemonic,
+                        16 + 8 * index,
+                        register
+                    ));
+                    self.instruction(&format!("{} {}, {}(%rbp)", mnemonic, register, slot));
+                }
+            }
+        }
+        for (name, _) in &types.variables {
+            let slot = self.slots[name.as_str()];
+            self.instruction(&format!("movl $0, {}(%rbp)", slot));
+        }
+
+        for statement in &function.body {
+            self.statement(statement);
+        }
+        if !matches!(
+            function.body.last(),
+            Some(Statement {
+                kind: StatementKind::Return(_),
+                ..
+            })
+        ) {
+            self.instruction("leave");
+            self.instruction("ret");
+        }
+        std::mem::take(&mut self.code)
+    }
+
+    fn instruction(&mut self, instruction: &str) {
+        self.code.push_str("    ");
+        self.code.push_str(instruction);
+        self.code.push('\n');
+    }
+
+    fn new_label(&mut self) -> String {
+        self.labels += 1;
+        format!(".L{}", self.labels)
+    }
+
+    fn label(&mut self, label: &str) {
+        self.code.push_str(label);
+        self.code.push_str(":\n");
+    }
+
+    /// Return the memory operand of a variable
+    fn variable(&self, name: &str) -> String {
+        match self.slots.get(name) {
+            Some(offset) => format!("{}(%rbp)", offset),
+            None => format!("var_{}(%rip)", name),
+        }
+    }
+
+    fn statement(&mut self, statement: &'a Statement) {
+        match &statement.kind {
+            StatementKind::Block(statements) => {
+                for statement in statements {
+                    self.statement(statement);
+                }
+            }
+            StatementKind::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                let else_label = self.new_label();
+                self.expr(condition);
+                self.instruction("testl %eax, %eax");
+                self.instruction(&format!("je {}", else_label));
+                self.statement(then_branch);
+                match else_branch {
+                    Some(else_branch) => {
+                        let end = self.new_label();
+                        self.instruction(&format!("jmp {}", end));
+                        self.label(&else_label);
+                        self.statement(else_branch);
+                        self.label(&end);
+                    }
+                    None => self.label(&else_label),
+                }
+            }
+            StatementKind::While { condition, body } => {
+                let start = self.new_label();
+                let end = self.new_label();
+                self.label(&start);
+                self.expr(condition);
+                self.instruction("testl %eax, %eax");
+                self.instruction(&format!("je {}", end));
+                self.statement(body);
+                self.instruction(&format!("jmp {}", start));
+                self.label(&end);
+            }
+            StatementKind::DoWhile { body, condition } => {
+                let start = self.new_label();
+                self.label(&start);
+                self.statement(body);
+                self.expr(condition);
+                self.instruction("testl %eax, %eax");
+                self.instruction(&format!("jne {}", start));
+            }
+            StatementKind::For {
+                init,
+                condition,
+                update,
+                body,
+            } => {
+                if let Some(init) = init {
+                    self.statement(init);
+                }
+                let start = self.new_label();
+                let end = self.new_label();
+                self.label(&start);
+                if let Some(condition) = condition {
+                    self.expr(condition);
+                    self.instruction("testl %eax, %eax");
+                    self.instruction(&format!("je {}", end));
+                }
+                self.statement(body);
+                if let Some(update) = update {
+                    self.statement(update);
+                }
+                self.instruction(&format!("jmp {}", start));
+                self.label(&end);
+            }
+            StatementKind::Declaration { name, value, .. } => match value {
+                Some(value) => {
+                    self.expr(value);
+                    self.store(&name.name, self.expr_type(value));
+                }
+                // The bits of 0.0 are zero as well
+                None => {
+                    let variable = self.variable(&name.name);
+                    self.instruction(&format!("movl $0, {}", variable));
+                }
+            },
+            StatementKind::Return(value) => {
+                if let Some(value) = value {
+                    self.expr(value);
+                    self.convert(self.expr_type(value), self.return_type);
+                }
+                self.instruction("leave");
+                self.instruction("ret");
+            }
+            StatementKind::Printf(PrintfArgument::String(text)) => {
+                self.helpers.insert(Helper::PrintString);
+                self.instruction(&format!("leaq .Lstring{}(%rip), %rdi", self.strings.len()));
+                self.instruction(&format!("movl ${}, %esi", text.len()));
+                self.instruction("call c1_print_string");
+                self.strings.push(text.clone());
+            }
+            StatementKind::Printf(PrintfArgument::Value(value)) => {
+                self.expr(value);
+                match self.expr_type(value) {
+                    Type::Int => {
+                        self.helpers.insert(Helper::PrintInt);
+                        self.instruction("movl %eax, %edi");
+                        self.instruction("call c1_print_int");
+                    }
+                    Type::Float => {
+                        self.helpers.insert(Helper::PrintFloat);
+                        self.instruction("call c1_print_float");
+                    }
+                    _ => {
+                        self.helpers.insert(Helper::PrintBool);
+                        self.instruction("movl %eax, %edi");
+                        self.instruction("call c1_print_bool");
+                    }
+                }
+            }
+            StatementKind::Assignment { target, value } => {
+                self.expr(value);
+                self.store(&target.name, self.expr_type(value));
+            }
+            StatementKind::Call(call) => self.call(call),
+        }
+    }
+
+    /// Store the value of the given type in the variable, converting it to the type of the
+    /// variable
+    fn store(&mut self, name: &str, ty: Type) {
+        let variable_type = self
+            .types
+            .variable(self.function, name)
+            .expect("variable of a checked program");
+        self.convert(ty, variable_type);
+        let variable = self.variable(name);
+        match variable_type {
+            Type::Float => self.instruction(&format!("movss %xmm0, {}", variable)),
+            _ => self.instruction(&format!("movl %eax, {}", variable)),
+        }
+    }
+
+    /// Convert the value of an expression to the type it is assigned to
+    fn convert(&mut self, from: Type, to: Type) {
+        if from == Type::Int && to == Type::Float {
+            self.instruction("cvtsi2ssl %eax, %xmm0");
+        }
+    }
+
+    fn expr_type(&self, expr: &Expr) -> Type {
+        self.types.expr_type(self.function, expr)
+    }
+
+    /// Push the value of the given type
+    fn push(&mut self, ty: Type) {
+        if ty == Type::Float {
+            self.instruction("subq $8, %rsp");
+            self.instruction("movss %xmm0, (%rsp)");
+        } else {
+            self.instruction("pushq %rax");
+        }
+        self.depth += 8;
+    }
+
+    /// Evaluate the expression into `%eax` or `%xmm0`
+    fn expr(&mut self, expr: &Expr) {
+        match &expr.kind {
+            ExprKind::Literal(value) => {
+                let ty = self.expr_type(expr);
+                self.instruction(&format!("movl ${}, %eax", constant(ty, *value) as i32));
+                if ty == Type::Float {
+                    self.instruction("movd %eax, %xmm0");
+                }
+            }
+            ExprKind::Variable(name) => {
+                let variable = self.variable(&name.name);
+                match self.expr_type(expr) {
+                    Type::Float => self.instruction(&format!("movss {}, %xmm0", variable)),
+                    _ => self.instruction(&format!("movl {}, %eax", variable)),
+                }
+            }
+            ExprKind::Call(call) => self.call(call),
+            ExprKind::Assignment { target, value } => {
+                self.expr(value);
+                self.store(&target.name, self.expr_type(value));
+            }
+            ExprKind::Unary { op, operand } => {
+                self.expr(operand);
+                match (op, self.expr_type(operand)) {
+                    (UnaryOperator::Plus, _) => {}
+                    (UnaryOperator::Minus, Type::Float) => {
+                        self.instruction("movd %xmm0, %eax");
+                        self.instruction("xorl $0x80000000, %eax");
+                        self.instruction("movd %eax, %xmm0");
+                    }
+                    (UnaryOperator::Minus, _) => self.instruction("negl %eax"),
+                    (UnaryOperator::Not, _) => self.instruction("xorl $1, %eax"),
+                }
+            }
+            ExprKind::Binary {
+                op: op @ (BinaryOperator::And | BinaryOperator::Or),
+                lhs,
+                rhs,
+            } => {
+                // The left operand decides the result if it is false for && and true for ||
+                let end = self.new_label();
+                self.expr(lhs);
+                self.instruction("testl %eax, %eax");
+                let jump = if *op == BinaryOperator::And {
+                    "je"
+                } else {
+                    "jne"
+                };
+                self.instruction(&format!("{} {}", jump, end));
+                self.expr(rhs);
+                self.label(&end);
+            }
+            ExprKind::Binary { op, lhs, rhs } => {
+                let operands = (self.expr_type(lhs), self.expr_type(rhs));
+                if operands.0 == Type::Float || operands.1 == Type::Float {
+                    self.expr(lhs);
+                    self.convert(operands.0, Type::Float);
+                    self.push(Type::Float);
+                    self.expr(rhs);
+                    self.convert(operands.1, Type::Float);
+                    self.instruction("movaps %xmm0, %xmm1");
+                    self.instruction("movss (%rsp), %xmm0");
+                    self.instruction("addq $8, %rsp");
+                    self.depth -= 8;
+                    self.float_binary(*op);
+                } else {
+                    self.expr(lhs);
+                    self.push(Type::Int);
+                    self.expr(rhs);
+                    self.instruction("movl %eax, %ecx");
+                    self.instruction("popq %rax");
+                    self.depth -= 8;
+                    self.int_binary(*op, expr.location.line);
+                }
+            }
+        }
+    }
+
+    /// Apply an operator to the `int` or `bool` operands in `%eax` and `%ecx`
+    fn int_binary(&mut self, op: BinaryOperator, line: usize) {
+        let condition = match op {
+            BinaryOperator::Add => return self.instruction("addl %ecx, %eax"),
+            BinaryOperator::Subtract => return self.instruction("subl %ecx, %eax"),
+            BinaryOperator::Multiply => return self.instruction("imull %ecx, %eax"),
+            BinaryOperator::Divide | BinaryOperator::Modulo => {
+                return self.division(op == BinaryOperator::Modulo, line)
+            }
+            BinaryOperator::Equal => "e",
+            BinaryOperator::NotEqual => "ne",
+            BinaryOperator::Less => "l",
+            BinaryOperator::LessEqual => "le",
+            BinaryOperator::Greater => "g",
+            BinaryOperator::GreaterEqual => "ge",
+            BinaryOperator::And | BinaryOperator::Or => unreachable!("logical operator"),
+        };
+        self.instruction("cmpl %ecx, %eax");
+        self.instruction(&format!("set{} %al", condition));
+        self.instruction("movzbl %al, %eax");
+    }
+
+    /// Divide `%eax` by `%ecx`. `idivl` traps when dividing the smallest `int` by -1, so dividing
+    /// by -1 is a negation.
+    fn division(&mut self, remainder: bool, line: usize) {
+        self.helpers.insert(Helper::DivisionByZero);
+        let divide = self.new_label();
+        let end = self.new_label();
+        let not_zero = self.new_label();
+        self.instruction("testl %ecx, %ecx");
+        self.instruction(&format!("jne {}", not_zero));
+        self.instruction(&format!("movl ${}, %edi", line));
+        self.instruction("call c1_division_by_zero");
+        self.label(&not_zero);
+        self.instruction("cmpl $-1, %ecx");
+        self.instruction(&format!("jne {}", divide));
+        if remainder {
+            self.instruction("xorl %eax, %eax");
+        } else {
+            self.instruction("negl %eax");
+        }
+        self.instruction(&format!("jmp {}", end));
+        self.label(&divide);
+        self.instruction("cltd");
+        self.instruction("idivl %ecx");
+        if remainder {
+            self.instruction("movl %edx, %eax");
+        }
+        self.label(&end);
+    }
+
+    /// Apply an operator to the `float` operands in `%xmm0` and `%xmm1`. Comparisons with NaN are
+    /// false, except for `!=`.
+    fn float_binary(&mut self, op: BinaryOperator) {
+        let condition = match op {
+            BinaryOperator::Add => return self.instruction("addss %xmm1, %xmm0"),
+            BinaryOperator::Subtract => return self.instruction("subss %xmm1, %xmm0"),
+            BinaryOperator::Multiply => return self.instruction("mulss %xmm1, %xmm0"),
+            BinaryOperator::Divide => return self.instruction("divss %xmm1, %xmm0"),
+            BinaryOperator::Equal => {
+                self.instruction("ucomiss %xmm1, %xmm0");
+                self.instruction("sete %al");
+                self.instruction("setnp %cl");
+                self.instruction("andb %cl, %al");
+                self.instruction("movzbl %al, %eax");
+                return;
+            }
+            BinaryOperator::NotEqual => {
+                self.instruction("ucomiss %xmm1, %xmm0");
+                self.instruction("setne %al");
+                self.instruction("setp %cl");
+                self.instruction("orb %cl, %al");
+                self.instruction("movzbl %al, %eax");
+                return;
+            }
+            // An unordered comparison sets the carry and zero flag, so above and above or equal
+            // are false. Less is greater with swapped operands.
+            BinaryOperator::Greater => "a",
+            BinaryOperator::GreaterEqual => "ae",
+            BinaryOperator::Less => {
+                self.instruction("movaps %xmm0, %xmm2");
+                self.instruction("movaps %xmm1, %xmm0");
+                self.instruction("movaps %xmm2, %xmm1");
+                "a"
+            }
+            BinaryOperator::LessEqual => {
+                self.instruction("movaps %xmm0, %xmm2");
+                self.instruction("movaps %xmm1, %xmm0");
+                self.instruction("movaps %xmm2, %xmm1");
+                "ae"
+            }
+            BinaryOperator::Modulo | BinaryOperator::And | BinaryOperator::Or => {
+                unreachable!("operator without float operands")
+            }
+        };
+        self.instruction("ucomiss %xmm1, %xmm0");
+        self.instruction(&format!("set{} %al", condition));
+        self.instruction("movzbl %al, %eax");
+    }
+
+    /// Call a function. The arguments are pushed from left to right and then moved to their
+    /// registers and stack slots.
+    fn call(&mut self, call: &FunctionCall) {
+        let function = self.functions[call.name.name.as_str()];
+        let parameters: Vec<_> = function
+            .parameters
+            .iter()
+            .map(|parameter| parameter.ty)
+            .collect();
+        for (argument, ty) in call.arguments.iter().zip(&parameters) {
+            self.expr(argument);
+            self.convert(self.expr_type(argument), *ty);
+            self.push(*ty);
+        }
+
+        let locations = argument_locations(parameters.iter().copied());
+        let stack_arguments = locations
+            .iter()
+            .filter(|location| matches!(location, ArgumentLocation::Stack(_)))
+            .count();
+        let padding = (self.depth + 8 * stack_arguments) % 16;
+        let reserved = padding + 8 * stack_arguments;
+        if reserved > 0 {
+            self.instruction(&format!("subq ${}, %rsp", reserved));
+        }
+        let count = locations.len();
+        for (i, location) in locations.into_iter().enumerate() {
+            let pushed = reserved + 8 * (count - 1 - i);
+            match location {
+                ArgumentLocation::Register(register) => {
+                    self.instruction(&format!("movl {}(%rsp), {}", pushed, register))
+                }
+                ArgumentLocation::Float(register) => {
+                    self.instruction(&format!("movss {}(%rsp), %xmm{}", pushed, register))
+                }
+                ArgumentLocation::Stack(index) => {
+                    self.instruction(&format!("movq {}(%rsp), %rax", pushed));
+                    self.instruction(&format!("movq %rax, {}(%rsp)", 8 * index));
+                }
+            }
+        }
+        self.instruction(&format!("call fn_{}", call.name.name));
+        let pushed = reserved + 8 * count;
+        if pushed > 0 {
+            self.instruction(&format!("addq ${}, %rsp", pushed));
+        }
+        self.depth -= 8 * count;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{argument_locations, emit, ArgumentLocation};
+    use crate::ast::Type;
+    use crate::semantic::analyze;
+    use crate::typecheck::check;
+    use crate::{C1Parser, Dialect};
+
+    fn emit_text(text: &str) -> String {
+        let program = C1Parser::parse_program_in(text, Dialect::C1).unwrap();
+        analyze(&program).unwrap();
+        let types = check(&program).unwrap();
+        emit(&program, &types)
+    }
+
+    #[test]
+    fn argument_classification() {
+        use ArgumentLocation::*;
+        let types = [Type::Int, Type::Float, Type::Bool]
+            .into_iter()
+            .cycle()
+            .take(27);
+        let locations = argument_locations(types);
+        assert_eq!(
+            locations[..6],
+            [
+                Register("%edi"),
+                Float(0),
+                Register("%esi"),
+                Register("%edx"),
+                Float(1),
+                Register("%ecx")
+            ]
+        );
+        assert_eq!(
+            locations[6..10],
+            [Register("%r8d"), Float(2), Register("%r9d"), Stack(0)]
+        );
+        // Floats go to the stack once the SSE registers are used up
+        assert_eq!(
+            locations[22..],
+            [Float(7), Stack(9), Stack(10), Stack(11), Stack(12)]
+        );
+    }
+
+    #[test]
+    fn operators() {
+        let code = emit_text("void main() { x = 1.5; b = x < 2; y = 7; y = -y % 2; }");
+        // Less is greater with swapped operands, which is false for NaN
+        assert!(code.contains(
+            "    movaps %xmm0, %xmm2
+    movaps %xmm1, %xmm0
+    movaps %xmm2, %xmm1
+    ucomiss %xmm1, %xmm0
+    seta %al
+    movzbl %al, %eax
+"
+        ));
+        assert!(code.contains(
+            "    negl %eax
+    pushq %rax
+    movl $2, %eax
+    movl %eax, %ecx
+    popq %rax
+    testl %ecx, %ecx
+    jne .L3
+    movl $1, %edi
+    call c1_division_by_zero
+.L3:
+    cmpl $-1, %ecx
+    jne .L1
+    xorl %eax, %eax
+    jmp .L2
+.L1:
+    cltd
+    idivl %ecx
+    movl %edx, %eax
+.L2:
+"
+        ));
+    }
+
+    #[test]
+    fn program_layout() {
+        let code = emit_text(
+            "float scale = 2; bool verbose = true; int count;
+             void show(float x) { printf(\"x: \\\"\\0\\n\"); printf(x * scale); }
+             void main() { show(count); }",
+        );
+        assert_eq!(
+            code,
+            "    .text
+
+fn_show:
+    pushq %rbp
+    movq %rsp, %rbp
+    subq $16, %rsp
+    movss %xmm0, -8(%rbp)
+    leaq .Lstring0(%rip), %rdi
+    movl $6, %esi
+    call c1_print_string
+    movss -8(%rbp), %xmm0
+    subq $8, %rsp
+    movss %xmm0, (%rsp)
+    movss var_scale(%rip), %xmm0
+    movaps %xmm0, %xmm1
+    movss (%rsp), %xmm0
+    addq $8, %rsp
+    mulss %xmm1, %xmm0
+    call c1_print_float
+    leave
+    ret
+
+fn_main:
+    pushq %rbp
+    movq %rsp, %rbp
+    movl var_count(%rip), %eax
+    cvtsi2ssl %eax, %xmm0
+    subq $8, %rsp
+    movss %xmm0, (%rsp)
+    subq $8, %rsp
+    movss 8(%rsp), %xmm0
+    call fn_show
+    addq $16, %rsp
+    leave
+    ret
+
+    .globl main
+main:
+    subq $8, %rsp
+    call fn_main
+    xorl %eax, %eax
+    addq $8, %rsp
+    ret
+
+"
+            .to_string()
+                + &code[code.find("c1_print_float:").unwrap()..code.find("    .data").unwrap()]
+                + "    .data
+    .p2align 2
+var_scale:
+    .long 1073741824
+var_verbose:
+    .long 1
+var_count:
+    .long 0
+
+    .section .rodata
+.Lstring0:
+    .ascii \"x: \\\"\\000\\012\"
+.Lfloat_format:
+    .asciz \"%f\\n\"
+.Lnan:
+    .asciz \"NaN\"
+
+    .section .note.GNU-stack,\"\",@progbits
+"
+        );
+    }
+}
@@ -0,0 +1,51 @@
+mod common;
+
+use cb_3::cgen::emit;
+use cb_3::{C1Parser, Dialect};
+use common::NativeBackend;
+
+const C: NativeBackend = NativeBackend {
+    name: "cgen",
+    extension: "c",
+    emit,
+    flags: &["-std=c99", "-pedantic", "-Wall", "-Werror"],
+    supported: true,
+};
+
+#[test]
+fn compiled_c_matches_interpreter() {
+    C.assert_data_matches_interpreter();
+}
+
+#[test]
+fn deep_recursion() {
+    C.assert_deep_recursion();
+}
+
+#[test]
+fn division_by_zero() {
+    C.assert_division_by_zero("/");
+}
+
+#[test]
+fn nested_assignments() {
+    let text = "int main() {
+    y = 3;
+    y = y = y * 2;
+    printf(y);
+    x = 1;
+    x = (y = x + 1) * (x = y + 1);
+    printf(x);
+    for (i = 0; i < 5; i = (i = i + 1) + 1) printf(i);
+    return y;
+}";
+    let program = C1Parser::parse_program_in(text, Dialect::C1).unwrap();
+    C.assert_matches_interpreter(&program, "nested_assignments");
+}
+
+#[test]
+fn float_division_by_zero() {
+    let text = "void main() { printf(1.5 / 0); printf(-1.5 / -0); printf(0 / 0.0); }";
+    let program = C1Parser::parse_program_in(text, Dialect::C1).unwrap();
+    C.assert_matches_interpreter(&program, "float_division_by_zero");
+}
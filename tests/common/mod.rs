@@ -0,0 +1,174 @@
+//! Helpers shared by the integration tests
+
+// Not every test suite uses every helper
+#![allow(dead_code)]
+
+use cb_3::ast::Program;
+use cb_3::bytecode::compile;
+use cb_3::interpreter::{Interpreter, Value};
+use cb_3::semantic::analyze;
+use cb_3::typecheck::{check, TypeInfo};
+use cb_3::vm::Vm;
+use cb_3::{C1Parser, Dialect};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+/// Parse a program from tests/data, which is C(-1) or C1 depending on the extension
+pub fn parse_data(path: &Path) -> Program {
+    let text = fs::read_to_string(path).unwrap();
+    let dialect = match path.extension().and_then(|extension| extension.to_str()) {
+        Some("c1") => Dialect::C1,
+        _ => Dialect::CMinus1,
+    };
+    C1Parser::parse_program_in(&text, dialect)
+        .unwrap_or_else(|error| panic!("{}: {}", path.display(), error))
+}
+
+/// Parse every program in tests/data, in the order of their paths
+pub fn data_programs() -> Vec<(PathBuf, Program)> {
+    let mut paths: Vec<_> = fs::read_dir("tests/data")
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    paths.sort();
+    paths
+        .into_iter()
+        .map(|path| {
+            let program = parse_data(&path);
+            (path, program)
+        })
+        .collect()
+}
+
+/// The C compiler from `$CC`, or `cc`
+fn compiler() -> String {
+    env::var("CC").unwrap_or_else(|_| "cc".to_string())
+}
+
+/// A backend whose output the system C compiler turns into an executable
+pub struct NativeBackend {
+    /// Names the directory of the generated files
+    pub name: &'static str,
+    /// Extension of the generated source files
+    pub extension: &'static str,
+    pub emit: fn(&Program, &TypeInfo) -> String,
+    /// Flags for the C compiler
+    pub flags: &'static [&'static str],
+    /// Whether the host can run the generated code
+    pub supported: bool,
+}
+
+impl NativeBackend {
+    /// Emit the program, build it with the system C compiler and run it. Return `None` if the host
+    /// cannot run the generated code or there is no C compiler.
+    pub fn build_and_run(&self, program: &Program, name: &str) -> Option<Output> {
+        if !self.supported || Command::new(compiler()).arg("--version").output().is_err() {
+            eprintln!("skipping {}: no toolchain for {}", name, self.name);
+            return None;
+        }
+        analyze(program).unwrap();
+        let types = check(program).unwrap();
+        let source = (self.emit)(program, &types);
+
+        let directory = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(self.name);
+        fs::create_dir_all(&directory).unwrap();
+        let source_path = directory.join(format!("{}.{}", name, self.extension));
+        let binary_path = directory.join(name);
+        fs::write(&source_path, &source).unwrap();
+        let built = Command::new(compiler())
+            .args(self.flags)
+            .arg("-o")
+            .arg(&binary_path)
+            .arg(&source_path)
+            .output()
+            .unwrap();
+        assert!(
+            built.status.success(),
+            "{}\n{}",
+            String::from_utf8_lossy(&built.stderr),
+            source
+        );
+        Some(Command::new(&binary_path).output().unwrap())
+    }
+
+    /// Check that the program prints the same and exits with the same value as in the interpreter
+    pub fn assert_matches_interpreter(&self, program: &Program, name: &str) {
+        let mut expected = Vec::new();
+        let exit_value = Interpreter::new(program, &mut expected).run().unwrap();
+        self.assert_output(program, name, expected, exit_value);
+    }
+
+    /// Check that the program prints the same and exits with the same value as in the virtual
+    /// machine, which allows much deeper recursion than the interpreter
+    pub fn assert_matches_vm(&self, program: &Program, name: &str) {
+        let mut expected = Vec::new();
+        let exit_value = Vm::new(&compile(program).unwrap(), &mut expected)
+            .run()
+            .unwrap();
+        self.assert_output(program, name, expected, exit_value);
+    }
+
+    fn assert_output(&self, program: &Program, name: &str, expected: Vec<u8>, exit_value: Value) {
+        let Some(output) = self.build_and_run(program, name) else {
+            return;
+        };
+        assert_eq!(
+            String::from_utf8(output.stdout).unwrap(),
+            String::from_utf8(expected).unwrap(),
+            "{}",
+            name
+        );
+        let status = match exit_value {
+            Value::Int(value) => value & 0xff,
+            _ => 0,
+        };
+        assert_eq!(output.status.code(), Some(status), "{}", name);
+    }
+
+    /// Check [`assert_matches_interpreter`](Self::assert_matches_interpreter) for every program in
+    /// tests/data
+    pub fn assert_data_matches_interpreter(&self) {
+        for (path, program) in data_programs() {
+            self.assert_matches_interpreter(&program, path.file_stem().unwrap().to_str().unwrap());
+        }
+    }
+
+    /// Check that a recursion 100000 calls deep runs like in the virtual machine
+    pub fn assert_deep_recursion(&self) {
+        let text = "int sum(int n) {
+    if (n % 25000 == 0) printf(n);
+    if (n == 0) return 0;
+    return n + sum(n - 1);
+}
+int main() { return sum(100000); }";
+        let program = C1Parser::parse_program_in(text, Dialect::C1).unwrap();
+        self.assert_matches_vm(&program, "deep_recursion");
+    }
+
+    /// Check that applying the operator `/` or `%` to a zero divisor stops the program with an
+    /// error message after the output of the statements before
+    pub fn assert_division_by_zero(&self, operator: &str) {
+        let text = format!(
+            "int f(int a) {{ return 1; }}
+void main() {{
+    printf(1);
+    x = 0;
+    printf(f(1) {} x);
+    printf(2);
+}}",
+            operator
+        );
+        let program = C1Parser::parse_program_in(&text, Dialect::C1).unwrap();
+        let Some(output) = self.build_and_run(&program, "division_by_zero") else {
+            return;
+        };
+        assert_eq!(output.stdout, b"1\n");
+        assert_eq!(
+            String::from_utf8(output.stderr).unwrap(),
+            "Division by zero at line 5\n"
+        );
+        assert_eq!(output.status.code(), Some(1));
+    }
+}
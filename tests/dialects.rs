@@ -0,0 +1,118 @@
+use cb_3::{C1Parser, Dialect};
+
+/// Programs using exactly one construct that only exists in C1
+const C1_ONLY: &[(&str, &str)] = &[
+    ("global declaration", "int x; void main() {}"),
+    ("initialized global", "float pi = 3.14; void main() {}"),
+    ("parameter", "void f(int a) {} void main() {}"),
+    (
+        "parameters",
+        "int f(int a, bool b) { return a; } void main() {}",
+    ),
+    ("argument", "void main() { f(1); }"),
+    (
+        "arguments in expression",
+        "void main() { x = f(1, y = 2); }",
+    ),
+    ("declaration", "void main() { int x; }"),
+    ("initialized declaration", "void main() { float y = 1.5; }"),
+    ("while loop", "void main() { while (x < 3) x = x + 1; }"),
+    (
+        "do-while loop",
+        "void main() { do { x = 1; } while (false); }",
+    ),
+    (
+        "for loop",
+        "void main() { for (i = 0; i < 3; i = i + 1) {} }",
+    ),
+    ("empty for loop", "void main() { for (;;) {} }"),
+    ("else branch", "void main() { if (a) x = 1; else x = 2; }"),
+    ("else if", "void main() { if (a) {} else if (b) {} }"),
+    ("string", r#"void main() { printf("hello\n"); }"#),
+];
+
+/// Programs that are valid in both dialects
+const COMMON: &[&str] = &[
+    "",
+    "void main() {}",
+    "int f() { return 1; } void main() { if (f() == 1) { printf(f()); } }",
+    "float calc() { x = 1.0; y = 2.2; return x + y; }",
+    "void main() { x = -a * -b % +3; done = !done; }",
+    include_str!("data/beispiel.c-1"),
+];
+
+#[test]
+fn c1_constructs_are_rejected_in_c_minus_1() {
+    for (construct, text) in C1_ONLY {
+        assert!(
+            C1Parser::parse_program_in(text, Dialect::C1).is_ok(),
+            "{} should be valid C1",
+            construct
+        );
+        assert!(
+            C1Parser::parse_program_in(text, Dialect::CMinus1).is_err(),
+            "{} should be rejected in C(-1)",
+            construct
+        );
+        assert!(
+            C1Parser::parse(text).is_err(),
+            "parse should reject the {}",
+            construct
+        );
+    }
+}
+
+#[test]
+fn c_minus_1_programs_are_valid_c1() {
+    for text in COMMON {
+        for dialect in [Dialect::CMinus1, Dialect::C1] {
+            assert!(
+                C1Parser::parse_program_in(text, dialect).is_ok(),
+                "{:?} should accept {:?}",
+                dialect,
+                text
+            );
+        }
+    }
+}
+
+#[test]
+fn dialects_agree_on_the_tree() {
+    for text in COMMON {
+        assert_eq!(
+            C1Parser::parse_program_in(text, Dialect::CMinus1),
+            C1Parser::parse_program_in(text, Dialect::C1)
+        );
+        assert_eq!(
+            C1Parser::parse_cst_in(text, Dialect::CMinus1)
+                .unwrap()
+                .debug_tree(),
+            C1Parser::parse_cst_in(text, Dialect::C1)
+                .unwrap()
+                .debug_tree()
+        );
+    }
+}
+
+#[test]
+fn invalid_in_both_dialects() {
+    let texts = [
+        "void main() { int x = 1 }",
+        "int x = y; void main() {}",
+        "void f(int a,) {}",
+        "void main() { for (int i = 0; i < 3; i = i + 1) {} }",
+        "void main() { do x = 1 while (true); }",
+        "void main() { printf(\"a\" + 1); }",
+        "void main() { else {} }",
+    ];
+    for text in texts {
+        for dialect in [Dialect::CMinus1, Dialect::C1] {
+            assert!(
+                C1Parser::parse_program_in(text, dialect).is_err(),
+                "{:?} should reject {:?}",
+                dialect,
+                text
+            );
+        }
+    }
+}
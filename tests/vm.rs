@@ -0,0 +1,61 @@
+mod common;
+
+use cb_3::bytecode::{compile, file, CompileErrorKind};
+use cb_3::interpreter::Interpreter;
+use cb_3::vm::Vm;
+use cb_3::C1Parser;
+use common::data_programs;
+
+#[test]
+fn vm_matches_interpreter() {
+    let programs = data_programs();
+    assert!(programs.len() >= 3);
+    for (path, program) in programs {
+        let mut expected = Vec::new();
+        let expected_result = Interpreter::new(&program, &mut expected).run();
+        let module = compile(&program).unwrap();
+        let mut output = Vec::new();
+        let result = Vm::new(&module, &mut output).run();
+
+        assert_eq!(result, expected_result, "{}", path.display());
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            String::from_utf8(expected).unwrap(),
+            "{}",
+            path.display()
+        );
+    }
+}
+
+#[test]
+fn modules_round_trip() {
+    for (path, program) in data_programs() {
+        let module = compile(&program).unwrap();
+
+        let bytes = file::to_bytes(&module);
+        let loaded =
+            file::load(&bytes).unwrap_or_else(|error| panic!("{}: {}", path.display(), error));
+        assert_eq!(loaded, module, "{}", path.display());
+        // Writing is deterministic
+        assert_eq!(file::to_bytes(&loaded), bytes);
+
+        let mut expected = Vec::new();
+        Interpreter::new(&program, &mut expected).run().unwrap();
+        let mut output = Vec::new();
+        Vm::new(&loaded, &mut output).run().unwrap();
+        assert_eq!(output, expected, "{}", path.display());
+    }
+}
+
+/// Unlike the interpreter, the compiler rejects calls that are never reached
+#[test]
+fn unreachable_undefined_call() {
+    let program = C1Parser::parse_program("void main() { printf(false && undefined()); }").unwrap();
+    let mut output = Vec::new();
+    Interpreter::new(&program, &mut output).run().unwrap();
+    assert_eq!(output, b"false\n");
+    assert_eq!(
+        compile(&program).unwrap_err().kind,
+        CompileErrorKind::UndefinedFunction("undefined".to_string())
+    );
+}
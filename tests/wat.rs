@@ -0,0 +1,86 @@
+mod common;
+
+use cb_3::ast::Program;
+use cb_3::semantic::analyze;
+use cb_3::typecheck::check;
+use cb_3::wat::emit;
+use cb_3::{C1Parser, Dialect};
+use common::data_programs;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use wasmparser::{ExternalKind, Parser, Payload, Validator};
+
+/// Emit the module for the program and check that it assembles into a valid WebAssembly module
+/// that exports the memory if it imports `print_string`
+fn emit_valid(program: &Program, name: &str) -> String {
+    analyze(program).unwrap();
+    let types = check(program).unwrap();
+    let module = emit(program, &types);
+
+    let bytes = wat::parse_str(&module).unwrap_or_else(|error| panic!("{}: {}", name, error));
+    Validator::new()
+        .validate_all(&bytes)
+        .unwrap_or_else(|error| panic!("{}: {}", name, error));
+    let mut prints_strings = false;
+    let mut exports_memory = false;
+    for payload in Parser::new(0).parse_all(&bytes) {
+        match payload.unwrap() {
+            Payload::ImportSection(imports) => {
+                for import in imports.into_imports() {
+                    prints_strings |= import.unwrap().name == "print_string";
+                }
+            }
+            Payload::ExportSection(exports) => {
+                for export in exports {
+                    let export = export.unwrap();
+                    exports_memory |=
+                        export.name == "memory" && export.kind == ExternalKind::Memory;
+                }
+            }
+            _ => {}
+        }
+    }
+    assert!(
+        exports_memory || !prints_strings,
+        "{}: print_string needs the exported memory",
+        name
+    );
+    module
+}
+
+/// Compare the module emitted for every program in tests/data with tests/wat/<name>.wat. Setting
+/// `UPDATE_GOLDEN` writes the emitted modules to the golden files instead.
+#[test]
+fn golden_files() {
+    let update = env::var_os("UPDATE_GOLDEN").is_some();
+    for (path, program) in data_programs() {
+        let golden = PathBuf::from("tests/wat")
+            .join(path.file_stem().unwrap())
+            .with_extension("wat");
+        let module = emit_valid(&program, &golden.display().to_string());
+        if update {
+            fs::write(&golden, &module).unwrap();
+        } else {
+            let expected = fs::read_to_string(&golden)
+                .unwrap_or_else(|error| panic!("{}: {}", golden.display(), error));
+            assert_eq!(module, expected, "{}", golden.display());
+        }
+    }
+}
+
+/// Check programs that tests/data does not cover
+#[test]
+fn valid_modules() {
+    let programs = [
+        "void main() { printf(\"\"); }",
+        "void main() { }",
+        "int f(int n) { while (true) { if (n > 9) return n; n = n + 1; } }
+         float g(float x) { return -x / 0; }
+         int main() { printf(f(1) % 3); printf((g(1) < 1) || false); printf(\"done\"); return 0; }",
+    ];
+    for (i, text) in programs.iter().enumerate() {
+        let program = C1Parser::parse_program_in(text, Dialect::C1).unwrap();
+        emit_valid(&program, &format!("program {}", i));
+    }
+}
@@ -0,0 +1,114 @@
+(module
+  (import "env" "print_int" (func $print_int (param i32)))
+  (import "env" "print_float" (func $print_float (param f32)))
+  (import "env" "print_string" (func $print_string (param i32 i32)))
+  (memory (export "memory") 1)
+  (data (i32.const 0) "fib(15) recursive:\0afib(n) iterative for n < 20:\0a")
+  (global $calls (mut i32) (i32.const 0))
+  (func $fib (param $n i32) (result i32)
+    global.get $calls
+    i32.const 1
+    i32.add
+    global.set $calls
+    local.get $n
+    i32.const 2
+    i32.lt_s
+    if
+      local.get $n
+      return
+    end
+    local.get $n
+    i32.const 1
+    i32.sub
+    call $fib
+    local.get $n
+    i32.const 2
+    i32.sub
+    call $fib
+    i32.add
+    return
+  )
+  (func $fibLoop (param $n i32) (result i32)
+    (local $a i32)
+    (local $b i32)
+    (local $i i32)
+    (local $next i32)
+    i32.const 0
+    local.set $a
+    i32.const 1
+    local.set $b
+    i32.const 0
+    local.set $i
+    block $exit1
+      loop $loop1
+        local.get $i
+        local.get $n
+        i32.lt_s
+        i32.eqz
+        br_if $exit1
+        local.get $a
+        local.get $b
+        i32.add
+        local.set $next
+        local.get $b
+        local.set $a
+        local.get $next
+        local.set $b
+        local.get $i
+        i32.const 1
+        i32.add
+        local.set $i
+        br $loop1
+      end
+    end
+    local.get $a
+    return
+  )
+  (func $main
+    (local $n i32)
+    (local $ratio f32)
+    i32.const 0
+    i32.const 19
+    call $print_string
+    i32.const 15
+    call $fib
+    call $print_int
+    global.get $calls
+    call $print_int
+    i32.const 19
+    i32.const 29
+    call $print_string
+    i32.const 0
+    local.set $n
+    block $exit2
+      loop $loop2
+        local.get $n
+        i32.const 20
+        i32.lt_s
+        i32.eqz
+        br_if $exit2
+        local.get $n
+        call $fibLoop
+        call $print_int
+        local.get $n
+        i32.const 1
+        i32.add
+        local.set $n
+        br $loop2
+      end
+    end
+    i32.const 30
+    call $fibLoop
+    f32.convert_i32_s
+    local.set $ratio
+    local.get $ratio
+    i32.const 29
+    call $fibLoop
+    f32.convert_i32_s
+    f32.div
+    local.set $ratio
+    local.get $ratio
+    call $print_float
+  )
+  (export "main" (func $main))
+)
@@ -0,0 +1,177 @@
+(module
+  (import "env" "print_int" (func $print_int (param i32)))
+  (import "env" "print_float" (func $print_float (param f32)))
+  (import "env" "print_bool" (func $print_bool (param i32)))
+  (import "env" "print_string" (func $print_string (param i32 i32)))
+  (memory (export "memory") 1)
+  (data (i32.const 0) "skipping a non-prime\0a")
+  (global $verbose (mut i32) (i32.const 0))
+  (func $isPrime (param $n i32) (result i32)
+    (local $d i32)
+    local.get $n
+    i32.const 2
+    i32.lt_s
+    if
+      i32.const 0
+      return
+    end
+    i32.const 2
+    local.set $d
+    block $exit1
+      loop $loop1
+        local.get $d
+        local.get $d
+        i32.mul
+        local.get $n
+        i32.le_s
+        i32.eqz
+        br_if $exit1
+        local.get $n
+        local.get $d
+        i32.rem_s
+        i32.const 0
+        i32.eq
+        if
+          i32.const 0
+          return
+        end
+        local.get $d
+        i32.const 1
+        i32.add
+        local.set $d
+        br $loop1
+      end
+    end
+    i32.const 1
+    return
+  )
+  (func $gcd (param $a i32) (param $b i32) (result i32)
+    (local $t i32)
+    block $exit2
+      loop $loop2
+        local.get $b
+        i32.const 0
+        i32.eq
+        i32.eqz
+        i32.eqz
+        br_if $exit2
+        local.get $a
+        local.get $b
+        i32.rem_s
+        local.set $t
+        local.get $b
+        local.set $a
+        local.get $t
+        local.set $b
+        br $loop2
+      end
+    end
+    local.get $a
+    return
+  )
+  (func $mean (param $sum f32) (param $count i32) (result f32)
+    local.get $sum
+    local.get $count
+    f32.convert_i32_s
+    f32.div
+    return
+  )
+  (func $main
+    (local $count i32)
+    (local $sum i32)
+    (local $n i32)
+    (local $x i32)
+    i32.const 0
+    local.set $count
+    i32.const 0
+    local.set $sum
+    i32.const 0
+    local.set $n
+    loop $loop3
+      local.get $n
+      call $isPrime
+      if (result i32)
+        global.get $verbose
+        i32.eqz
+      else
+        i32.const 0
+      end
+      if
+        local.get $count
+        i32.const 1
+        i32.add
+        local.set $count
+        local.get $sum
+        local.get $n
+        i32.add
+        local.set $sum
+      else
+        global.get $verbose
+        if (result i32)
+          i32.const 1
+        else
+          local.get $n
+          i32.const 1
+          i32.eq
+        end
+        if
+          i32.const 0
+          i32.const 21
+          call $print_string
+        end
+      end
+      local.get $n
+      i32.const 1
+      i32.add
+      local.set $n
+      local.get $n
+      i32.const 100
+      i32.le_s
+      br_if $loop3
+    end
+    local.get $count
+    call $print_int
+    local.get $sum
+    call $print_int
+    local.get $sum
+    f32.convert_i32_s
+    local.get $count
+    call $mean
+    call $print_float
+    i32.const 1071
+    i32.const 462
+    call $gcd
+    call $print_int
+    i32.const 0
+    i32.const 48
+    i32.sub
+    i32.const 18
+    call $gcd
+    call $print_int
+    i32.const 0
+    i32.const 7
+    i32.sub
+    i32.const 3
+    i32.rem_s
+    i32.const 0
+    i32.const 2
+    i32.sub
+    i32.mul
+    i32.const 1
+    i32.add
+    call $print_int
+    i32.const 2147483647
+    local.set $x
+    local.get $x
+    i32.const 1
+    i32.add
+    i32.const 0
+    local.get $x
+    i32.sub
+    i32.const 1
+    i32.sub
+    i32.eq
+    call $print_bool
+  )
+  (export "main" (func $main))
+)
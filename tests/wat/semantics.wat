@@ -0,0 +1,329 @@
+(module
+  (import "env" "print_int" (func $print_int (param i32)))
+  (import "env" "print_float" (func $print_float (param f32)))
+  (import "env" "print_bool" (func $print_bool (param i32)))
+  (import "env" "print_string" (func $print_string (param i32 i32)))
+  (memory (export "memory") 1)
+  (data (i32.const 0) "done?? \"quoted\"\09\\ end\0a")
+  (global $calls (mut i32) (i32.const 0))
+  (global $scale (mut f32) (f32.const 2.0))
+  (global $abs (mut i32) (i32.const 0))
+  (func $c1_div (param $lhs i32) (param $rhs i32) (result i32)
+    local.get $rhs
+    i32.const -1
+    i32.eq
+    if (result i32)
+      i32.const 0
+      local.get $lhs
+      i32.sub
+    else
+      local.get $lhs
+      local.get $rhs
+      i32.div_s
+    end
+  )
+  (func $trace (param $value i32) (result i32)
+    global.get $calls
+    i32.const 1
+    i32.add
+    global.set $calls
+    local.get $value
+    call $print_int
+    local.get $value
+    return
+  )
+  (func $exit (param $code i32) (result i32)
+    local.get $code
+    i32.const 10
+    i32.mul
+    return
+  )
+  (func $half (param $value f32) (result f32)
+    local.get $value
+    i32.const 2
+    f32.convert_i32_s
+    f32.div
+    return
+  )
+  (func $firstSquareAbove (param $n i32) (result i32)
+    (local $i i32)
+    i32.const 1
+    local.set $i
+    block $exit1
+      loop $loop1
+        local.get $i
+        local.get $i
+        i32.mul
+        local.get $n
+        i32.gt_s
+        if
+          local.get $i
+          return
+        end
+        local.get $i
+        i32.const 1
+        i32.add
+        local.set $i
+        br $loop1
+      end
+    end
+    unreachable
+  )
+  (func $main
+    (local $unset i32)
+    (local $x i32)
+    (local $min i32)
+    (local $zero f32)
+    (local $nan f32)
+    (local $f i32)
+    i32.const 0
+    local.set $unset
+    local.get $unset
+    call $print_int
+    i32.const 1
+    call $trace
+    i32.const 2
+    call $trace
+    i32.const 3
+    call $trace
+    i32.mul
+    i32.sub
+    call $print_int
+    i32.const 4
+    call $trace
+    i32.const 5
+    call $trace
+    i32.gt_s
+    if (result i32)
+      i32.const 1
+    else
+      i32.const 6
+      call $trace
+      i32.const 6
+      i32.eq
+    end
+    call $print_bool
+    i32.const 2147483647
+    local.set $x
+    local.get $x
+    i32.const 2
+    i32.mul
+    call $print_int
+    i32.const 0
+    local.get $x
+    i32.sub
+    i32.const 1
+    i32.sub
+    local.set $min
+    i32.const 0
+    local.get $min
+    i32.sub
+    call $print_int
+    local.get $min
+    i32.const 0
+    i32.const 1
+    i32.sub
+    call $c1_div
+    call $print_int
+    local.get $min
+    i32.const 0
+    i32.const 1
+    i32.sub
+    i32.rem_s
+    call $print_int
+    i32.const 0
+    i32.const 7
+    i32.sub
+    i32.const 2
+    call $c1_div
+    call $print_int
+    i32.const 7
+    i32.const 0
+    i32.const 3
+    i32.sub
+    i32.rem_s
+    call $print_int
+    i32.const 7
+    f32.convert_i32_s
+    call $half
+    call $print_float
+    global.get $scale
+    i32.const 3
+    f32.convert_i32_s
+    f32.mul
+    i32.const 4
+    f32.convert_i32_s
+    f32.div
+    call $print_float
+    f32.const 0.0
+    local.set $zero
+    local.get $zero
+    local.get $zero
+    f32.div
+    call $print_float
+    i32.const 1
+    f32.convert_i32_s
+    local.get $zero
+    f32.div
+    call $print_float
+    local.get $zero
+    local.get $zero
+    f32.div
+    local.set $nan
+    local.get $nan
+    i32.const 1
+    f32.convert_i32_s
+    f32.lt
+    if (result i32)
+      i32.const 1
+    else
+      local.get $nan
+      i32.const 1
+      f32.convert_i32_s
+      f32.le
+    end
+    if (result i32)
+      i32.const 1
+    else
+      local.get $nan
+      i32.const 1
+      f32.convert_i32_s
+      f32.gt
+    end
+    if (result i32)
+      i32.const 1
+    else
+      local.get $nan
+      i32.const 1
+      f32.convert_i32_s
+      f32.ge
+    end
+    if (result i32)
+      i32.const 1
+    else
+      local.get $nan
+      local.get $nan
+      f32.eq
+    end
+    call $print_bool
+    local.get $nan
+    local.get $nan
+    f32.ne
+    call $print_bool
+    i32.const 1
+    f32.convert_i32_s
+    f32.const 2.5
+    f32.lt
+    if (result i32)
+      f32.const 2.5
+      f32.const 2.5
+      f32.le
+    else
+      i32.const 0
+    end
+    if (result i32)
+      i32.const 3
+      f32.convert_i32_s
+      f32.const 2.5
+      f32.gt
+    else
+      i32.const 0
+    end
+    if (result i32)
+      f32.const 2.5
+      f32.const 2.5
+      f32.ge
+    else
+      i32.const 0
+    end
+    if (result i32)
+      f32.const 2.5
+      f32.const 2.5
+      f32.eq
+    else
+      i32.const 0
+    end
+    call $print_bool
+    i32.const 2
+    i32.const 1
+    i32.lt_s
+    if (result i32)
+      i32.const 1
+    else
+      i32.const 2
+      i32.const 1
+      i32.le_s
+    end
+    if (result i32)
+      i32.const 1
+    else
+      i32.const 1
+      i32.const 2
+      i32.gt_s
+    end
+    if (result i32)
+      i32.const 1
+    else
+      i32.const 1
+      i32.const 2
+      i32.ge_s
+    end
+    if (result i32)
+      i32.const 1
+    else
+      i32.const 1
+      i32.const 2
+      i32.eq
+    end
+    if (result i32)
+      i32.const 1
+    else
+      i32.const 1
+      i32.const 1
+      i32.ne
+    end
+    call $print_bool
+    f32.const inf
+    f32.neg
+    call $print_float
+    f32.const 0.1
+    f32.const 0.2
+    f32.add
+    f32.const 0.3
+    f32.eq
+    call $print_bool
+    f32.const 16777216.0
+    call $print_float
+    i32.const 3
+    f32.convert_i32_s
+    f32.const 3.0
+    f32.eq
+    call $print_bool
+    i32.const 4
+    call $exit
+    call $print_int
+    i32.const 50
+    call $firstSquareAbove
+    call $print_int
+    i32.const 3
+    global.set $abs
+    global.get $abs
+    call $print_int
+    i32.const 1
+    local.set $f
+    local.get $f
+    call $trace
+    i32.const 5
+    local.tee $f
+    i32.add
+    local.set $f
+    local.get $f
+    call $print_int
+    global.get $calls
+    call $print_int
+    i32.const 0
+    i32.const 22
+    call $print_string
+  )
+  (export "main" (func $main))
+)
@@ -0,0 +1,54 @@
+mod common;
+
+use cb_3::x86_64::emit;
+use cb_3::{C1Parser, Dialect};
+use common::NativeBackend;
+
+const X86_64: NativeBackend = NativeBackend {
+    name: "x86_64",
+    extension: "s",
+    emit,
+    flags: &[],
+    supported: cfg!(all(target_arch = "x86_64", target_os = "linux")),
+};
+
+#[test]
+fn native_code_matches_interpreter() {
+    X86_64.assert_data_matches_interpreter();
+}
+
+#[test]
+fn arguments() {
+    // More arguments than registers, mixed types and calls nested in operands, which have to
+    // align the stack before printing floats
+    let text = "
+float mix(int a, float b, bool c, int d, int e, float f, int g, int h, int i, float j,
+          float k, float l, float m, float n, float o, bool p, float q, int r) {
+    printf(a - d + e - g + h - i + r);
+    printf(b + f + j + k + l + m + n + o + q);
+    printf(c && !p);
+    return q - r;
+}
+
+float half(float x) { printf(x); return x / 2; }
+
+int main() {
+    printf(1 + half(3));
+    printf(1 + (2 + half(half(5))));
+    x = mix(1, 2, true, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, false, 17.5, 18);
+    printf(x);
+    return 3 + 4 * 10;
+}";
+    let program = C1Parser::parse_program_in(text, Dialect::C1).unwrap();
+    X86_64.assert_matches_interpreter(&program, "arguments");
+}
+
+#[test]
+fn deep_recursion() {
+    X86_64.assert_deep_recursion();
+}
+
+#[test]
+fn division_by_zero() {
+    X86_64.assert_division_by_zero("%");
+}